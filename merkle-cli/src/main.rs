@@ -0,0 +1,248 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+use merkle_tree_lib::{LeafEncode, MerkleProof, MerkleTree, NodeLabel, RenderOptions};
+
+/// Builds Merkle Trees from the lines of a file and generates and verifies inclusion proofs.
+///
+/// Every non empty line of the input is one leaf, hashed as it is without the line break.
+/// The default tags match the proof of reserve app, but the app salts every leaf and the CLI
+/// hashes the lines unsalted, so `id,balance` lines do not reproduce the root of the server.
+#[derive(Parser)]
+#[command(name = "merkle-cli", version)]
+struct Cli {
+    /// The tag used for hashing leaf nodes.
+    #[arg(long, global = true, default_value = "ProofOfReserve_Leaf")]
+    tag_leaf: String,
+
+    /// The tag used for hashing branch nodes.
+    #[arg(long, global = true, default_value = "ProofOfReserve_Branch")]
+    tag_branch: String,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Prints the hex encoded root hash.
+    Root {
+        /// The file with the leaves, stdin if omitted.
+        file: Option<PathBuf>,
+    },
+    /// Prints the inclusion proof of a leaf as JSON.
+    Prove {
+        /// The position of the leaf, starting at 0.
+        #[arg(long, conflicts_with = "key", required_unless_present = "key")]
+        index: Option<usize>,
+        /// The content of the leaf, the first matching line is proven.
+        #[arg(long)]
+        key: Option<String>,
+        /// The file with the leaves, stdin if omitted.
+        file: Option<PathBuf>,
+    },
+    /// Verifies a JSON proof, exits with 1 if it does not check out.
+    Verify {
+        /// The content of the proven leaf.
+        #[arg(long)]
+        leaf: String,
+        /// The hex encoded root hash to verify against.
+        #[arg(long)]
+        root: String,
+        /// The file with the JSON proof, stdin if omitted.
+        proof: Option<PathBuf>,
+    },
+    /// Renders the tree as indented text, a Mermaid diagram or a Graphviz DOT digraph.
+    Render {
+        #[arg(long, conflicts_with = "dot")]
+        mermaid: bool,
+        #[arg(long)]
+        dot: bool,
+        /// The number of levels rendered below the starting node.
+        #[arg(long)]
+        max_depth: Option<u32>,
+        /// The hex encoded hash of the node to start rendering from instead of the root.
+        #[arg(long)]
+        subtree: Option<String>,
+        /// Prints node hashes in full instead of shortened.
+        #[arg(long)]
+        full_hashes: bool,
+        /// The file with the leaves, stdin if omitted.
+        file: Option<PathBuf>,
+    },
+}
+
+/// A line of the input file.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Line(String);
+
+impl LeafEncode for Line {
+    fn serialize(&self) -> Vec<u8> {
+        self.0.as_bytes().to_vec()
+    }
+}
+
+impl NodeLabel for Line {
+    fn mermaid_node_label(&self) -> String {
+        format!("<br>{}", self.0)
+    }
+}
+
+fn open_input(file: Option<&PathBuf>) -> Result<Box<dyn Read>, String> {
+    match file {
+        Some(path) => File::open(path)
+            .map(|file| Box::new(file) as Box<dyn Read>)
+            .map_err(|err| format!("failed to open {}: {err}", path.display())),
+        None => Ok(Box::new(io::stdin())),
+    }
+}
+
+/// Builds a tree from the non empty lines of the input, hashing them while they are read.
+fn build_tree(cli: &Cli, input: impl Read) -> Result<MerkleTree<Line>, String> {
+    let mut error = None;
+    let leaves = BufReader::new(input)
+        .lines()
+        .map_while(|line| line.map_err(|err| error = Some(err)).ok())
+        .filter(|line| !line.is_empty())
+        .map(Line);
+    let tree = MerkleTree::build_from_iter(&cli.tag_leaf, &cli.tag_branch, leaves);
+
+    match error {
+        Some(err) => Err(format!("failed to read leaves: {err}")),
+        None => Ok(tree),
+    }
+}
+
+/// Runs a command and writes its result to `output`.
+///
+/// # Returns
+///
+/// A `Result` containing `false` if a proof did not verify, an error message if the command failed.
+fn run(cli: &Cli, output: &mut impl Write) -> Result<bool, String> {
+    let build = |file: Option<&PathBuf>| build_tree(cli, open_input(file)?);
+
+    let text = match &cli.command {
+        Command::Root { file } => build(file.as_ref())?
+            .root()
+            .ok_or("no leaves in the input")?,
+        Command::Prove { index, key, file } => {
+            let tree = build(file.as_ref())?;
+            let proof = match (index, key) {
+                (Some(index), _) => tree.proof(*index).map_err(|err| err.to_string())?,
+                (None, key) => tree
+                    .search_with_path(|line| Some(&line.0) == key.as_ref())
+                    .ok_or("no leaf matches the key")?
+                    .proof(),
+            };
+
+            serde_json::to_string_pretty(&proof).map_err(|err| err.to_string())?
+        }
+        Command::Verify { leaf, root, proof } => {
+            let proof: MerkleProof = serde_json::from_reader(open_input(proof.as_ref())?)
+                .map_err(|err| format!("invalid proof: {err}"))?;
+            let valid = proof.verify(&cli.tag_leaf, &cli.tag_branch, &Line(leaf.clone()), root);
+
+            writeln!(output, "{}", if valid { "valid" } else { "invalid" })
+                .map_err(|err| err.to_string())?;
+            return Ok(valid);
+        }
+        Command::Render {
+            mermaid,
+            dot,
+            max_depth,
+            subtree,
+            full_hashes,
+            file,
+        } => {
+            let tree = build(file.as_ref())?;
+            let options = RenderOptions {
+                max_depth: *max_depth,
+                subtree_root: subtree.clone(),
+                collapse_hashes: !full_hashes,
+            };
+
+            if *mermaid {
+                tree.display_mermaid_diagram_with(&options)
+            } else if *dot {
+                tree.display_dot_with(&options)
+            } else {
+                tree.display_tree_with(&options)
+            }
+        }
+    };
+
+    writeln!(output, "{text}").map_err(|err| err.to_string())?;
+    Ok(true)
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match run(&cli, &mut io::stdout().lock()) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("merkle-cli: {err}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_args(args: &[&str]) -> (Result<bool, String>, String) {
+        let cli = Cli::parse_from(["merkle-cli"].iter().chain(args));
+        let mut output = Vec::new();
+        let result = run(&cli, &mut output);
+
+        (result, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn it_can_read_leaves_without_empty_lines() {
+        let cli = Cli::parse_from(["merkle-cli", "root"]);
+        let tree = build_tree(&cli, "1,1111\n\n2,2222\n".as_bytes()).unwrap();
+        let leaves: Vec<Line> = tree.leaves().map(|(_, line, _)| line.clone()).collect();
+
+        assert_eq!(
+            leaves,
+            vec![Line("1,1111".to_string()), Line("2,2222".to_string())]
+        );
+    }
+
+    #[test]
+    fn it_can_prove_and_verify_a_leaf() {
+        let dir = std::env::temp_dir().join(format!("merkle-cli-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let leaves = dir.join("leaves.txt");
+        let proof = dir.join("proof.json");
+        std::fs::write(&leaves, "aaa\nbbb\nccc\nddd\neee\n").unwrap();
+        let leaves = leaves.to_str().unwrap();
+
+        let (_, root) = run_args(&["root", leaves]);
+        let (_, by_index) = run_args(&["prove", "--index", "2", leaves]);
+        let (_, by_key) = run_args(&["prove", "--key", "ccc", leaves]);
+        assert_eq!(by_index, by_key);
+
+        std::fs::write(&proof, by_index).unwrap();
+        let proof = proof.to_str().unwrap();
+        let root = root.trim();
+
+        assert_eq!(
+            run_args(&["verify", "--leaf", "ccc", "--root", root, proof]),
+            (Ok(true), "valid\n".to_string())
+        );
+        assert_eq!(
+            run_args(&["verify", "--leaf", "ddd", "--root", root, proof]),
+            (Ok(false), "invalid\n".to_string())
+        );
+        assert!(run_args(&["prove", "--index", "5", leaves]).0.is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
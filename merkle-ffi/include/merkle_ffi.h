@@ -0,0 +1,150 @@
+/* Declares the functions of merkle-ffi/src/lib.rs, regenerate with `cbindgen --config cbindgen.toml --output include/merkle_ffi.h`. */
+
+#ifndef MERKLE_FFI_H
+#define MERKLE_FFI_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+// The outcome of a call.
+typedef enum {
+  MERKLE_STATUS_OK = 0,
+  // A required pointer is null.
+  MERKLE_STATUS_NULL_POINTER = 1,
+  // A tag is not valid UTF-8.
+  MERKLE_STATUS_INVALID_UTF8 = 2,
+  // The tree has no leaves, so it has no root.
+  MERKLE_STATUS_EMPTY_TREE = 3,
+  // There is no leaf at the given position.
+  MERKLE_STATUS_INDEX_OUT_OF_RANGE = 4,
+  // The output buffer is too small, the required length has been written.
+  MERKLE_STATUS_BUFFER_TOO_SMALL = 5,
+} MerkleStatus;
+
+// A tree built by `merkle_tree_build`, opaque to C.
+typedef struct MerkleTreeHandle MerkleTreeHandle;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Builds a tree from raw byte leaves.
+//
+// # Arguments
+//
+// * `tag_leaf`: The NUL terminated tag used for hashing leaf nodes.
+// * `tag_branch`: The NUL terminated tag used for hashing branch nodes.
+// * `leaves`: `leaf_count` pointers to the bytes of the leaves, in order.
+// * `leaf_lens`: `leaf_count` lengths of the leaves.
+// * `leaf_count`: The number of leaves, `leaves` and `leaf_lens` may be null if it is 0.
+// * `out_tree`: Receives the tree, which must be released with `merkle_tree_free`.
+//
+// # Safety
+//
+// The tags must be NUL terminated strings, `leaves` and `leaf_lens` must point to
+// `leaf_count` elements each and every leaf to as many bytes as its length says.
+// `out_tree` must be valid for a write. The leaves are copied, they may be freed afterwards.
+MerkleStatus merkle_tree_build(const char *tag_leaf,
+                               const char *tag_branch,
+                               const uint8_t *const *leaves,
+                               const size_t *leaf_lens,
+                               size_t leaf_count,
+                               MerkleTreeHandle **out_tree);
+
+// Releases a tree built by `merkle_tree_build`. Does nothing for null.
+//
+// # Safety
+//
+// `tree` must be null or a tree returned by `merkle_tree_build` that was not released yet.
+void merkle_tree_free(MerkleTreeHandle *tree);
+
+// Returns the number of leaves of a tree, 0 for null.
+//
+// # Safety
+//
+// `tree` must be null or a live tree returned by `merkle_tree_build`.
+size_t merkle_tree_len(const MerkleTreeHandle *tree);
+
+// Returns the number of levels below the root, which is the length of every proof of the
+// tree, 0 for null.
+//
+// # Safety
+//
+// `tree` must be null or a live tree returned by `merkle_tree_build`.
+uint32_t merkle_tree_depth(const MerkleTreeHandle *tree);
+
+// Writes the root hash of a tree.
+//
+// # Arguments
+//
+// * `tree`: The tree.
+// * `out_root`: Receives the 32 byte root hash.
+//
+// # Safety
+//
+// `tree` must be null or a live tree returned by `merkle_tree_build`, `out_root` must be
+// valid for writing 32 bytes.
+MerkleStatus merkle_tree_root(const MerkleTreeHandle *tree, uint8_t *out_root);
+
+// Writes the inclusion proof of a leaf as its sibling hashes and their directions, from the
+// leaf up to the root.
+//
+// # Arguments
+//
+// * `tree`: The tree.
+// * `index`: The position of the leaf, starting at 0.
+// * `out_hashes`: Receives the 32 byte sibling hashes, one after the other.
+// * `out_directions`: Receives the side every sibling sits on, 0 for left and 1 for right.
+// * `capacity`: The number of siblings the output buffers have room for, `merkle_tree_depth`
+//   is always enough.
+// * `out_len`: Receives the number of siblings, also when the buffers are too small.
+//
+// # Safety
+//
+// `tree` must be null or a live tree returned by `merkle_tree_build`. `out_hashes` must be
+// valid for writing `32 * capacity` bytes, `out_directions` for `capacity` bytes and
+// `out_len` for a write.
+MerkleStatus merkle_tree_proof(const MerkleTreeHandle *tree,
+                               size_t index,
+                               uint8_t *out_hashes,
+                               uint8_t *out_directions,
+                               size_t capacity,
+                               size_t *out_len);
+
+// Verifies an inclusion proof written by `merkle_tree_proof`, without the tree.
+//
+// # Arguments
+//
+// * `tag_leaf`: The NUL terminated tag used for hashing leaf nodes.
+// * `tag_branch`: The NUL terminated tag used for hashing branch nodes.
+// * `leaf`: The bytes of the proven leaf.
+// * `leaf_len`: The length of the leaf.
+// * `hashes`: The 32 byte sibling hashes, one after the other.
+// * `directions`: The side every sibling sits on, 0 for left and 1 for right.
+// * `proof_len`: The number of siblings.
+// * `root`: The 32 byte root hash to verify against.
+//
+// # Returns
+//
+// `true` if the proof links the leaf to the root, `false` if it does not or an argument
+// is invalid.
+//
+// # Safety
+//
+// The tags must be NUL terminated strings, `leaf` must point to `leaf_len` bytes, `hashes`
+// to `32 * proof_len` bytes, `directions` to `proof_len` bytes and `root` to 32 bytes.
+bool merkle_proof_verify(const char *tag_leaf,
+                         const char *tag_branch,
+                         const uint8_t *leaf,
+                         size_t leaf_len,
+                         const uint8_t *hashes,
+                         const uint8_t *directions,
+                         size_t proof_len,
+                         const uint8_t *root);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* MERKLE_FFI_H */
@@ -0,0 +1,505 @@
+//! A C interface to the Merkle Trees of `merkle-tree-lib`, for systems that are not written
+//! in Rust.
+//!
+//! Leaves are raw byte strings, hashed as they are with the leaf tag, so a tree built here
+//! has the same root as `MerkleTree::build` and the CLI compute for the same leaves. Trees
+//! use the default domain separation and odd leaf policy.
+//!
+//! Every function checks its pointers for null and reports the outcome as a `MerkleStatus`.
+//! Hashes are passed as raw 32 byte arrays. The declarations are in `include/merkle_ffi.h`.
+
+use std::ffi::{c_char, CStr};
+use std::slice;
+
+use merkle_tree_lib::{tagged_hash, LeafEncode, MerkleProof, MerkleTree, NodeDirection};
+
+/// The length of every hash passed through the interface.
+const HASH_LEN: usize = 32;
+
+/// The outcome of a call.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MerkleStatus {
+    Ok = 0,
+    /// A required pointer is null.
+    NullPointer = 1,
+    /// A tag is not valid UTF-8.
+    InvalidUtf8 = 2,
+    /// The tree has no leaves, so it has no root.
+    EmptyTree = 3,
+    /// There is no leaf at the given position.
+    IndexOutOfRange = 4,
+    /// The output buffer is too small, the required length has been written.
+    BufferTooSmall = 5,
+}
+
+/// A leaf of raw bytes.
+#[derive(Debug, Clone, Default)]
+struct ByteLeaf(Vec<u8>);
+
+impl LeafEncode for ByteLeaf {
+    fn serialize(&self) -> Vec<u8> {
+        self.0.clone()
+    }
+}
+
+/// A tree built by `merkle_tree_build`, opaque to C.
+pub struct MerkleTreeHandle(MerkleTree<ByteLeaf>);
+
+/// Borrows a slice from a pointer and a length, a null pointer is fine for an empty slice.
+unsafe fn borrow_slice<'a, T>(ptr: *const T, len: usize) -> Result<&'a [T], MerkleStatus> {
+    match (ptr.is_null(), len) {
+        (_, 0) => Ok(&[]),
+        (true, _) => Err(MerkleStatus::NullPointer),
+        (false, _) => Ok(slice::from_raw_parts(ptr, len)),
+    }
+}
+
+/// Borrows a tag from a NUL terminated string.
+unsafe fn borrow_tag<'a>(tag: *const c_char) -> Result<&'a str, MerkleStatus> {
+    if tag.is_null() {
+        return Err(MerkleStatus::NullPointer);
+    }
+
+    CStr::from_ptr(tag)
+        .to_str()
+        .map_err(|_| MerkleStatus::InvalidUtf8)
+}
+
+unsafe fn build(
+    tag_leaf: *const c_char,
+    tag_branch: *const c_char,
+    leaves: *const *const u8,
+    leaf_lens: *const usize,
+    leaf_count: usize,
+) -> Result<MerkleTree<ByteLeaf>, MerkleStatus> {
+    let (tag_leaf, tag_branch) = (borrow_tag(tag_leaf)?, borrow_tag(tag_branch)?);
+    let leaves = borrow_slice(leaves, leaf_count)?
+        .iter()
+        .zip(borrow_slice(leaf_lens, leaf_count)?)
+        .map(|(&leaf, &len)| borrow_slice(leaf, len).map(|bytes| ByteLeaf(bytes.to_vec())))
+        .collect::<Result<Vec<ByteLeaf>, MerkleStatus>>()?;
+
+    Ok(MerkleTree::build_from_iter(tag_leaf, tag_branch, leaves))
+}
+
+/// Builds a tree from raw byte leaves.
+///
+/// # Arguments
+///
+/// * `tag_leaf`: The NUL terminated tag used for hashing leaf nodes.
+/// * `tag_branch`: The NUL terminated tag used for hashing branch nodes.
+/// * `leaves`: `leaf_count` pointers to the bytes of the leaves, in order.
+/// * `leaf_lens`: `leaf_count` lengths of the leaves.
+/// * `leaf_count`: The number of leaves, `leaves` and `leaf_lens` may be null if it is 0.
+/// * `out_tree`: Receives the tree, which must be released with `merkle_tree_free`.
+///
+/// # Safety
+///
+/// The tags must be NUL terminated strings, `leaves` and `leaf_lens` must point to
+/// `leaf_count` elements each and every leaf to as many bytes as its length says.
+/// `out_tree` must be valid for a write. The leaves are copied, they may be freed afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn merkle_tree_build(
+    tag_leaf: *const c_char,
+    tag_branch: *const c_char,
+    leaves: *const *const u8,
+    leaf_lens: *const usize,
+    leaf_count: usize,
+    out_tree: *mut *mut MerkleTreeHandle,
+) -> MerkleStatus {
+    if out_tree.is_null() {
+        return MerkleStatus::NullPointer;
+    }
+
+    match build(tag_leaf, tag_branch, leaves, leaf_lens, leaf_count) {
+        Ok(tree) => {
+            *out_tree = Box::into_raw(Box::new(MerkleTreeHandle(tree)));
+            MerkleStatus::Ok
+        }
+        Err(status) => status,
+    }
+}
+
+/// Releases a tree built by `merkle_tree_build`. Does nothing for null.
+///
+/// # Safety
+///
+/// `tree` must be null or a tree returned by `merkle_tree_build` that was not released yet.
+#[no_mangle]
+pub unsafe extern "C" fn merkle_tree_free(tree: *mut MerkleTreeHandle) {
+    if !tree.is_null() {
+        drop(Box::from_raw(tree));
+    }
+}
+
+/// Returns the number of leaves of a tree, 0 for null.
+///
+/// # Safety
+///
+/// `tree` must be null or a live tree returned by `merkle_tree_build`.
+#[no_mangle]
+pub unsafe extern "C" fn merkle_tree_len(tree: *const MerkleTreeHandle) -> usize {
+    tree.as_ref().map_or(0, |tree| tree.0.len())
+}
+
+/// Returns the number of levels below the root, which is the length of every proof of the
+/// tree, 0 for null.
+///
+/// # Safety
+///
+/// `tree` must be null or a live tree returned by `merkle_tree_build`.
+#[no_mangle]
+pub unsafe extern "C" fn merkle_tree_depth(tree: *const MerkleTreeHandle) -> u32 {
+    tree.as_ref().map_or(0, |tree| tree.0.depth())
+}
+
+/// Writes the root hash of a tree.
+///
+/// # Arguments
+///
+/// * `tree`: The tree.
+/// * `out_root`: Receives the 32 byte root hash.
+///
+/// # Safety
+///
+/// `tree` must be null or a live tree returned by `merkle_tree_build`, `out_root` must be
+/// valid for writing 32 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn merkle_tree_root(
+    tree: *const MerkleTreeHandle,
+    out_root: *mut u8,
+) -> MerkleStatus {
+    let Some(tree) = tree.as_ref() else {
+        return MerkleStatus::NullPointer;
+    };
+    if out_root.is_null() {
+        return MerkleStatus::NullPointer;
+    }
+
+    let Some(root) = tree.0.root() else {
+        return MerkleStatus::EmptyTree;
+    };
+    let root = hex::decode(root).expect("roots are hex encoded");
+    slice::from_raw_parts_mut(out_root, HASH_LEN).copy_from_slice(&root);
+
+    MerkleStatus::Ok
+}
+
+/// Writes the inclusion proof of a leaf as its sibling hashes and their directions, from the
+/// leaf up to the root.
+///
+/// # Arguments
+///
+/// * `tree`: The tree.
+/// * `index`: The position of the leaf, starting at 0.
+/// * `out_hashes`: Receives the 32 byte sibling hashes, one after the other.
+/// * `out_directions`: Receives the side every sibling sits on, 0 for left and 1 for right.
+/// * `capacity`: The number of siblings the output buffers have room for, `merkle_tree_depth`
+///   is always enough.
+/// * `out_len`: Receives the number of siblings, also when the buffers are too small.
+///
+/// # Safety
+///
+/// `tree` must be null or a live tree returned by `merkle_tree_build`. `out_hashes` must be
+/// valid for writing `32 * capacity` bytes, `out_directions` for `capacity` bytes and
+/// `out_len` for a write.
+#[no_mangle]
+pub unsafe extern "C" fn merkle_tree_proof(
+    tree: *const MerkleTreeHandle,
+    index: usize,
+    out_hashes: *mut u8,
+    out_directions: *mut u8,
+    capacity: usize,
+    out_len: *mut usize,
+) -> MerkleStatus {
+    let Some(tree) = tree.as_ref() else {
+        return MerkleStatus::NullPointer;
+    };
+    if out_len.is_null() {
+        return MerkleStatus::NullPointer;
+    }
+
+    let Ok(proof) = tree.0.proof(index) else {
+        return MerkleStatus::IndexOutOfRange;
+    };
+    let len = proof.hashes.len();
+    *out_len = len;
+    if len > capacity {
+        return MerkleStatus::BufferTooSmall;
+    }
+    if len == 0 {
+        return MerkleStatus::Ok;
+    }
+    if out_hashes.is_null() || out_directions.is_null() {
+        return MerkleStatus::NullPointer;
+    }
+
+    let hashes = slice::from_raw_parts_mut(out_hashes, len * HASH_LEN);
+    let directions = slice::from_raw_parts_mut(out_directions, len);
+    for (step, (hash, direction)) in proof.hashes.iter().zip(&proof.directions).enumerate() {
+        let hash = hex::decode(hash).expect("proof hashes are hex encoded");
+        hashes[step * HASH_LEN..(step + 1) * HASH_LEN].copy_from_slice(&hash);
+        directions[step] = match direction {
+            NodeDirection::Left => 0,
+            _ => 1,
+        };
+    }
+
+    MerkleStatus::Ok
+}
+
+unsafe fn verify(
+    tag_leaf: *const c_char,
+    tag_branch: *const c_char,
+    leaf: &[u8],
+    hashes: &[u8],
+    directions: &[u8],
+    root: *const u8,
+) -> Result<bool, MerkleStatus> {
+    let (tag_leaf, tag_branch) = (borrow_tag(tag_leaf)?, borrow_tag(tag_branch)?);
+    let root = borrow_slice(root, HASH_LEN)?;
+
+    let mut proof = MerkleProof {
+        leaf_index: 0,
+        hashes: hashes.chunks(HASH_LEN).map(hex::encode).collect(),
+        directions: Vec::with_capacity(directions.len()),
+    };
+    for &direction in directions {
+        proof.directions.push(match direction {
+            0 => NodeDirection::Left,
+            1 => NodeDirection::Right,
+            _ => return Ok(false),
+        });
+    }
+
+    Ok(proof
+        .compute_root(tag_branch, &tagged_hash(tag_leaf, leaf))
+        .is_some_and(|computed| computed == root))
+}
+
+/// Verifies an inclusion proof written by `merkle_tree_proof`, without the tree.
+///
+/// # Arguments
+///
+/// * `tag_leaf`: The NUL terminated tag used for hashing leaf nodes.
+/// * `tag_branch`: The NUL terminated tag used for hashing branch nodes.
+/// * `leaf`: The bytes of the proven leaf.
+/// * `leaf_len`: The length of the leaf.
+/// * `hashes`: The 32 byte sibling hashes, one after the other.
+/// * `directions`: The side every sibling sits on, 0 for left and 1 for right.
+/// * `proof_len`: The number of siblings.
+/// * `root`: The 32 byte root hash to verify against.
+///
+/// # Returns
+///
+/// `true` if the proof links the leaf to the root, `false` if it does not or an argument
+/// is invalid.
+///
+/// # Safety
+///
+/// The tags must be NUL terminated strings, `leaf` must point to `leaf_len` bytes, `hashes`
+/// to `32 * proof_len` bytes, `directions` to `proof_len` bytes and `root` to 32 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn merkle_proof_verify(
+    tag_leaf: *const c_char,
+    tag_branch: *const c_char,
+    leaf: *const u8,
+    leaf_len: usize,
+    hashes: *const u8,
+    directions: *const u8,
+    proof_len: usize,
+    root: *const u8,
+) -> bool {
+    let (Ok(leaf), Ok(hashes), Ok(directions)) = (
+        borrow_slice(leaf, leaf_len),
+        borrow_slice(hashes, proof_len * HASH_LEN),
+        borrow_slice(directions, proof_len),
+    ) else {
+        return false;
+    };
+
+    verify(tag_leaf, tag_branch, leaf, hashes, directions, root).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use std::ptr;
+
+    const TAG_LEAF: &CStr = c"ProofOfReserve_Leaf";
+    const TAG_BRANCH: &CStr = c"ProofOfReserve_Branch";
+
+    fn build_tree(leaves: &[&[u8]]) -> *mut MerkleTreeHandle {
+        let pointers: Vec<*const u8> = leaves.iter().map(|leaf| leaf.as_ptr()).collect();
+        let lens: Vec<usize> = leaves.iter().map(|leaf| leaf.len()).collect();
+        let mut tree = ptr::null_mut();
+
+        let status = unsafe {
+            merkle_tree_build(
+                TAG_LEAF.as_ptr(),
+                TAG_BRANCH.as_ptr(),
+                pointers.as_ptr(),
+                lens.as_ptr(),
+                leaves.len(),
+                &mut tree,
+            )
+        };
+        assert_eq!(status, MerkleStatus::Ok);
+
+        tree
+    }
+
+    #[rstest]
+    #[case(1)]
+    #[case(5)]
+    #[case(8)]
+    fn it_can_prove_and_verify_every_leaf(#[case] n: usize) {
+        let leaves: Vec<Vec<u8>> = (1..=n)
+            .map(|i| format!("{i},{}", i * 1111).into_bytes())
+            .collect();
+        let borrowed: Vec<&[u8]> = leaves.iter().map(Vec::as_slice).collect();
+        let tree = build_tree(&borrowed);
+
+        let mut root = [0; HASH_LEN];
+        unsafe {
+            assert_eq!(merkle_tree_len(tree), n);
+            assert_eq!(merkle_tree_root(tree, root.as_mut_ptr()), MerkleStatus::Ok);
+        }
+        let expected: Vec<ByteLeaf> = leaves.iter().cloned().map(ByteLeaf).collect();
+        let expected = MerkleTree::build("ProofOfReserve_Leaf", "ProofOfReserve_Branch", &expected);
+        assert_eq!(Some(hex::encode(root)), expected.root());
+
+        let depth = unsafe { merkle_tree_depth(tree) } as usize;
+        for (index, leaf) in leaves.iter().enumerate() {
+            let mut hashes = vec![0; depth * HASH_LEN];
+            let mut directions = vec![0; depth];
+            let mut len = 0;
+            let valid = unsafe {
+                assert_eq!(
+                    merkle_tree_proof(
+                        tree,
+                        index,
+                        hashes.as_mut_ptr(),
+                        directions.as_mut_ptr(),
+                        depth,
+                        &mut len
+                    ),
+                    MerkleStatus::Ok
+                );
+                let verify = |leaf: &[u8]| {
+                    merkle_proof_verify(
+                        TAG_LEAF.as_ptr(),
+                        TAG_BRANCH.as_ptr(),
+                        leaf.as_ptr(),
+                        leaf.len(),
+                        hashes.as_ptr(),
+                        directions.as_ptr(),
+                        len,
+                        root.as_ptr(),
+                    )
+                };
+                assert!(!verify(b"0,0"));
+                verify(leaf)
+            };
+            assert_eq!(len, depth);
+            assert!(valid);
+        }
+
+        unsafe { merkle_tree_free(tree) };
+    }
+
+    #[test]
+    fn it_can_report_invalid_arguments() {
+        let tree = build_tree(&[b"1,1111", b"2,2222", b"3,3333"]);
+        let mut root = [0; HASH_LEN];
+        let mut hashes = [0; HASH_LEN];
+        let mut directions = [0; 1];
+        let mut len = 0;
+
+        unsafe {
+            assert_eq!(
+                merkle_tree_root(ptr::null(), root.as_mut_ptr()),
+                MerkleStatus::NullPointer
+            );
+            assert_eq!(
+                merkle_tree_proof(
+                    tree,
+                    3,
+                    hashes.as_mut_ptr(),
+                    directions.as_mut_ptr(),
+                    1,
+                    &mut len
+                ),
+                MerkleStatus::IndexOutOfRange
+            );
+            assert_eq!(
+                merkle_tree_proof(
+                    tree,
+                    0,
+                    hashes.as_mut_ptr(),
+                    directions.as_mut_ptr(),
+                    1,
+                    &mut len
+                ),
+                MerkleStatus::BufferTooSmall
+            );
+            assert_eq!(len, 2);
+            merkle_tree_free(tree);
+
+            let empty = build_tree(&[]);
+            assert_eq!(merkle_tree_len(empty), 0);
+            assert_eq!(
+                merkle_tree_root(empty, root.as_mut_ptr()),
+                MerkleStatus::EmptyTree
+            );
+            merkle_tree_free(empty);
+
+            let mut tree = ptr::null_mut();
+            let invalid = [0xff, 0];
+            assert_eq!(
+                merkle_tree_build(
+                    invalid.as_ptr().cast(),
+                    TAG_BRANCH.as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    0,
+                    &mut tree
+                ),
+                MerkleStatus::InvalidUtf8
+            );
+            assert_eq!(
+                merkle_tree_build(
+                    TAG_LEAF.as_ptr(),
+                    TAG_BRANCH.as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    1,
+                    &mut tree
+                ),
+                MerkleStatus::NullPointer
+            );
+            assert!(tree.is_null());
+            merkle_tree_free(tree);
+        }
+    }
+
+    #[test]
+    fn it_can_declare_every_function_in_the_header() {
+        let header = include_str!("../include/merkle_ffi.h");
+        let functions: Vec<&str> = include_str!("lib.rs")
+            .lines()
+            .filter_map(|line| line.strip_prefix("pub unsafe extern \"C\" fn "))
+            .filter_map(|line| line.split('(').next())
+            .collect();
+
+        assert_eq!(functions.len(), 7);
+        for function in functions {
+            assert!(
+                header.contains(&format!(" {function}(")),
+                "{function} is not declared"
+            );
+        }
+    }
+}
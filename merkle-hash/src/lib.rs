@@ -0,0 +1,153 @@
+//! The hashing schemes shared by every crate of the workspace.
+//!
+//! Roots computed by the library, the app and the CLI are compared with each other, so the
+//! digests are defined once here and nowhere else.
+
+#[cfg(feature = "hmac")]
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+#[cfg(feature = "keccak")]
+use sha3::Keccak256;
+
+/// A 32 byte SHA-256 digest.
+pub type Hash = [u8; 32];
+
+/// Calculates tagged hashes for one tag.
+///
+/// The tag is hashed once when the hasher is created instead of once per input, which
+/// matters when the same tag is used for every node of a tree. The two tag hashes fill
+/// exactly one SHA-256 block, so the hasher also keeps the state after compressing it and
+/// every input starts from there.
+#[derive(Debug, Clone)]
+pub struct TagHasher {
+    midstate: Sha256,
+}
+
+impl TagHasher {
+    /// Creates a hasher for the given tag.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag`: The tag string.
+    pub fn new(tag: &str) -> Self {
+        let tag_hash = Sha256::digest(tag.as_bytes());
+        let mut midstate = Sha256::new();
+        midstate.update(tag_hash);
+        midstate.update(tag_hash);
+
+        TagHasher { midstate }
+    }
+
+    /// Calculates the tagged hash of the input, see `tagged_hash`.
+    pub fn hash(&self, input: &[u8]) -> Vec<u8> {
+        self.hash_parts(&[input])
+    }
+
+    /// Calculates the tagged hash of the concatenation of the parts, without copying them
+    /// into one buffer first.
+    pub fn hash_parts(&self, parts: &[&[u8]]) -> Vec<u8> {
+        let mut hasher = self.midstate.clone();
+        for part in parts {
+            hasher.update(part);
+        }
+        hasher.finalize().to_vec()
+    }
+}
+
+/// Calculates a tagged hash using SHA256.
+///
+/// This function takes a tag and an input byte slice, calculates the SHA256 hash of the tag,
+/// then calculates the SHA256 hash of the concatenation of the tag's hash (twice) and the input.
+///
+/// # Arguments
+///
+/// * `tag`: The tag string.
+/// * `input`: The input byte slice.
+///
+/// # Returns
+///
+/// The tagged SHA256 hash as a `Vec<u8>`.
+pub fn tagged_hash(tag: &str, input: &[u8]) -> Vec<u8> {
+    TagHasher::new(tag).hash(input)
+}
+
+/// Calculates `SHA256(SHA256(input))`, the hash Bitcoin combines its Merkle nodes with.
+pub fn double_sha256(input: &[u8]) -> Hash {
+    Sha256::digest(Sha256::digest(input)).into()
+}
+
+/// Calculates `keccak256(input)`, the hash of the EVM and of OpenZeppelin's Merkle proofs.
+#[cfg(feature = "keccak")]
+pub fn keccak256(input: &[u8]) -> Hash {
+    Keccak256::digest(input).into()
+}
+
+/// Calculates `HMAC-SHA256(key, input)`.
+///
+/// Used to derive the salt of a leaf from a secret key, so salts never have to be drawn
+/// from a random source or stored apart from the tree.
+#[cfg(feature = "hmac")]
+pub fn hmac_sha256(key: &[u8], input: &[u8]) -> Hash {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(input);
+    mac.finalize().into_bytes().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(
+        "Bitcoin_Transaction",
+        "aaa",
+        "d2d838724571ff750eb7f498a667c32f522efae2b403eae6f678207ac6f978de"
+    )]
+    #[case(
+        "hello",
+        "aaa",
+        "aa7deacc6231c611d10b4a2b14bec43c30251b977610fd5a322550003f2b216b"
+    )]
+    fn it_can_tagged_hash(#[case] tag: &str, #[case] input: &str, #[case] expected: &str) {
+        assert_eq!(hex::encode(tagged_hash(tag, input.as_bytes())), expected);
+        assert_eq!(
+            hex::encode(TagHasher::new(tag).hash(input.as_bytes())),
+            expected
+        );
+        let (head, tail) = input.as_bytes().split_at(1);
+        assert_eq!(
+            hex::encode(TagHasher::new(tag).hash_parts(&[head, &[], tail])),
+            expected
+        );
+    }
+
+    #[test]
+    fn it_can_double_sha256() {
+        assert_eq!(
+            hex::encode(double_sha256(b"")),
+            "5df6e0e2761359d30a8275058e299fcc0381534545f55cf43e41983f5d4c9456"
+        );
+    }
+
+    #[cfg(feature = "keccak")]
+    #[rstest]
+    #[case("", "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470")]
+    #[case(
+        "hello",
+        "1c8aff950685c2ed4bc3174f3472287b56d9517b9c948127319a09a7a36deac8"
+    )]
+    fn it_can_keccak256(#[case] input: &str, #[case] expected: &str) {
+        assert_eq!(hex::encode(keccak256(input.as_bytes())), expected);
+    }
+
+    #[cfg(feature = "hmac")]
+    #[test]
+    fn it_can_hmac_sha256() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            hex::encode(hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
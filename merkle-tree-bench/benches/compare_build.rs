@@ -0,0 +1,85 @@
+use std::hash::Hasher;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use merkle_light::hash::Algorithm;
+use merkle_tree_lib::LeafEncode;
+use rs_merkle::Hasher as _;
+use sha2::{Digest, Sha256};
+
+/// `merkle_light` needs its hash function wrapped in an `Algorithm` implementation.
+#[derive(Clone, Default)]
+struct Sha256Algorithm(Sha256);
+
+impl Hasher for Sha256Algorithm {
+    fn write(&mut self, msg: &[u8]) {
+        self.0.update(msg)
+    }
+
+    /// The first 8 bytes of the digest so far, `merkle_light` only uses `Algorithm::hash`.
+    fn finish(&self) -> u64 {
+        let digest: [u8; 32] = self.0.clone().finalize().into();
+        u64::from_le_bytes(digest[..8].try_into().unwrap())
+    }
+}
+
+impl Algorithm<[u8; 32]> for Sha256Algorithm {
+    fn hash(&mut self) -> [u8; 32] {
+        self.0.clone().finalize().into()
+    }
+
+    fn reset(&mut self) {
+        self.0 = Sha256::new();
+    }
+}
+
+/// Builds the same dataset with this crate, `rs_merkle` and `merkle_light`.
+///
+/// Every implementation starts from the same serialized user records and hashes the
+/// leaves with SHA-256 inside the measured closure, so the numbers include leaf hashing
+/// for all of them. Criterion groups the three functions per input size, which gives a
+/// side by side comparison in `target/criterion/compare::build/report/index.html`.
+fn bench_compare_build(c: &mut Criterion) {
+    let tag_leaf = "ProofOfReserve_Leaf";
+    let tag_branch = "ProofOfReserve_Branch";
+
+    let mut group = c.benchmark_group("compare::build");
+
+    for i in [10, 100, 1_000, 10_000, 100_000, 1_000_000].iter() {
+        let user_data = merkle_tree_lib::util::generate_random_user_data(*i);
+        let serialized: Vec<Vec<u8>> = user_data.iter().map(|data| data.serialize()).collect();
+
+        group.bench_with_input(BenchmarkId::new("merkle_tree_lib", i), i, |b, _| {
+            b.iter(|| {
+                let tree = merkle_tree_lib::MerkleTree::build(tag_leaf, tag_branch, &user_data);
+                std::hint::black_box(tree.root())
+            });
+        });
+
+        group.bench_with_input(BenchmarkId::new("rs_merkle", i), i, |b, _| {
+            b.iter(|| {
+                let leaves: Vec<[u8; 32]> = serialized
+                    .iter()
+                    .map(|data| rs_merkle::algorithms::Sha256::hash(data))
+                    .collect();
+                let tree =
+                    rs_merkle::MerkleTree::<rs_merkle::algorithms::Sha256>::from_leaves(&leaves);
+                std::hint::black_box(tree.root())
+            });
+        });
+
+        group.bench_with_input(BenchmarkId::new("merkle_light", i), i, |b, _| {
+            b.iter(|| {
+                let tree: merkle_light::merkle::MerkleTree<[u8; 32], Sha256Algorithm> = serialized
+                    .iter()
+                    .map(|data| Sha256::digest(data).into())
+                    .collect();
+                std::hint::black_box(tree.root())
+            });
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_compare_build);
+criterion_main!(benches);
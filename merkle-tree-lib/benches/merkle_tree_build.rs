@@ -1,7 +1,5 @@
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 
-use merkle_tree_lib;
-
 fn bench_merkle_tree_lib_build(c: &mut Criterion) {
     let tag_leaf = "ProofOfReserve_Leaf";
     let tag_branch = "ProofOfReserve_Branch";
@@ -13,9 +11,34 @@ fn bench_merkle_tree_lib_build(c: &mut Criterion) {
             let user_data_large = merkle_tree_lib::util::generate_random_user_data(i);
 
             b.iter(|| {
-                std::hint::black_box({
-                    merkle_tree_lib::MerkleTree::build(tag_leaf, tag_branch, &user_data_large);
-                });
+                std::hint::black_box(merkle_tree_lib::MerkleTree::build(
+                    tag_leaf,
+                    tag_branch,
+                    &user_data_large,
+                ));
+            });
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_merkle_tree_lib_streaming_root(c: &mut Criterion) {
+    let tag_leaf = "ProofOfReserve_Leaf";
+    let tag_branch = "ProofOfReserve_Branch";
+
+    let mut group = c.benchmark_group("merkle_tree_lib::streaming_root");
+
+    for i in [10, 100, 1_000, 10_000, 100_000, 1_000_000].iter() {
+        group.bench_with_input(BenchmarkId::from_parameter(i), i, |b, &i| {
+            let user_data_large = merkle_tree_lib::util::generate_random_user_data(i);
+
+            b.iter(|| {
+                let mut hasher = merkle_tree_lib::MerkleRootHasher::new(tag_leaf, tag_branch);
+                for data in &user_data_large {
+                    hasher.push(data);
+                }
+                std::hint::black_box(hasher.finalize());
             });
         });
     }
@@ -23,5 +46,9 @@ fn bench_merkle_tree_lib_build(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, bench_merkle_tree_lib_build);
+criterion_group!(
+    benches,
+    bench_merkle_tree_lib_build,
+    bench_merkle_tree_lib_streaming_root
+);
 criterion_main!(benches);
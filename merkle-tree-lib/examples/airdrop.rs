@@ -0,0 +1,100 @@
+//! Builds the allowlist of an airdrop whose claims are checked on chain.
+//!
+//! Every entry is an `(address, uint256)` pair, ABI encoded like `abi.encode(account, amount)`
+//! and hashed like `StandardMerkleTree` of `@openzeppelin/merkle-tree` does, so the contract
+//! only stores the `bytes32` root and verifies a claim with OpenZeppelin's
+//! `MerkleProof.verify(proof, root, keccak256(bytes.concat(keccak256(abi.encode(account,
+//! amount)))))`.
+//!
+//! Run with a file of `address,amount` lines, or without arguments for sample data:
+//!
+//! ```sh
+//! cargo run -p merkle-tree-lib --features openzeppelin --example airdrop -- allowlist.csv
+//! ```
+
+use merkle_tree_lib::openzeppelin::{to_bytes32, SortedPairTree};
+use merkle_tree_lib::LeafEncode;
+
+#[derive(Clone, Debug)]
+struct Allowance {
+    account: [u8; 20],
+    amount: u128,
+}
+
+impl Allowance {
+    fn parse(line: &str) -> Option<Self> {
+        let (account, amount) = line.split_once(',')?;
+        let account = account.trim();
+        let account = hex::decode(account.strip_prefix("0x").unwrap_or(account)).ok()?;
+
+        Some(Allowance {
+            account: account.try_into().ok()?,
+            amount: amount.trim().parse().ok()?,
+        })
+    }
+}
+
+impl LeafEncode for Allowance {
+    /// The ABI encoding of `(address, uint256)`, both left padded to 32 bytes.
+    fn serialize(&self) -> Vec<u8> {
+        let mut encoded = vec![0; 64];
+        encoded[12..32].copy_from_slice(&self.account);
+        encoded[48..].copy_from_slice(&self.amount.to_be_bytes());
+        encoded
+    }
+}
+
+fn main() -> std::io::Result<()> {
+    let allowances = match std::env::args().nth(1) {
+        None => vec![
+            Allowance {
+                account: [0x11; 20],
+                amount: 5_000_000_000_000_000_000,
+            },
+            Allowance {
+                account: [0x22; 20],
+                amount: 2_500_000_000_000_000_000,
+            },
+            Allowance {
+                account: [0x33; 20],
+                amount: 1_000_000_000_000_000_000,
+            },
+        ],
+        Some(path) => std::fs::read_to_string(path)?
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                Allowance::parse(line).ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("not an address,amount line: {line}"),
+                    )
+                })
+            })
+            .collect::<std::io::Result<_>>()?,
+    };
+
+    let tree = SortedPairTree::build(&allowances);
+    let root = tree.root().expect("at least one allowance");
+
+    println!("allowlist of {} accounts", allowances.len());
+    println!("root: {}", to_bytes32(&root));
+
+    for (index, allowance) in allowances.iter().enumerate() {
+        let proof = tree.proof(index).unwrap();
+
+        let verified = proof.verify(allowance, &root);
+        println!(
+            "0x{} {}: {} verified: {verified}",
+            hex::encode(allowance.account),
+            allowance.amount,
+            proof.to_bytes32_array()
+        );
+
+        let mut claimed_more = allowance.clone();
+        claimed_more.amount += 1;
+        assert!(!proof.verify(&claimed_more, &root));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,85 @@
+//! Notarizes a set of documents under a single Merkle root.
+//!
+//! Every document is reduced to its SHA256 digest, the digests become the leaves of a tree
+//! and only the root needs to be published. Each document then gets its own proof that it
+//! was part of the notarized set, without revealing the other documents.
+//!
+//! Run with the paths of the documents to notarize, or without arguments for sample data:
+//!
+//! ```sh
+//! cargo run -p merkle-tree-lib --example notarize -- README.md Cargo.toml
+//! ```
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use merkle_tree_lib::{LeafEncode, MerkleTree, ProofEnvelope};
+use sha2::{Digest, Sha256};
+
+const TAG_LEAF: &str = "Notarization_Document";
+const TAG_BRANCH: &str = "Notarization_Branch";
+
+#[derive(Clone, Debug, Default)]
+struct Document {
+    name: String,
+    digest: Vec<u8>,
+}
+
+impl Document {
+    fn new(name: &str, content: &[u8]) -> Self {
+        Document {
+            name: name.to_string(),
+            digest: Sha256::digest(content).to_vec(),
+        }
+    }
+}
+
+impl LeafEncode for Document {
+    fn serialize(&self) -> Vec<u8> {
+        self.digest.clone()
+    }
+}
+
+fn main() -> std::io::Result<()> {
+    let paths: Vec<String> = std::env::args().skip(1).collect();
+
+    let documents = if paths.is_empty() {
+        vec![
+            Document::new("contract.txt", b"Alice sells Bob one bicycle."),
+            Document::new("invoice.txt", b"Bob owes Alice 100 EUR."),
+            Document::new("receipt.txt", b"Alice received 100 EUR from Bob."),
+        ]
+    } else {
+        paths
+            .iter()
+            .map(|path| Ok(Document::new(path, &std::fs::read(path)?)))
+            .collect::<std::io::Result<_>>()?
+    };
+
+    let tree = MerkleTree::build(TAG_LEAF, TAG_BRANCH, &documents);
+    let root = tree.root().expect("at least one document");
+    let notarized_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+
+    println!("notarized {} documents at {notarized_at}", documents.len());
+    println!("root: {root}");
+
+    for (index, document) in documents.iter().enumerate() {
+        let envelope = ProofEnvelope::new(root.clone(), tree.proof(index).unwrap())
+            .with_metadata("notarization", notarized_at);
+
+        let verified = envelope.verify(TAG_LEAF, TAG_BRANCH, document);
+        println!(
+            "{}: {} ({} siblings) verified: {verified}",
+            document.name,
+            hex::encode(&document.digest),
+            envelope.proof.hashes.len()
+        );
+
+        let mut altered = document.clone();
+        altered.digest[0] ^= 1;
+        assert!(!envelope.verify(TAG_LEAF, TAG_BRANCH, &altered));
+    }
+
+    Ok(())
+}
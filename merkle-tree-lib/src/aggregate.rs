@@ -0,0 +1,209 @@
+use crate::{LeafEncode, MerkleTree};
+
+/// A value computed for every node of a tree, bottom-up from the leaves.
+///
+/// Implement it for per-subtree analytics such as account counts or balance totals.
+pub trait Aggregate<T> {
+    /// Computes the value of a leaf from its user data.
+    fn from_leaf(data: &T) -> Self;
+    /// Computes the value of a branch from the values of its children.
+    fn combine(left: &Self, right: &Self) -> Self;
+}
+
+/// A Merkle Tree with an aggregate value attached to every node.
+///
+/// The last node of a level with an odd number of nodes is paired with a copy of itself
+/// for hashing, but its value is only counted once: the parent takes the value of the
+/// single child as it is, without calling `combine`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AggregatedMerkleTree<T, A> {
+    tree: MerkleTree<T>,
+    aggregates: Vec<Vec<A>>,
+}
+
+impl<T, A> AggregatedMerkleTree<T, A>
+where
+    T: Clone + LeafEncode,
+    A: Clone,
+{
+    /// Computes the aggregate values of an existing tree with the given functions.
+    ///
+    /// # Arguments
+    ///
+    /// * `tree`: The tree to aggregate.
+    /// * `leaf_fn`: A function that computes the value of a leaf from its user data.
+    /// * `combine_fn`: A function that computes the value of a branch from its children.
+    pub fn from_tree<F, G>(tree: MerkleTree<T>, leaf_fn: F, combine_fn: G) -> Self
+    where
+        F: Fn(&T) -> A,
+        G: Fn(&A, &A) -> A,
+    {
+        let levels = tree.levels();
+
+        let mut aggregates: Vec<Vec<A>> = Vec::with_capacity(levels.len());
+        if let Some(leaves) = levels.first() {
+            aggregates.push(
+                leaves
+                    .iter()
+                    .filter_map(|leaf| leaf.user_data.as_ref().map(&leaf_fn))
+                    .collect(),
+            );
+        }
+
+        for _ in 1..levels.len() {
+            let next = aggregates
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => combine_fn(left, right),
+                    [single] => single.clone(),
+                    _ => unreachable!(),
+                })
+                .collect();
+            aggregates.push(next);
+        }
+
+        AggregatedMerkleTree { tree, aggregates }
+    }
+
+    /// Returns the underlying Merkle Tree.
+    pub fn tree(&self) -> &MerkleTree<T> {
+        &self.tree
+    }
+
+    /// Returns the value of the node at the given position.
+    ///
+    /// # Arguments
+    ///
+    /// * `level`: The level of the node, counted from the leaves at 0.
+    /// * `position`: The position of the node within its level.
+    pub fn aggregate_at(&self, level: usize, position: usize) -> Option<&A> {
+        self.aggregates.get(level)?.get(position)
+    }
+
+    /// Returns the value of the root node, which covers every leaf.
+    pub fn root_aggregate(&self) -> Option<&A> {
+        self.aggregates.last()?.first()
+    }
+
+    /// Returns the values of every level, leaves first and the root last.
+    pub fn aggregates(&self) -> &[Vec<A>] {
+        &self.aggregates
+    }
+}
+
+impl<T> MerkleTree<T>
+where
+    T: Clone + LeafEncode,
+{
+    /// Builds a Merkle Tree and computes the `Aggregate` value of every node.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_leaf`: The tag used for hashing leaf nodes.
+    /// * `tag_branch`: The tag used for hashing branch nodes.
+    /// * `input`: The user data stored in the leaves.
+    pub fn build_with_aggregate<A>(
+        tag_leaf: &str,
+        tag_branch: &str,
+        input: &[T],
+    ) -> AggregatedMerkleTree<T, A>
+    where
+        A: Aggregate<T> + Clone,
+    {
+        AggregatedMerkleTree::from_tree(
+            Self::build(tag_leaf, tag_branch, input),
+            A::from_leaf,
+            A::combine,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{generate_random_user_data, UserData};
+    use rstest::rstest;
+
+    const TAG_LEAF: &str = "ProofOfReserve_Leaf";
+    const TAG_BRANCH: &str = "ProofOfReserve_Branch";
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct AccountStats {
+        accounts: usize,
+        total_balance: u64,
+    }
+
+    impl Aggregate<UserData> for AccountStats {
+        fn from_leaf(data: &UserData) -> Self {
+            AccountStats {
+                accounts: 1,
+                total_balance: data.balance as u64,
+            }
+        }
+
+        fn combine(left: &Self, right: &Self) -> Self {
+            AccountStats {
+                accounts: left.accounts + right.accounts,
+                total_balance: left.total_balance + right.total_balance,
+            }
+        }
+    }
+
+    #[rstest]
+    #[case(1)]
+    #[case(5)]
+    #[case(8)]
+    #[case(13)]
+    fn it_can_aggregate_every_subtree(#[case] n: usize) {
+        let user_data = generate_random_user_data(n);
+        let tree: AggregatedMerkleTree<_, AccountStats> =
+            MerkleTree::build_with_aggregate(TAG_LEAF, TAG_BRANCH, &user_data);
+
+        assert_eq!(
+            tree.root_aggregate().unwrap(),
+            &AccountStats {
+                accounts: n,
+                total_balance: user_data.iter().map(|data| data.balance as u64).sum(),
+            }
+        );
+
+        for (level, values) in tree.aggregates().iter().enumerate() {
+            let width = 1 << level;
+            for (position, value) in values.iter().enumerate() {
+                let leaves = &user_data[position * width..n.min((position + 1) * width)];
+                assert_eq!(value.accounts, leaves.len());
+            }
+        }
+
+        assert_eq!(
+            tree.tree().root(),
+            MerkleTree::build(TAG_LEAF, TAG_BRANCH, &user_data).root()
+        );
+    }
+
+    #[test]
+    fn it_can_aggregate_with_closures() {
+        let user_data = generate_random_user_data(6);
+        let tree = AggregatedMerkleTree::from_tree(
+            MerkleTree::build(TAG_LEAF, TAG_BRANCH, &user_data),
+            |data| data.balance,
+            |left, right| *left.max(right),
+        );
+
+        assert_eq!(tree.root_aggregate(), Some(&6000));
+        assert_eq!(tree.aggregate_at(1, 0), Some(&2000));
+        assert_eq!(tree.aggregate_at(2, 1), Some(&6000));
+        assert_eq!(tree.aggregate_at(2, 2), None);
+    }
+
+    #[test]
+    fn it_can_aggregate_an_empty_tree() {
+        let input: Vec<UserData> = vec![];
+        let tree: AggregatedMerkleTree<_, AccountStats> =
+            MerkleTree::build_with_aggregate(TAG_LEAF, TAG_BRANCH, &input);
+
+        assert!(tree.root_aggregate().is_none());
+    }
+}
@@ -0,0 +1,199 @@
+use crate::{
+    level_sizes, to_hash, LeafEncode, LevelLinks, MerkleNode, MerkleTree, MerkleTreeError, NodeId,
+    OddLeafPolicy, TagHasher,
+};
+
+/// The shape of a tree, see `MerkleTree::stats`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TreeStats {
+    /// The number of levels below the root, the length of the longest proof.
+    pub depth: u32,
+    pub leaf_count: usize,
+    /// The number of branch nodes hashed from two children. Nodes promoted unchanged by
+    /// `OddLeafPolicy::PromoteOdd` are not counted.
+    pub internal_node_count: usize,
+}
+
+impl<T> MerkleTree<T> {
+    /// Returns the depth, the number of leaves and the number of internal nodes.
+    pub fn stats(&self) -> TreeStats {
+        let sizes = level_sizes(self.leaf_count);
+        let internal_node_count = sizes[..sizes.len() - 1]
+            .iter()
+            .map(|&size| match self.odd_leaf_policy {
+                OddLeafPolicy::PromoteOdd => size / 2,
+                _ => size.div_ceil(2),
+            })
+            .sum();
+
+        TreeStats {
+            depth: self.depth(),
+            leaf_count: self.leaf_count,
+            internal_node_count,
+        }
+    }
+}
+
+impl<T> MerkleTree<T>
+where
+    T: Clone + LeafEncode,
+{
+    /// Re-hashes the whole tree bottom-up and checks every stored hash against the user data
+    /// or the children it was computed from.
+    ///
+    /// Building a tree cannot get its hashes wrong, but `from_bytes` takes the hashes of a
+    /// snapshot as they are. Auditing a tree restored from untrusted storage detects any
+    /// stored hash that was altered, before its proofs are handed out.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_leaf`: The tag the tree was built with for hashing leaf nodes.
+    /// * `tag_branch`: The tag the tree was built with for hashing branch nodes.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if every hash matches, otherwise the first mismatch from the leaves up,
+    /// `MerkleTreeError::LeafHashMismatch` or `MerkleTreeError::BranchHashMismatch`.
+    pub fn audit(&self, tag_leaf: &str, tag_branch: &str) -> Result<(), MerkleTreeError> {
+        let domain_separation = self.domain_separation;
+        let (tag_leaf, tag_branch) = (TagHasher::new(tag_leaf), TagHasher::new(tag_branch));
+        let levels = self.levels();
+        let Some(leaves) = levels.first() else {
+            return Ok(());
+        };
+
+        for (index, leaf) in leaves.iter().enumerate() {
+            let matches = leaf.user_data.as_ref().is_some_and(|data| {
+                to_hash(domain_separation.leaf_hash_with(&tag_leaf, &data.serialize())) == leaf.hash
+            });
+            if !matches {
+                return Err(MerkleTreeError::LeafHashMismatch { index });
+            }
+        }
+
+        let pad = level_sizes(self.leaf_count).iter().sum::<usize>() as NodeId;
+        let mut start = 0;
+        for (level, pair) in levels.windows(2).enumerate() {
+            let (below, nodes) = (pair[0], pair[1]);
+            let links = LevelLinks {
+                start: start as NodeId,
+                pad,
+                odd_leaf_policy: self.odd_leaf_policy,
+            };
+            for (position, node) in nodes.iter().enumerate() {
+                let expected: MerkleNode<T> =
+                    MerkleNode::new_parent(below, links, position, |left, right| {
+                        to_hash(domain_separation.branch_hash_with(&tag_branch, left, right))
+                    });
+                if expected.hash != node.hash {
+                    return Err(MerkleTreeError::BranchHashMismatch {
+                        level: level as u32 + 1,
+                        position,
+                    });
+                }
+            }
+            start += below.len();
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{generate_random_user_data, UserData};
+    use crate::DomainSeparation;
+    use rstest::rstest;
+
+    const TAG_LEAF: &str = "ProofOfReserve_Leaf";
+    const TAG_BRANCH: &str = "ProofOfReserve_Branch";
+
+    #[rstest]
+    #[case(0, OddLeafPolicy::DuplicateLast, 0, 0)]
+    #[case(1, OddLeafPolicy::DuplicateLast, 0, 0)]
+    #[case(5, OddLeafPolicy::DuplicateLast, 3, 6)]
+    #[case(5, OddLeafPolicy::PromoteOdd, 3, 4)]
+    #[case(5, OddLeafPolicy::PadWithEmptyHash, 3, 6)]
+    #[case(8, OddLeafPolicy::PromoteOdd, 3, 7)]
+    fn it_can_count_the_nodes_of_a_tree(
+        #[case] n: usize,
+        #[case] odd_leaf_policy: OddLeafPolicy,
+        #[case] depth: u32,
+        #[case] internal_node_count: usize,
+    ) {
+        let tree = MerkleTree::build_with_odd_leaf_policy(
+            TAG_LEAF,
+            TAG_BRANCH,
+            &generate_random_user_data(n),
+            odd_leaf_policy,
+        );
+
+        assert_eq!(
+            tree.stats(),
+            TreeStats {
+                depth,
+                leaf_count: n,
+                internal_node_count,
+            }
+        );
+    }
+
+    #[rstest]
+    #[case(DomainSeparation::Tags, OddLeafPolicy::DuplicateLast)]
+    #[case(DomainSeparation::Tags, OddLeafPolicy::PromoteOdd)]
+    #[case(DomainSeparation::Tags, OddLeafPolicy::PadWithEmptyHash)]
+    #[case(DomainSeparation::Prefixed, OddLeafPolicy::DuplicateLast)]
+    fn it_can_audit_a_built_tree(
+        #[case] domain_separation: DomainSeparation,
+        #[case] odd_leaf_policy: OddLeafPolicy,
+    ) {
+        for n in [0, 1, 2, 5, 11] {
+            let tree = MerkleTree::build_with(
+                TAG_LEAF,
+                TAG_BRANCH,
+                &generate_random_user_data(n),
+                domain_separation,
+                odd_leaf_policy,
+            );
+
+            assert_eq!(tree.audit(TAG_LEAF, TAG_BRANCH), Ok(()));
+            if n > 0 {
+                assert_eq!(
+                    tree.audit(TAG_BRANCH, TAG_BRANCH),
+                    Err(MerkleTreeError::LeafHashMismatch { index: 0 })
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn it_can_detect_a_tampered_snapshot() {
+        let tree = MerkleTree::build(TAG_LEAF, TAG_BRANCH, &generate_random_user_data(5));
+        let bytes = tree.to_bytes();
+        // the hashes of the 5 leaves, 3 and 2 branches and the root end the snapshot
+        let hash_at = |node: usize| bytes.len() - (11 - node) * 32;
+
+        let mut tampered = bytes.clone();
+        tampered[hash_at(7)] ^= 1;
+        let restored = MerkleTree::<UserData>::from_bytes(&tampered).unwrap();
+        assert_eq!(
+            restored.audit(TAG_LEAF, TAG_BRANCH),
+            Err(MerkleTreeError::BranchHashMismatch {
+                level: 1,
+                position: 2
+            })
+        );
+
+        let mut tampered = bytes.clone();
+        tampered[hash_at(3)] ^= 1;
+        let restored = MerkleTree::<UserData>::from_bytes(&tampered).unwrap();
+        assert_eq!(
+            restored.audit(TAG_LEAF, TAG_BRANCH),
+            Err(MerkleTreeError::LeafHashMismatch { index: 3 })
+        );
+
+        let restored = MerkleTree::<UserData>::from_bytes(&bytes).unwrap();
+        assert_eq!(restored.audit(TAG_LEAF, TAG_BRANCH), Ok(()));
+    }
+}
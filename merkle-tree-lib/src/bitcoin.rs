@@ -0,0 +1,113 @@
+//! Bitcoin compatible Merkle roots.
+//!
+//! Bitcoin does not use tagged hashes for its block Merkle tree: nodes are combined with
+//! double SHA-256, the last node of a level with an odd number of nodes is paired with
+//! itself at every level, and txids are displayed in the reverse of their internal byte
+//! order.
+
+pub use merkle_hash::double_sha256;
+
+use crate::Hash;
+
+/// Calculates the Merkle root of a block from its txids in internal byte order.
+///
+/// # Arguments
+///
+/// * `txids`: The txids of the block's transactions in block order, in internal byte order.
+///
+/// # Returns
+///
+/// An `Option` containing the Merkle root in internal byte order, `None` if there are no txids.
+pub fn merkle_root(txids: &[Hash]) -> Option<Hash> {
+    let mut level = txids.to_vec();
+
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| {
+                let (left, right) = (pair[0], *pair.last().unwrap());
+                double_sha256(&[left, right].concat())
+            })
+            .collect();
+    }
+
+    level.first().copied()
+}
+
+/// Calculates the Merkle root of a block from txids as shown by block explorers and RPC.
+///
+/// # Arguments
+///
+/// * `txids`: The hex encoded txids in display (reversed) byte order.
+///
+/// # Returns
+///
+/// An `Option` containing the hex encoded Merkle root in display byte order, `None` if there
+/// are no txids or a txid is not 32 bytes of hex.
+pub fn merkle_root_from_hex<S>(txids: &[S]) -> Option<String>
+where
+    S: AsRef<str>,
+{
+    let txids = txids
+        .iter()
+        .map(|txid| {
+            let mut hash: Hash = hex::decode(txid.as_ref()).ok()?.try_into().ok()?;
+            hash.reverse();
+            Some(hash)
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    merkle_root(&txids).map(|mut root| {
+        root.reverse();
+        hex::encode(root)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    // block 100000
+    #[case(
+        vec![
+            "8c14f0db3df150123e6f3dbbf30f8b955a8249b62ac1d1ff16284aefa3d06d87",
+            "fff2525b8931402dd09222c50775608f75787bd2b87e56995a7bdd30f79702c4",
+            "6359f0868171b1d194cbee1af2f16ea598ae8fad666d9b012c8ed2b79a236ec4",
+            "e9a66845e05d5abc0ad04ec80f774a7e585c6e8db975962d069a522137b80c1d",
+        ],
+        "f3e94742aca4b5ef85488dc37c06c3282295ffec960994b2c0d5ac2a25a95766"
+    )]
+    // block 170
+    #[case(
+        vec![
+            "b1fea52486ce0c62bb442b530a3f0132b826c74e473d1f2c220bfa78111c5082",
+            "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16",
+        ],
+        "7dac2c5666815c17a3b36427de37bb9d2e2c5ccec3f8633eb91a4205cb4c10ff"
+    )]
+    // block 1, a single coinbase transaction is its own root
+    #[case(
+        vec!["0e3e2357e806b6cdb1f70b54c3a3a17b6714ee1f0e68bebb44a74b1efd512098"],
+        "0e3e2357e806b6cdb1f70b54c3a3a17b6714ee1f0e68bebb44a74b1efd512098"
+    )]
+    fn it_can_reproduce_mainnet_merkle_root(#[case] txids: Vec<&str>, #[case] expected: &str) {
+        assert_eq!(merkle_root_from_hex(&txids).unwrap(), expected);
+    }
+
+    #[test]
+    fn it_can_duplicate_last_txid_on_odd_levels() {
+        let txids: Vec<Hash> = (0u8..3).map(|i| double_sha256(&[i])).collect();
+        let padded = [txids.clone(), vec![txids[2]]].concat();
+
+        assert_eq!(merkle_root(&txids), merkle_root(&padded));
+    }
+
+    #[test]
+    fn it_can_reject_invalid_txids() {
+        assert!(merkle_root_from_hex::<&str>(&[]).is_none());
+        assert!(merkle_root_from_hex(&["abcd"]).is_none());
+        assert!(merkle_root_from_hex(&["zz".repeat(32)]).is_none());
+    }
+}
@@ -0,0 +1,629 @@
+use std::io;
+use std::marker::PhantomData;
+use std::ops::ControlFlow;
+
+#[cfg(feature = "salted")]
+use crate::Salted;
+use crate::{
+    level_sizes, to_hash, DomainSeparation, LeafEncode, LevelLinks, MerkleNode, MerkleTree,
+    MerkleTreeError, NodeStore, OddLeafPolicy, StoredMerkleTree, TagHasher,
+};
+
+/// The number of leaves `build_with_progress` hashes between two progress reports.
+const PROGRESS_INTERVAL: usize = 1 << 14;
+
+/// How far `MerkleTreeBuilder::build_with_progress` got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildProgress {
+    /// `hashed` leaves are hashed, reported every 16384 leaves and once all of them are.
+    LeavesHashed { hashed: usize },
+    /// The nodes of `level` are built, counted from the leaves at 0. The level equal to
+    /// `depth` is the root.
+    LevelCompleted { level: u32, depth: u32 },
+}
+
+/// What the builder needs from the user data on top of `Clone + LeafEncode`: `Send + Sync`
+/// with the `parallel` feature, nothing without it.
+///
+/// Implemented for every type that qualifies, so without the feature leaves holding an `Rc`
+/// or a `RefCell` are built with the builder like with `MerkleTree::build`.
+#[cfg(feature = "parallel")]
+pub trait ParallelLeaf: Send + Sync {}
+
+#[cfg(feature = "parallel")]
+impl<T: Send + Sync> ParallelLeaf for T {}
+
+/// What the builder needs from the user data on top of `Clone + LeafEncode`: `Send + Sync`
+/// with the `parallel` feature, nothing without it.
+///
+/// Implemented for every type that qualifies, so without the feature leaves holding an `Rc`
+/// or a `RefCell` are built with the builder like with `MerkleTree::build`.
+#[cfg(not(feature = "parallel"))]
+pub trait ParallelLeaf {}
+
+#[cfg(not(feature = "parallel"))]
+impl<T> ParallelLeaf for T {}
+
+/// Collects the options of a Merkle Tree before building it from the leaves.
+///
+/// Starts out like `MerkleTree::build`: no sorting, `DomainSeparation::Tags` and
+/// `OddLeafPolicy::DuplicateLast`, so only the options that differ need to be set.
+#[derive(Debug, Clone)]
+pub struct MerkleTreeBuilder<T> {
+    tag_leaf: String,
+    tag_branch: String,
+    domain_separation: DomainSeparation,
+    odd_leaf_policy: OddLeafPolicy,
+    sort_leaves: bool,
+    #[cfg(feature = "parallel")]
+    parallel: bool,
+    leaf_type: PhantomData<fn(&T)>,
+}
+
+impl<T> MerkleTreeBuilder<T> {
+    /// Creates a builder with the default options.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_leaf`: The tag used for hashing leaf nodes.
+    /// * `tag_branch`: The tag used for hashing branch nodes.
+    pub fn new(tag_leaf: &str, tag_branch: &str) -> Self {
+        MerkleTreeBuilder {
+            tag_leaf: tag_leaf.to_string(),
+            tag_branch: tag_branch.to_string(),
+            domain_separation: DomainSeparation::default(),
+            odd_leaf_policy: OddLeafPolicy::default(),
+            sort_leaves: false,
+            #[cfg(feature = "parallel")]
+            parallel: false,
+            leaf_type: PhantomData,
+        }
+    }
+
+    /// Sets how leaf, branch and root hashes are kept apart.
+    pub fn domain_separation(mut self, domain_separation: DomainSeparation) -> Self {
+        self.domain_separation = domain_separation;
+        self
+    }
+
+    /// Sets what the last node of a level with an odd number of nodes is paired with.
+    pub fn odd_leaf_policy(mut self, odd_leaf_policy: OddLeafPolicy) -> Self {
+        self.odd_leaf_policy = odd_leaf_policy;
+        self
+    }
+
+    /// Sorts the leaves by their serialized user data, so the root does not depend on the
+    /// order they are passed in. Leaves that serialize equally keep their order.
+    pub fn sort_leaves(mut self, sort_leaves: bool) -> Self {
+        self.sort_leaves = sort_leaves;
+        self
+    }
+
+    /// Hashes the leaves and the nodes of every level on the rayon thread pool.
+    ///
+    /// The tree is the same as a sequentially built one, only the wall time changes.
+    #[cfg(feature = "parallel")]
+    pub fn parallel(mut self, parallel: bool) -> Self {
+        self.parallel = parallel;
+        self
+    }
+
+    /// Returns a builder with the same options for another type of user data.
+    #[cfg(feature = "salted")]
+    fn for_leaves<U>(&self) -> MerkleTreeBuilder<U> {
+        MerkleTreeBuilder {
+            tag_leaf: self.tag_leaf.clone(),
+            tag_branch: self.tag_branch.clone(),
+            domain_separation: self.domain_separation,
+            odd_leaf_policy: self.odd_leaf_policy,
+            sort_leaves: self.sort_leaves,
+            #[cfg(feature = "parallel")]
+            parallel: self.parallel,
+            leaf_type: PhantomData,
+        }
+    }
+}
+
+impl<T> MerkleTreeBuilder<T>
+where
+    T: Clone + LeafEncode + ParallelLeaf,
+{
+    /// Builds a Merkle Tree from the given user data with the collected options.
+    ///
+    /// # Arguments
+    ///
+    /// * `leaves`: The user data stored in the leaves.
+    pub fn build(&self, leaves: &[T]) -> MerkleTree<T> {
+        self.build_from_iter(leaves.iter().cloned())
+    }
+
+    /// Builds a Merkle Tree from user data that is moved into the leaves instead of copied,
+    /// see `MerkleTree::build_from_iter`.
+    ///
+    /// Sorting and parallel builds need all leaves at once, with either option the leaves
+    /// are collected first.
+    ///
+    /// # Arguments
+    ///
+    /// * `leaves`: The user data stored in the leaves.
+    pub fn build_from_iter<I>(&self, leaves: I) -> MerkleTree<T>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        #[cfg(feature = "parallel")]
+        if self.parallel {
+            return self.build_parallel(self.collect_leaves(leaves));
+        }
+
+        if self.sort_leaves {
+            self.build_sequential(self.collect_leaves(leaves))
+        } else {
+            self.build_sequential(leaves)
+        }
+    }
+
+    /// Builds a Merkle Tree from the given user data, salting every leaf with a salt derived
+    /// from `key` as `Salted::with_key` does.
+    ///
+    /// Salting changes the type of the leaves, so it is a build method of its own rather than
+    /// an option. With `sort_leaves` the salted leaves are sorted, which orders them by salt.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The secret key of the tree owner.
+    /// * `leaves`: The user data stored in the leaves.
+    #[cfg(feature = "salted")]
+    pub fn build_salted(&self, key: &[u8], leaves: &[T]) -> MerkleTree<Salted<T>> {
+        self.for_leaves().build_from_iter(
+            leaves
+                .iter()
+                .map(|data| Salted::with_key(key, data.clone())),
+        )
+    }
+
+    /// Builds a Merkle Tree like `build_from_iter`, reporting the progress after every batch
+    /// of leaves and every level, and stopping as soon as `progress` asks to.
+    ///
+    /// Building millions of leaves takes a while, the reports let a service show how far it
+    /// got and abort on shutdown, e.g. by checking a flag in `progress`. Run it on a blocking
+    /// thread in async code, it does not yield.
+    ///
+    /// # Arguments
+    ///
+    /// * `leaves`: The user data stored in the leaves.
+    /// * `progress`: Called with every `BuildProgress`, returns `ControlFlow::Break` to cancel.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the tree, `MerkleTreeError::BuildCancelled` if `progress` stopped
+    /// the build.
+    pub fn build_with_progress<I, F>(
+        &self,
+        leaves: I,
+        mut progress: F,
+    ) -> Result<MerkleTree<T>, MerkleTreeError>
+    where
+        I: IntoIterator<Item = T>,
+        F: FnMut(BuildProgress) -> ControlFlow<()>,
+    {
+        let mut report = |update| match progress(update) {
+            ControlFlow::Continue(()) => Ok(()),
+            ControlFlow::Break(()) => Err(MerkleTreeError::BuildCancelled),
+        };
+
+        let (tag_leaf, tag_branch) = (
+            TagHasher::new(&self.tag_leaf),
+            TagHasher::new(&self.tag_branch),
+        );
+        let nodes = if self.sort_leaves {
+            self.hash_leaves_in_batches(&tag_leaf, self.collect_leaves(leaves), &mut report)?
+        } else {
+            self.hash_leaves_in_batches(&tag_leaf, leaves, &mut report)?
+        };
+
+        let depth = (level_sizes(nodes.len()).len() - 1) as u32;
+        let mut level = 0;
+        MerkleTree::try_from_leaf_nodes(
+            nodes,
+            self.domain_separation,
+            self.odd_leaf_policy,
+            |nodes, links| {
+                let parents = self.hash_level(&tag_branch, nodes, links);
+                level += 1;
+                report(BuildProgress::LevelCompleted { level, depth })?;
+                Ok(parents)
+            },
+        )
+    }
+
+    /// Builds a tree that keeps only the hashes of its nodes, in `store`, see
+    /// `StoredMerkleTree`.
+    ///
+    /// The leaves are hashed as they come and every level is read back from the store, so
+    /// the memory used does not grow with the tree unless `sort_leaves` is set, which collects
+    /// the leaves first. `parallel` is ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `leaves`: The user data hashed into the leaves.
+    /// * `store`: The empty store to write the nodes to.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the tree, the `io::Error` of the store if writing failed, of kind
+    /// `InvalidInput` if the store is not empty.
+    pub fn build_into_store<I, S>(&self, leaves: I, store: S) -> io::Result<StoredMerkleTree<S>>
+    where
+        I: IntoIterator<Item = T>,
+        S: NodeStore,
+    {
+        let (tag_leaf, tag_branch) = (
+            TagHasher::new(&self.tag_leaf),
+            TagHasher::new(&self.tag_branch),
+        );
+        if self.sort_leaves {
+            StoredMerkleTree::build(
+                store,
+                &tag_leaf,
+                &tag_branch,
+                self.collect_leaves(leaves),
+                self.domain_separation,
+                self.odd_leaf_policy,
+            )
+        } else {
+            StoredMerkleTree::build(
+                store,
+                &tag_leaf,
+                &tag_branch,
+                leaves,
+                self.domain_separation,
+                self.odd_leaf_policy,
+            )
+        }
+    }
+
+    /// Hashes the leaves in batches of `PROGRESS_INTERVAL`, reporting after every batch.
+    fn hash_leaves_in_batches<I, F>(
+        &self,
+        tag_leaf: &TagHasher,
+        leaves: I,
+        report: &mut F,
+    ) -> Result<Vec<MerkleNode<T>>, MerkleTreeError>
+    where
+        I: IntoIterator<Item = T>,
+        F: FnMut(BuildProgress) -> Result<(), MerkleTreeError>,
+    {
+        let mut leaves = leaves.into_iter();
+        let mut nodes = Vec::with_capacity(leaves.size_hint().0);
+
+        loop {
+            let batch: Vec<T> = leaves.by_ref().take(PROGRESS_INTERVAL).collect();
+            let last = batch.len() < PROGRESS_INTERVAL;
+            // a last empty batch was already reported with the one before, unless there is none
+            if !batch.is_empty() || nodes.is_empty() {
+                nodes.extend(self.hash_leaves(tag_leaf, batch));
+                report(BuildProgress::LeavesHashed {
+                    hashed: nodes.len(),
+                })?;
+            }
+            if last {
+                return Ok(nodes);
+            }
+        }
+    }
+
+    /// Collects the leaves, sorted if `sort_leaves` is set.
+    fn collect_leaves<I>(&self, leaves: I) -> Vec<T>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut leaves: Vec<T> = leaves.into_iter().collect();
+        if self.sort_leaves {
+            leaves.sort_by_cached_key(|data| data.serialize());
+        }
+
+        leaves
+    }
+
+    fn build_sequential<I>(&self, leaves: I) -> MerkleTree<T>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        MerkleTree::build_iter_with(
+            &self.tag_leaf,
+            &self.tag_branch,
+            leaves,
+            self.domain_separation,
+            self.odd_leaf_policy,
+        )
+    }
+
+    #[cfg(feature = "parallel")]
+    fn build_parallel(&self, leaves: Vec<T>) -> MerkleTree<T> {
+        let (tag_leaf, tag_branch) = (
+            TagHasher::new(&self.tag_leaf),
+            TagHasher::new(&self.tag_branch),
+        );
+        let nodes = self.hash_leaves(&tag_leaf, leaves);
+
+        MerkleTree::from_leaf_nodes(
+            nodes,
+            self.domain_separation,
+            self.odd_leaf_policy,
+            |level, links| self.hash_level(&tag_branch, level, links),
+        )
+    }
+
+    /// Hashes the given leaves, on the rayon thread pool if `parallel` is set.
+    fn hash_leaves(&self, tag_leaf: &TagHasher, leaves: Vec<T>) -> Vec<MerkleNode<T>> {
+        let domain_separation = self.domain_separation;
+        let hash_leaf = |data: T| {
+            MerkleNode::new_leaf(
+                to_hash(domain_separation.leaf_hash_with(tag_leaf, &data.serialize())),
+                Some(data),
+            )
+        };
+
+        #[cfg(feature = "parallel")]
+        if self.parallel {
+            use rayon::prelude::*;
+
+            return leaves.into_par_iter().map(hash_leaf).collect();
+        }
+
+        leaves.into_iter().map(hash_leaf).collect()
+    }
+
+    /// Hashes the parents of the nodes of a level, on the rayon thread pool if `parallel` is
+    /// set.
+    fn hash_level(
+        &self,
+        tag_branch: &TagHasher,
+        level: &[MerkleNode<T>],
+        links: LevelLinks,
+    ) -> Vec<MerkleNode<T>> {
+        let domain_separation = self.domain_separation;
+        let hash_parent = |position| {
+            MerkleNode::new_parent(level, links, position, |left, right| {
+                to_hash(domain_separation.branch_hash_with(tag_branch, left, right))
+            })
+        };
+
+        #[cfg(feature = "parallel")]
+        if self.parallel {
+            use rayon::prelude::*;
+
+            return (0..level.len().div_ceil(2))
+                .into_par_iter()
+                .map(hash_parent)
+                .collect();
+        }
+
+        (0..level.len().div_ceil(2)).map(hash_parent).collect()
+    }
+}
+
+impl<T> MerkleTree<T> {
+    /// Starts building a Merkle Tree with the given tags, see `MerkleTreeBuilder`.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_leaf`: The tag used for hashing leaf nodes.
+    /// * `tag_branch`: The tag used for hashing branch nodes.
+    pub fn builder(tag_leaf: &str, tag_branch: &str) -> MerkleTreeBuilder<T> {
+        MerkleTreeBuilder::new(tag_leaf, tag_branch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{generate_random_user_data, UserData};
+    use rstest::rstest;
+
+    const TAG_LEAF: &str = "ProofOfReserve_Leaf";
+    const TAG_BRANCH: &str = "ProofOfReserve_Branch";
+
+    #[cfg(not(feature = "parallel"))]
+    #[test]
+    fn it_can_build_leaves_that_are_not_send() {
+        use std::rc::Rc;
+
+        #[derive(Clone)]
+        struct Shared(Rc<str>);
+
+        impl LeafEncode for Shared {
+            fn serialize(&self) -> Vec<u8> {
+                self.0.as_bytes().to_vec()
+            }
+        }
+
+        let leaves: Vec<Shared> = ["aaa", "bbb", "ccc"]
+            .into_iter()
+            .map(|value| Shared(Rc::from(value)))
+            .collect();
+
+        assert_eq!(
+            MerkleTree::builder(TAG_LEAF, TAG_BRANCH)
+                .build(&leaves)
+                .root(),
+            MerkleTree::build(TAG_LEAF, TAG_BRANCH, &leaves).root()
+        );
+    }
+
+    #[rstest]
+    #[case(DomainSeparation::Tags, OddLeafPolicy::DuplicateLast)]
+    #[case(DomainSeparation::Prefixed, OddLeafPolicy::PromoteOdd)]
+    #[case(DomainSeparation::Prefixed, OddLeafPolicy::PadWithEmptyHash)]
+    fn it_can_build_like_the_build_functions(
+        #[case] domain_separation: DomainSeparation,
+        #[case] odd_leaf_policy: OddLeafPolicy,
+    ) {
+        let user_data = generate_random_user_data(13);
+
+        let tree = MerkleTree::builder(TAG_LEAF, TAG_BRANCH)
+            .domain_separation(domain_separation)
+            .odd_leaf_policy(odd_leaf_policy)
+            .build(&user_data);
+
+        let expected = MerkleTree::build_with(
+            TAG_LEAF,
+            TAG_BRANCH,
+            &user_data,
+            domain_separation,
+            odd_leaf_policy,
+        );
+        assert_eq!(tree.root(), expected.root());
+        assert_eq!(tree.domain_separation(), domain_separation);
+        assert_eq!(tree.odd_leaf_policy(), odd_leaf_policy);
+        assert_eq!(
+            MerkleTree::builder(TAG_LEAF, TAG_BRANCH)
+                .build(&user_data)
+                .root(),
+            MerkleTree::build(TAG_LEAF, TAG_BRANCH, &user_data).root()
+        );
+    }
+
+    #[rstest]
+    #[case(false)]
+    #[case(true)]
+    fn it_can_build_from_an_iterator(#[case] sort_leaves: bool) {
+        let user_data = generate_random_user_data(11);
+        let builder = MerkleTree::builder(TAG_LEAF, TAG_BRANCH).sort_leaves(sort_leaves);
+
+        let tree = builder.build_from_iter(user_data.iter().cloned());
+
+        assert_eq!(tree.root(), builder.build(&user_data).root());
+        assert_eq!(tree.len(), user_data.len());
+        #[cfg(feature = "parallel")]
+        assert_eq!(
+            builder
+                .parallel(true)
+                .build_from_iter(user_data.iter().cloned())
+                .root(),
+            tree.root()
+        );
+    }
+
+    #[test]
+    fn it_can_sort_the_leaves() {
+        let user_data = generate_random_user_data(9);
+        let mut reversed = user_data.clone();
+        reversed.reverse();
+
+        let builder = MerkleTree::<UserData>::builder(TAG_LEAF, TAG_BRANCH).sort_leaves(true);
+        let tree = builder.build(&user_data);
+        assert_eq!(tree.root(), builder.build(&reversed).root());
+
+        let serialized: Vec<Vec<u8>> = tree.leaves().map(|(_, data, _)| data.serialize()).collect();
+        assert!(serialized.windows(2).all(|pair| pair[0] <= pair[1]));
+
+        let root = tree.root().unwrap();
+        let leaf = tree.search_index_with_path(4).unwrap();
+        assert!(leaf
+            .proof()
+            .verify(TAG_LEAF, TAG_BRANCH, leaf.data(), &root));
+    }
+
+    #[cfg(feature = "salted")]
+    #[test]
+    fn it_can_build_salted_leaves() {
+        let user_data = generate_random_user_data(5);
+        let key = b"server secret";
+
+        let tree = MerkleTree::builder(TAG_LEAF, TAG_BRANCH).build_salted(key, &user_data);
+        let salted: Vec<Salted<UserData>> = user_data
+            .iter()
+            .map(|data| Salted::with_key(key, data.clone()))
+            .collect();
+
+        assert_eq!(
+            tree.root(),
+            MerkleTree::build(TAG_LEAF, TAG_BRANCH, &salted).root()
+        );
+    }
+
+    #[rstest]
+    #[case(false)]
+    #[case(true)]
+    fn it_can_report_the_build_progress(#[case] sort_leaves: bool) {
+        let user_data = generate_random_user_data(2 * PROGRESS_INTERVAL + 5);
+        let builder = MerkleTree::builder(TAG_LEAF, TAG_BRANCH).sort_leaves(sort_leaves);
+
+        let mut reports = Vec::new();
+        let tree = builder
+            .build_with_progress(user_data.iter().cloned(), |progress| {
+                reports.push(progress);
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+
+        let mut expected: Vec<BuildProgress> = [
+            PROGRESS_INTERVAL,
+            2 * PROGRESS_INTERVAL,
+            2 * PROGRESS_INTERVAL + 5,
+        ]
+        .into_iter()
+        .map(|hashed| BuildProgress::LeavesHashed { hashed })
+        .collect();
+        expected.extend((1..=16).map(|level| BuildProgress::LevelCompleted { level, depth: 16 }));
+        assert_eq!(reports, expected);
+        assert_eq!(tree.root(), builder.build(&user_data).root());
+        #[cfg(feature = "parallel")]
+        assert_eq!(
+            builder
+                .parallel(true)
+                .build_with_progress(user_data, |_| ControlFlow::Continue(()))
+                .unwrap()
+                .root(),
+            tree.root()
+        );
+    }
+
+    #[test]
+    fn it_can_cancel_a_build() {
+        let user_data = generate_random_user_data(100);
+        let builder = MerkleTree::builder(TAG_LEAF, TAG_BRANCH);
+
+        let mut reports = 0;
+        let cancelled = builder.build_with_progress(user_data.iter().cloned(), |progress| {
+            reports += 1;
+            match progress {
+                BuildProgress::LevelCompleted { level: 3, .. } => ControlFlow::Break(()),
+                _ => ControlFlow::Continue(()),
+            }
+        });
+        assert_eq!(cancelled.err(), Some(MerkleTreeError::BuildCancelled));
+        assert_eq!(reports, 4);
+
+        let cancelled = builder.build_with_progress(user_data, |_| ControlFlow::Break(()));
+        assert_eq!(cancelled.err(), Some(MerkleTreeError::BuildCancelled));
+
+        let mut reports = Vec::new();
+        let empty = builder
+            .build_with_progress(Vec::new(), |progress| {
+                reports.push(progress);
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+        assert!(empty.root().is_none());
+        assert_eq!(reports, vec![BuildProgress::LeavesHashed { hashed: 0 }]);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[rstest]
+    #[case(OddLeafPolicy::DuplicateLast)]
+    #[case(OddLeafPolicy::PromoteOdd)]
+    #[case(OddLeafPolicy::PadWithEmptyHash)]
+    fn it_can_build_in_parallel(#[case] odd_leaf_policy: OddLeafPolicy) {
+        for n in [0, 1, 2, 7, 100] {
+            let user_data = generate_random_user_data(n);
+            let builder =
+                MerkleTree::builder(TAG_LEAF, TAG_BRANCH).odd_leaf_policy(odd_leaf_policy);
+
+            let tree = builder.clone().parallel(true).build(&user_data);
+            assert_eq!(tree.root(), builder.build(&user_data).root());
+            assert_eq!(tree.to_bytes(), builder.build(&user_data).to_bytes());
+        }
+    }
+}
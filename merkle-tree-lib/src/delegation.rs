@@ -0,0 +1,313 @@
+use std::ops::Range;
+
+use crate::{
+    level_sizes, DomainSeparation, LeafEncode, MerkleProof, MerkleTree, MerkleTreeError,
+    NodeDirection, OddLeafPolicy, TagHasher,
+};
+
+/// Everything a regional service needs to serve proofs for one subtree of a tree.
+///
+/// The bundle holds the user data of the leaves below the subtree root, the subtree root
+/// itself and a proof of the subtree root under the global root. Proofs generated from the
+/// bundle are regular `MerkleProof`s that clients verify against the global root.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DelegationBundle<T> {
+    /// The level of the subtree root, counted from the leaves at 0.
+    pub level: u32,
+    /// The position of the subtree root within its level.
+    pub position: usize,
+    /// The number of leaves of the whole tree.
+    pub leaf_count: usize,
+    pub leaves: Vec<T>,
+    pub subtree_root: String,
+    pub subtree_proof: MerkleProof,
+}
+
+/// Calculates the leaves covered by the node at `position` on `level`.
+///
+/// # Returns
+///
+/// An `Option` containing the range of leaf positions, `None` if there is no such node.
+fn subtree_leaf_range(leaf_count: usize, level: u32, position: usize) -> Option<Range<usize>> {
+    let sizes = level_sizes(leaf_count);
+    if leaf_count == 0 || position >= *sizes.get(level as usize)? {
+        return None;
+    }
+
+    let width = 1usize.checked_shl(level)?;
+    let start = position.checked_mul(width)?;
+    Some(start..leaf_count.min(start.saturating_add(width)))
+}
+
+/// Builds the levels of a subtree with the given height from its leaf hashes.
+///
+/// Like the whole tree, the last node of a level with an odd number of nodes is paired with
+/// itself, including a single node that has not reached the subtree height yet.
+fn subtree_levels(tag_branch: &str, leaf_hashes: Vec<Vec<u8>>, height: u32) -> Vec<Vec<Vec<u8>>> {
+    let tag_branch = TagHasher::new(tag_branch);
+    let mut levels = vec![leaf_hashes];
+
+    for _ in 0..height {
+        let next = levels
+            .last()
+            .unwrap()
+            .chunks(2)
+            .map(|pair| tag_branch.hash_parts(&[&pair[0], pair.last().unwrap()]))
+            .collect();
+        levels.push(next);
+    }
+
+    levels
+}
+
+/// Finds the sibling of the node at `position` and the side it sits on.
+///
+/// The last node of an odd level is its own sibling.
+fn sibling_of<'a>(level: &[&'a [u8]], position: usize) -> (&'a [u8], NodeDirection) {
+    if position & 1 == 0 {
+        (
+            level.get(position + 1).unwrap_or(&level[position]),
+            NodeDirection::Right,
+        )
+    } else {
+        (level[position - 1], NodeDirection::Left)
+    }
+}
+
+impl<T> MerkleTree<T>
+where
+    T: Clone + LeafEncode,
+{
+    /// Exports the subtree rooted at the given node as a delegation bundle.
+    ///
+    /// # Arguments
+    ///
+    /// * `level`: The level of the subtree root, counted from the leaves at 0.
+    /// * `position`: The position of the subtree root within its level. The subtree covers
+    ///   the leaves from `position * 2^level` up to, but not including, `(position + 1) * 2^level`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `DelegationBundle`, `MerkleTreeError::NodeNotFound` if there is
+    /// no such node, `MerkleTreeError::UnsupportedLayout` if the tree is not built with the
+    /// default domain separation and odd leaf policy, which bundles cannot be verified with.
+    pub fn delegate(
+        &self,
+        level: u32,
+        position: usize,
+    ) -> Result<DelegationBundle<T>, MerkleTreeError> {
+        if self.domain_separation != DomainSeparation::Tags
+            || self.odd_leaf_policy != OddLeafPolicy::DuplicateLast
+        {
+            return Err(MerkleTreeError::UnsupportedLayout);
+        }
+
+        let range = subtree_leaf_range(self.leaf_count, level, position)
+            .ok_or(MerkleTreeError::NodeNotFound { level, position })?;
+        let levels = self.levels();
+
+        let mut subtree_proof = MerkleProof::new(position);
+        let mut current = position;
+        for nodes in &levels[level as usize..levels.len() - 1] {
+            let hashes: Vec<&[u8]> = nodes.iter().map(|node| node.hash.as_slice()).collect();
+            let (sibling, direction) = sibling_of(&hashes, current);
+            subtree_proof.add_sibling(hex::encode(sibling), direction);
+            current /= 2;
+        }
+
+        Ok(DelegationBundle {
+            level,
+            position,
+            leaf_count: self.leaf_count,
+            leaves: levels[0][range]
+                .iter()
+                .filter_map(|leaf| leaf.user_data.clone())
+                .collect(),
+            subtree_root: hex::encode(levels[level as usize][position].hash),
+            subtree_proof,
+        })
+    }
+}
+
+impl<T> DelegationBundle<T>
+where
+    T: LeafEncode,
+{
+    /// Returns the positions of the delegated leaves in the whole tree.
+    pub fn leaf_range(&self) -> Option<Range<usize>> {
+        subtree_leaf_range(self.leaf_count, self.level, self.position)
+    }
+
+    fn levels(&self, tag_leaf: &str, tag_branch: &str) -> Vec<Vec<Vec<u8>>> {
+        let tag_leaf = TagHasher::new(tag_leaf);
+        let leaf_hashes = self
+            .leaves
+            .iter()
+            .map(|data| tag_leaf.hash(&data.serialize()))
+            .collect();
+
+        subtree_levels(tag_branch, leaf_hashes, self.level)
+    }
+
+    /// Verifies the bundle end to end against the global root.
+    ///
+    /// The leaves must hash up to the subtree root, and the subtree root must hash up to
+    /// the global root with the subtree proof.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_leaf`: The tag used for hashing leaf nodes.
+    /// * `tag_branch`: The tag used for hashing branch nodes.
+    /// * `root`: The hex encoded root hash of the whole tree.
+    pub fn verify(&self, tag_leaf: &str, tag_branch: &str, root: &str) -> bool {
+        let Some(range) = self.leaf_range() else {
+            return false;
+        };
+        if range.len() != self.leaves.len() || self.subtree_proof.leaf_index != self.position {
+            return false;
+        }
+
+        let levels = self.levels(tag_leaf, tag_branch);
+        let subtree_root = &levels[self.level as usize][0];
+
+        hex::encode(subtree_root) == self.subtree_root
+            && self
+                .subtree_proof
+                .compute_root(tag_branch, subtree_root)
+                .is_some_and(|computed| hex::encode(computed) == root)
+    }
+
+    /// Generates an inclusion proof for a delegated leaf, verifiable against the global root.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_leaf`: The tag used for hashing leaf nodes.
+    /// * `tag_branch`: The tag used for hashing branch nodes.
+    /// * `index`: The position of the leaf within the bundle, starting at 0.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `MerkleProof`, a `MerkleTreeError` if the index is out of range
+    /// or the bundle does not describe a node of the tree.
+    pub fn proof(
+        &self,
+        tag_leaf: &str,
+        tag_branch: &str,
+        index: usize,
+    ) -> Result<MerkleProof, MerkleTreeError> {
+        let start = self
+            .leaf_range()
+            .ok_or(MerkleTreeError::NodeNotFound {
+                level: self.level,
+                position: self.position,
+            })?
+            .start;
+        if index >= self.leaves.len() {
+            return Err(MerkleTreeError::LeafIndexOutOfRange {
+                index,
+                leaf_count: self.leaves.len(),
+            });
+        }
+
+        let levels = self.levels(tag_leaf, tag_branch);
+        let mut proof = MerkleProof::new(start + index);
+        let mut current = index;
+        for nodes in &levels[..self.level as usize] {
+            let hashes: Vec<&[u8]> = nodes.iter().map(Vec::as_slice).collect();
+            let (sibling, direction) = sibling_of(&hashes, current);
+            proof.add_sibling(hex::encode(sibling), direction);
+            current /= 2;
+        }
+
+        for (hash, direction) in self
+            .subtree_proof
+            .hashes
+            .iter()
+            .zip(self.subtree_proof.directions.iter())
+        {
+            proof.add_sibling(hash.clone(), direction.clone());
+        }
+
+        Ok(proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::generate_random_user_data;
+    use crate::{MerkleTree, MerkleTreeError, OddLeafPolicy};
+    use rstest::rstest;
+
+    const TAG_LEAF: &str = "ProofOfReserve_Leaf";
+    const TAG_BRANCH: &str = "ProofOfReserve_Branch";
+
+    #[rstest]
+    #[case(8, 0, 3)]
+    #[case(8, 2, 1)]
+    #[case(8, 3, 0)]
+    #[case(5, 2, 1)]
+    #[case(5, 1, 2)]
+    #[case(13, 2, 3)]
+    #[case(1, 0, 0)]
+    fn it_can_serve_proofs_from_a_delegation_bundle(
+        #[case] n: usize,
+        #[case] level: u32,
+        #[case] position: usize,
+    ) {
+        let user_data = generate_random_user_data(n);
+        let tree = MerkleTree::build(TAG_LEAF, TAG_BRANCH, &user_data);
+        let root = tree.root().unwrap();
+
+        let bundle = tree.delegate(level, position).unwrap();
+        assert!(bundle.verify(TAG_LEAF, TAG_BRANCH, &root));
+
+        let range = bundle.leaf_range().unwrap();
+        for (index, global_index) in range.enumerate() {
+            let proof = bundle.proof(TAG_LEAF, TAG_BRANCH, index).unwrap();
+            assert_eq!(proof, tree.proof(global_index).unwrap());
+            assert!(proof.verify(TAG_LEAF, TAG_BRANCH, &user_data[global_index], &root));
+        }
+    }
+
+    #[test]
+    fn it_can_reject_tampered_delegation_bundle() {
+        let user_data = generate_random_user_data(8);
+        let tree = MerkleTree::build(TAG_LEAF, TAG_BRANCH, &user_data);
+        let root = tree.root().unwrap();
+
+        assert!(matches!(
+            tree.delegate(2, 2),
+            Err(MerkleTreeError::NodeNotFound {
+                level: 2,
+                position: 2
+            })
+        ));
+        assert!(tree.delegate(4, 0).is_err());
+
+        let promoted = MerkleTree::build_with_odd_leaf_policy(
+            TAG_LEAF,
+            TAG_BRANCH,
+            &user_data,
+            OddLeafPolicy::PromoteOdd,
+        );
+        assert!(matches!(
+            promoted.delegate(2, 1),
+            Err(MerkleTreeError::UnsupportedLayout)
+        ));
+
+        let bundle = tree.delegate(2, 1).unwrap();
+
+        let mut changed_balance = bundle.clone();
+        changed_balance.leaves[0].balance += 1;
+        assert!(!changed_balance.verify(TAG_LEAF, TAG_BRANCH, &root));
+
+        let mut dropped_leaf = bundle.clone();
+        dropped_leaf.leaves.pop();
+        assert!(!dropped_leaf.verify(TAG_LEAF, TAG_BRANCH, &root));
+
+        let mut moved = bundle.clone();
+        moved.position = 0;
+        assert!(!moved.verify(TAG_LEAF, TAG_BRANCH, &root));
+    }
+}
@@ -0,0 +1,95 @@
+//! Fixed vectors that every target must reproduce bit for bit.
+//!
+//! Roots, snapshots, proofs and signed bytes are compared across machines, so they may not depend
+//! on the pointer width or the endianness of the platform. CI runs these tests on 32 and 64
+//! bit, little and big endian targets and on wasm32.
+
+use sha2::{Digest, Sha256};
+
+use crate::util::generate_random_user_data;
+use crate::{MerkleRootHasher, MerkleSumTree, MerkleTree, ProofEnvelope};
+
+const TAG_LEAF: &str = "ProofOfReserve_Leaf";
+const TAG_BRANCH: &str = "ProofOfReserve_Branch";
+const LEAF_COUNT: usize = 13;
+
+const EXPECTED_ROOT: &str = "6a1486d4ea0b84565aab139c1fa030c8b1b1793f50d683d46fe60b7ff06aa69e";
+const EXPECTED_SNAPSHOT_DIGEST: &str =
+    "24397d10ddfde2fa9782e7ad128c1b0e5cb7ab5c8286bbe5c1d6cf6214c4cbba";
+const EXPECTED_SIGNING_BYTES_DIGEST: &str =
+    "56031f888bae81687d7bde8c866acdea05690ebdf23d6e75ac94363e564a7011";
+const EXPECTED_PROOF_DIGEST: &str =
+    "fa233518938a95b091686e450d563741da245b850bfb026396f22d223d172304";
+const EXPECTED_PROOF_BYTES_DIGEST: &str =
+    "7b5aa44a50fbc36c4871e50ece4a023a9d943c31e56f246950fb2228aab9865b";
+const EXPECTED_SUM_ROOT: &str = "44755667e325fe28c5c445ac4f0d91d0940d79c353cac985671d9d0f5edc0318";
+
+fn digest(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+#[test]
+fn it_can_reproduce_the_root() {
+    let user_data = generate_random_user_data(LEAF_COUNT);
+
+    assert_eq!(
+        MerkleTree::build(TAG_LEAF, TAG_BRANCH, &user_data)
+            .root()
+            .unwrap(),
+        EXPECTED_ROOT
+    );
+    assert_eq!(
+        MerkleRootHasher::root_of(TAG_LEAF, TAG_BRANCH, user_data).unwrap(),
+        EXPECTED_ROOT
+    );
+}
+
+#[test]
+fn it_can_reproduce_the_snapshot() {
+    let user_data = generate_random_user_data(LEAF_COUNT);
+    let bytes = MerkleTree::build(TAG_LEAF, TAG_BRANCH, &user_data).to_bytes();
+
+    assert_eq!(digest(&bytes), EXPECTED_SNAPSHOT_DIGEST);
+}
+
+#[test]
+fn it_can_reproduce_the_proof() {
+    let user_data = generate_random_user_data(LEAF_COUNT);
+    let proof = MerkleTree::build(TAG_LEAF, TAG_BRANCH, &user_data)
+        .proof(11)
+        .unwrap();
+
+    let siblings: String = proof
+        .to_vec()
+        .iter()
+        .map(|(hash, direction)| format!("{hash}:{direction},"))
+        .collect();
+    assert_eq!(digest(siblings.as_bytes()), EXPECTED_PROOF_DIGEST);
+    // the binary form carries the leaf index as a `u64`
+    assert_eq!(
+        digest(&proof.to_bytes().unwrap()),
+        EXPECTED_PROOF_BYTES_DIGEST
+    );
+}
+
+#[test]
+fn it_can_reproduce_the_signing_bytes() {
+    let user_data = generate_random_user_data(LEAF_COUNT);
+    let tree = MerkleTree::build(TAG_LEAF, TAG_BRANCH, &user_data);
+    let envelope = ProofEnvelope::new(tree.root().unwrap(), tree.proof(11).unwrap())
+        .with_metadata("2026-10-16", 1_791_000_000);
+
+    assert_eq!(
+        digest(&envelope.signing_bytes()),
+        EXPECTED_SIGNING_BYTES_DIGEST
+    );
+}
+
+#[test]
+fn it_can_reproduce_the_sum_root() {
+    let user_data = generate_random_user_data(LEAF_COUNT);
+    let tree = MerkleSumTree::build(TAG_LEAF, TAG_BRANCH, &user_data).unwrap();
+
+    assert_eq!(tree.root().unwrap(), EXPECTED_SUM_ROOT);
+    assert_eq!(tree.root_sum(), Some(91_000));
+}
@@ -0,0 +1,169 @@
+use crate::{tagged_hash, TagHasher};
+
+/// Prefixes the input of a leaf hash in `DomainSeparation::Prefixed` trees.
+const LEAF_PREFIX: u8 = 0x00;
+/// Prefixes the input of a branch hash in `DomainSeparation::Prefixed` trees.
+const BRANCH_PREFIX: u8 = 0x01;
+/// Prefixes the input of the root commitment in `DomainSeparation::Prefixed` trees.
+const ROOT_PREFIX: u8 = 0x02;
+/// Tags the root commitment, the top node it commits to is already tagged by the tree.
+const ROOT_TAG: &str = "MerkleTree_Root";
+
+/// How the hashes of leaves, branches and the root are kept apart from each other.
+///
+/// With `Tags` a leaf is only told apart from a branch by the tag it is hashed with. Trees
+/// that use the same tag for both, like Bitcoin's, accept the 64 bytes of two child hashes
+/// as the data of a leaf, so a branch can be passed off as a leaf. Duplicating the last node
+/// of odd levels also gives `[a, b, c]` and `[a, b, c, c]` the same root.
+///
+/// `Prefixed` closes both gaps: leaf and branch inputs start with different bytes whatever
+/// the tags are, and the root commits to the number of leaves. Verifiers have to know that
+/// number to check a proof. `MerkleRootHasher`, `DelegationBundle` and the `reference`
+/// module only compute `Tags` hashes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum DomainSeparation {
+    /// Leaves and branches are only separated by their tags.
+    #[default]
+    Tags,
+    /// Leaf and branch inputs are prefixed with `0x00` and `0x01`, and the root is
+    /// `tagged_hash("MerkleTree_Root", 0x02 || leaf_count || top)` with the leaf count as a
+    /// little endian `u64`.
+    Prefixed,
+}
+
+impl DomainSeparation {
+    /// Calculates the hash of a leaf from its serialized user data.
+    pub fn leaf_hash(self, tag_leaf: &str, data: &[u8]) -> Vec<u8> {
+        self.leaf_hash_with(&TagHasher::new(tag_leaf), data)
+    }
+
+    /// Calculates the hash of a leaf like `leaf_hash`, with the tag already hashed.
+    pub fn leaf_hash_with(self, tag_leaf: &TagHasher, data: &[u8]) -> Vec<u8> {
+        match self {
+            DomainSeparation::Tags => tag_leaf.hash(data),
+            DomainSeparation::Prefixed => tag_leaf.hash_parts(&[&[LEAF_PREFIX], data]),
+        }
+    }
+
+    /// Calculates the hash of a branch from the hashes of its children.
+    pub fn branch_hash(self, tag_branch: &str, left: &[u8], right: &[u8]) -> Vec<u8> {
+        self.branch_hash_with(&TagHasher::new(tag_branch), left, right)
+    }
+
+    /// Calculates the hash of a branch like `branch_hash`, with the tag already hashed.
+    pub fn branch_hash_with(self, tag_branch: &TagHasher, left: &[u8], right: &[u8]) -> Vec<u8> {
+        match self {
+            DomainSeparation::Tags => tag_branch.hash_parts(&[left, right]),
+            DomainSeparation::Prefixed => tag_branch.hash_parts(&[&[BRANCH_PREFIX], left, right]),
+        }
+    }
+
+    /// Calculates the root of a tree from the hash of its top node.
+    ///
+    /// # Arguments
+    ///
+    /// * `leaf_count`: The number of leaves of the tree.
+    /// * `top`: The hash of the top node, the only node of the last level.
+    ///
+    /// # Returns
+    ///
+    /// `top` itself with `Tags`, the commitment to `top` and `leaf_count` with `Prefixed`.
+    pub fn root_hash(self, leaf_count: usize, top: &[u8]) -> Vec<u8> {
+        match self {
+            DomainSeparation::Tags => top.to_vec(),
+            DomainSeparation::Prefixed => tagged_hash(
+                ROOT_TAG,
+                &[&[ROOT_PREFIX], &(leaf_count as u64).to_le_bytes()[..], top].concat(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::generate_random_user_data;
+    use crate::{LeafEncode, MerkleTree};
+    use rstest::rstest;
+
+    // the tags Bitcoin style trees use for leaves and branches alike
+    const TAG: &str = "Bitcoin_Transaction";
+
+    #[derive(Clone, Debug, Default)]
+    struct Bytes(Vec<u8>);
+
+    impl LeafEncode for Bytes {
+        fn serialize(&self) -> Vec<u8> {
+            self.0.clone()
+        }
+    }
+
+    #[rstest]
+    #[case(DomainSeparation::Tags, true)]
+    #[case(DomainSeparation::Prefixed, false)]
+    fn it_can_reject_a_branch_replayed_as_a_leaf(
+        #[case] domain_separation: DomainSeparation,
+        #[case] replayable: bool,
+    ) {
+        let leaves: Vec<Bytes> = (0u8..4).map(|i| Bytes(vec![i])).collect();
+        let tree = MerkleTree::build_with_domain_separation(TAG, TAG, &leaves, domain_separation);
+        let root = tree.root().unwrap();
+
+        // the left branch above the leaves 0 and 1, presented as a leaf with the 64 bytes of
+        // its children as data and the sibling branch as the only proof step
+        let forged_leaf = Bytes(
+            [
+                domain_separation.leaf_hash(TAG, &leaves[0].0),
+                domain_separation.leaf_hash(TAG, &leaves[1].0),
+            ]
+            .concat(),
+        );
+        let mut forged_proof = tree.proof(0).unwrap();
+        forged_proof.hashes.drain(..1);
+        forged_proof.directions.drain(..1);
+
+        assert_eq!(
+            forged_proof.verify_with(domain_separation, 4, TAG, TAG, &forged_leaf, &root),
+            replayable
+        );
+        assert!(tree.proof(0).unwrap().verify_with(
+            domain_separation,
+            4,
+            TAG,
+            TAG,
+            &leaves[0],
+            &root
+        ));
+    }
+
+    #[rstest]
+    #[case(DomainSeparation::Tags, true)]
+    #[case(DomainSeparation::Prefixed, false)]
+    fn it_can_commit_to_the_leaf_count(
+        #[case] domain_separation: DomainSeparation,
+        #[case] same_root: bool,
+    ) {
+        let user_data = generate_random_user_data(3);
+        let mut duplicated = user_data.clone();
+        duplicated.push(user_data[2].clone());
+
+        let build = |input| {
+            MerkleTree::build_with_domain_separation(TAG, TAG, input, domain_separation).root()
+        };
+
+        assert_eq!(build(&user_data) == build(&duplicated), same_root);
+    }
+
+    #[test]
+    fn it_can_keep_the_tags_only_hashes() {
+        let user_data = generate_random_user_data(5);
+
+        assert_eq!(
+            MerkleTree::build_with_domain_separation(TAG, TAG, &user_data, DomainSeparation::Tags)
+                .root(),
+            MerkleTree::build(TAG, TAG, &user_data).root()
+        );
+    }
+}
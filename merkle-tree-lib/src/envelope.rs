@@ -0,0 +1,195 @@
+use crate::{LeafEncode, MerkleProof};
+
+/// Separates envelope signatures from any other use of the same signing key.
+const ENVELOPE_DOMAIN: &[u8] = b"merkle-tree-lib/proof-envelope/v1";
+
+/// Tells which snapshot a proof was generated from and when.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProofMetadata {
+    pub snapshot_id: String,
+    /// Seconds since the Unix epoch.
+    pub generated_at: u64,
+}
+
+/// A proof together with the root it proves against, optional metadata and an optional
+/// signature of the issuing server over all of it.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProofEnvelope {
+    pub root: String,
+    pub proof: MerkleProof,
+    pub metadata: Option<ProofMetadata>,
+    /// Hex encoded signature over `signing_bytes`.
+    pub signature: Option<String>,
+}
+
+impl ProofEnvelope {
+    pub fn new(root: String, proof: MerkleProof) -> Self {
+        ProofEnvelope {
+            root,
+            proof,
+            metadata: None,
+            signature: None,
+        }
+    }
+
+    /// Attaches the snapshot id and the generation time to the envelope.
+    ///
+    /// # Arguments
+    ///
+    /// * `snapshot_id`: The identifier of the tree snapshot the proof was generated from.
+    /// * `generated_at`: The generation time in seconds since the Unix epoch.
+    pub fn with_metadata(mut self, snapshot_id: &str, generated_at: u64) -> Self {
+        self.metadata = Some(ProofMetadata {
+            snapshot_id: snapshot_id.to_string(),
+            generated_at,
+        });
+        self
+    }
+
+    /// Encodes everything the signature covers: the root, the proof and the metadata.
+    ///
+    /// Strings and lists are prefixed with their length as `u32` and integers are little
+    /// endian, so two different envelopes can never produce the same bytes.
+    ///
+    /// # Returns
+    ///
+    /// The canonical bytes as a `Vec<u8>`.
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        fn push_str(bytes: &mut Vec<u8>, value: &str) {
+            bytes.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(value.as_bytes());
+        }
+
+        let mut bytes = ENVELOPE_DOMAIN.to_vec();
+        push_str(&mut bytes, &self.root);
+
+        bytes.extend_from_slice(&(self.proof.leaf_index as u64).to_le_bytes());
+        bytes.extend_from_slice(&(self.proof.hashes.len() as u32).to_le_bytes());
+        for (hash, direction) in self.proof.to_vec() {
+            push_str(&mut bytes, &hash);
+            bytes.push(direction);
+        }
+
+        match &self.metadata {
+            Some(metadata) => {
+                bytes.push(1);
+                push_str(&mut bytes, &metadata.snapshot_id);
+                bytes.extend_from_slice(&metadata.generated_at.to_le_bytes());
+            }
+            None => bytes.push(0),
+        }
+
+        bytes
+    }
+
+    /// Verifies that the user data is included in the tree with the envelope's root.
+    ///
+    /// This does not check the signature, see `verify_signature`.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_leaf`: The tag used for hashing leaf nodes.
+    /// * `tag_branch`: The tag used for hashing branch nodes.
+    /// * `data`: The user data the proof was generated for.
+    pub fn verify<T>(&self, tag_leaf: &str, tag_branch: &str, data: &T) -> bool
+    where
+        T: LeafEncode,
+    {
+        self.proof.verify(tag_leaf, tag_branch, data, &self.root)
+    }
+
+    /// Signs the envelope with the server's Ed25519 key, replacing any previous signature.
+    #[cfg(feature = "signing")]
+    pub fn sign(mut self, key: &ed25519_dalek::SigningKey) -> Self {
+        use ed25519_dalek::Signer;
+
+        let signature = key.sign(&self.signing_bytes());
+        self.signature = Some(hex::encode(signature.to_bytes()));
+        self
+    }
+
+    /// Verifies the envelope's signature against the server's Ed25519 public key.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the envelope is signed and the signature covers its current content, `false` otherwise.
+    #[cfg(feature = "signing")]
+    pub fn verify_signature(&self, key: &ed25519_dalek::VerifyingKey) -> bool {
+        let Some(signature) = self
+            .signature
+            .as_ref()
+            .and_then(|signature| hex::decode(signature).ok())
+            .and_then(|signature| ed25519_dalek::Signature::from_slice(&signature).ok())
+        else {
+            return false;
+        };
+
+        key.verify_strict(&self.signing_bytes(), &signature).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::generate_random_user_data;
+    use crate::MerkleTree;
+
+    const TAG_LEAF: &str = "ProofOfReserve_Leaf";
+    const TAG_BRANCH: &str = "ProofOfReserve_Branch";
+
+    fn build_envelope() -> (Vec<crate::util::UserData>, ProofEnvelope) {
+        let user_data = generate_random_user_data(5);
+        let tree = MerkleTree::build(TAG_LEAF, TAG_BRANCH, &user_data);
+        let envelope = ProofEnvelope::new(tree.root().unwrap(), tree.proof(2).unwrap())
+            .with_metadata("2026-10-16", 1_791_000_000);
+
+        (user_data, envelope)
+    }
+
+    #[test]
+    fn it_can_verify_envelope_inclusion() {
+        let (user_data, envelope) = build_envelope();
+
+        assert!(envelope.verify(TAG_LEAF, TAG_BRANCH, &user_data[2]));
+        assert!(!envelope.verify(TAG_LEAF, TAG_BRANCH, &user_data[3]));
+    }
+
+    #[test]
+    fn it_can_bind_metadata_into_signing_bytes() {
+        let (_user_data, envelope) = build_envelope();
+
+        let other_snapshot = envelope.clone().with_metadata("2026-10-17", 1_791_000_000);
+        let other_time = envelope.clone().with_metadata("2026-10-16", 1_791_000_001);
+        let without_metadata = ProofEnvelope {
+            metadata: None,
+            ..envelope.clone()
+        };
+
+        assert_ne!(envelope.signing_bytes(), other_snapshot.signing_bytes());
+        assert_ne!(envelope.signing_bytes(), other_time.signing_bytes());
+        assert_ne!(envelope.signing_bytes(), without_metadata.signing_bytes());
+    }
+
+    #[cfg(feature = "signing")]
+    #[test]
+    fn it_can_sign_and_verify_envelope() {
+        let (_user_data, envelope) = build_envelope();
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7; 32]);
+        let other_key = ed25519_dalek::SigningKey::from_bytes(&[8; 32]);
+
+        assert!(!envelope.verify_signature(&key.verifying_key()));
+
+        let signed = envelope.sign(&key);
+        assert!(signed.verify_signature(&key.verifying_key()));
+        assert!(!signed.verify_signature(&other_key.verifying_key()));
+
+        let tampered = signed.clone().with_metadata("2026-10-16", 0);
+        let tampered = ProofEnvelope {
+            signature: signed.signature.clone(),
+            ..tampered
+        };
+        assert!(!tampered.verify_signature(&key.verifying_key()));
+    }
+}
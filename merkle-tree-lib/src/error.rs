@@ -0,0 +1,92 @@
+use std::fmt;
+
+/// The reasons an operation on a tree or a snapshot fails.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MerkleTreeError {
+    /// There is no leaf at the given position.
+    LeafIndexOutOfRange { index: usize, leaf_count: usize },
+    /// A proof was requested for an empty set of leaves.
+    NoLeafIndices,
+    /// No leaf is indexed under the requested key.
+    KeyNotFound,
+    /// A leaf has the key that was to be proven missing.
+    KeyIncluded,
+    /// There is no node at the given level and position.
+    NodeNotFound { level: u32, position: usize },
+    /// The values of a sum tree add up to more than `u64::MAX`.
+    SumOverflow,
+    /// The snapshot is truncated, has trailing bytes or an unknown header.
+    MalformedSnapshot,
+    /// The user data of a leaf in the snapshot cannot be deserialized.
+    InvalidLeafData { index: usize },
+    /// The operation only supports trees built with the default `DomainSeparation` and
+    /// `OddLeafPolicy`.
+    UnsupportedLayout,
+    /// The version is not kept by the `VersionedMerkleTree`, or does not exist yet.
+    VersionNotFound { version: u64 },
+    /// The encoded proof is truncated, has trailing bytes or a sibling that is not a 32 byte
+    /// hash.
+    MalformedProof,
+    /// The stored hash of a leaf does not match its user data.
+    LeafHashMismatch { index: usize },
+    /// The stored hash of a branch does not match its children, `level` counts from the
+    /// leaves at 0.
+    BranchHashMismatch { level: u32, position: usize },
+    /// The progress callback of `MerkleTreeBuilder::build_with_progress` stopped the build.
+    BuildCancelled,
+    /// The `NodeStore` does not hold the number of nodes of a tree with the given leaves.
+    NodeCountMismatch { expected: usize, stored: usize },
+    /// The deserialized nodes are not linked like the nodes of a tree with their leaf count.
+    MalformedTree,
+    /// A leaf of the deserialized tree has no user data.
+    MissingLeafData { index: usize },
+}
+
+impl fmt::Display for MerkleTreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MerkleTreeError::LeafIndexOutOfRange { index, leaf_count } => {
+                write!(
+                    f,
+                    "leaf {index} is out of range, the tree has {leaf_count} leaves"
+                )
+            }
+            MerkleTreeError::NoLeafIndices => write!(f, "no leaf indices given"),
+            MerkleTreeError::KeyNotFound => write!(f, "no leaf found for the key"),
+            MerkleTreeError::KeyIncluded => write!(f, "a leaf has the key, it is not missing"),
+            MerkleTreeError::NodeNotFound { level, position } => {
+                write!(f, "no node at level {level} position {position}")
+            }
+            MerkleTreeError::SumOverflow => write!(f, "sum of the leaf values overflows u64"),
+            MerkleTreeError::MalformedSnapshot => write!(f, "snapshot is malformed"),
+            MerkleTreeError::InvalidLeafData { index } => {
+                write!(f, "user data of leaf {index} cannot be deserialized")
+            }
+            MerkleTreeError::UnsupportedLayout => write!(
+                f,
+                "only trees with the default domain separation and odd leaf policy are supported"
+            ),
+            MerkleTreeError::VersionNotFound { version } => {
+                write!(f, "version {version} is not kept")
+            }
+            MerkleTreeError::MalformedProof => write!(f, "proof is malformed"),
+            MerkleTreeError::LeafHashMismatch { index } => {
+                write!(f, "hash of leaf {index} does not match its user data")
+            }
+            MerkleTreeError::BranchHashMismatch { level, position } => write!(
+                f,
+                "hash of node {position} on level {level} does not match its children"
+            ),
+            MerkleTreeError::BuildCancelled => write!(f, "tree build was cancelled"),
+            MerkleTreeError::NodeCountMismatch { expected, stored } => {
+                write!(f, "store holds {stored} nodes, the tree has {expected}")
+            }
+            MerkleTreeError::MalformedTree => write!(f, "nodes are not linked as a tree"),
+            MerkleTreeError::MissingLeafData { index } => {
+                write!(f, "leaf {index} has no user data")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MerkleTreeError {}
@@ -0,0 +1,89 @@
+//! `Arbitrary` trees, to fuzz code built on the crate without writing the generators.
+//!
+//! `UserData`, `Salted` leaves, `DomainSeparation` and `OddLeafPolicy` derive `Arbitrary`
+//! with the `arbitrary` feature, `Salted` also needs `salted`. A `MerkleTree` is built from arbitrary leaves with an
+//! arbitrary layout and the tags below, which the checks of `invariants` need.
+
+use arbitrary::{Arbitrary, Unstructured};
+
+use crate::{LeafEncode, MerkleTree};
+
+/// The tag arbitrary trees hash their leaves with.
+pub const TAG_LEAF: &str = "Arbitrary_Leaf";
+/// The tag arbitrary trees hash their branches with.
+pub const TAG_BRANCH: &str = "Arbitrary_Branch";
+
+impl<'a, T> Arbitrary<'a> for MerkleTree<T>
+where
+    T: Arbitrary<'a> + Clone + LeafEncode,
+{
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let domain_separation = u.arbitrary()?;
+        let odd_leaf_policy = u.arbitrary()?;
+        // sized from the input, unlike `Vec::arbitrary` which rarely grows past a few leaves
+        let len = u.arbitrary_len::<T>()?;
+        let leaves = (0..len)
+            .map(|_| T::arbitrary(u))
+            .collect::<arbitrary::Result<Vec<T>>>()?;
+
+        Ok(MerkleTree::build_with(
+            TAG_LEAF,
+            TAG_BRANCH,
+            &leaves,
+            domain_separation,
+            odd_leaf_policy,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::invariants::{check_proofs, check_snapshot};
+    use crate::util::UserData;
+    #[cfg(feature = "salted")]
+    use crate::{invariants::check_leaf_encoding, Salted};
+    use rstest::rstest;
+
+    /// Bytes that look random enough to drive `Unstructured`, from a linear congruential
+    /// generator.
+    fn noise(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6_364_136_223_846_793_005)
+                    .wrapping_add(1_442_695_040_888_963_407);
+                (state >> 56) as u8
+            })
+            .collect()
+    }
+
+    #[rstest]
+    #[case(0)]
+    #[case(64)]
+    #[case(1024)]
+    #[case(8192)]
+    fn it_can_check_the_invariants_of_arbitrary_trees(#[case] len: usize) {
+        for seed in 0..8 {
+            let bytes = noise(len, seed);
+
+            let tree = MerkleTree::<UserData>::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+            assert!(len < 8192 || tree.len() > 1);
+            assert_eq!(check_proofs(&tree, TAG_LEAF, TAG_BRANCH), Ok(()));
+            assert_eq!(check_snapshot(&tree), Ok(()));
+
+            #[cfg(feature = "salted")]
+            {
+                let tree =
+                    MerkleTree::<Salted<UserData>>::arbitrary(&mut Unstructured::new(&bytes))
+                        .unwrap();
+                assert_eq!(check_proofs(&tree, TAG_LEAF, TAG_BRANCH), Ok(()));
+                assert_eq!(check_snapshot(&tree), Ok(()));
+                for (_index, data, _hash) in tree.leaves() {
+                    assert_eq!(check_leaf_encoding(data), Ok(()));
+                }
+            }
+        }
+    }
+}
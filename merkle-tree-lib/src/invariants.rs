@@ -0,0 +1,218 @@
+//! Invariants every tree upholds, for property tests and fuzzers of code built on the crate.
+//!
+//! Each check returns the first violation it finds instead of panicking, so it can be
+//! asserted in a `proptest`, mapped into a fuzz target or logged by a canary in production.
+//! With the `arbitrary` feature the inputs can be generated with `fuzz`.
+
+use std::fmt;
+
+use crate::{LeafEncode, MerkleProof, MerkleTree, SnapshotData};
+
+/// The invariant a check found broken.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvariantViolation {
+    /// The proof generated for the leaf does not verify against the root.
+    ProofRejected { index: usize },
+    /// The proof of the leaf decodes to a different proof after `MerkleProof::to_bytes`.
+    ProofEncoding { index: usize },
+    /// The multi proof of all leaves does not verify against the root.
+    MultiProofRejected,
+    /// `SnapshotData::deserialize` does not restore what `LeafEncode::serialize` returned.
+    LeafEncoding,
+    /// The tree restored from `MerkleTree::to_bytes` differs from the original.
+    Snapshot,
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvariantViolation::ProofRejected { index } => {
+                write!(f, "proof of leaf {index} does not verify")
+            }
+            InvariantViolation::ProofEncoding { index } => {
+                write!(f, "proof of leaf {index} does not survive encoding")
+            }
+            InvariantViolation::MultiProofRejected => {
+                write!(f, "multi proof of all leaves does not verify")
+            }
+            InvariantViolation::LeafEncoding => {
+                write!(f, "user data does not survive serialization")
+            }
+            InvariantViolation::Snapshot => write!(f, "tree does not survive a snapshot"),
+        }
+    }
+}
+
+impl std::error::Error for InvariantViolation {}
+
+/// Checks that the proof generated for every leaf verifies and survives encoding, and that
+/// the multi proof of all leaves verifies.
+///
+/// # Arguments
+///
+/// * `tree`: The tree to check.
+/// * `tag_leaf`: The tag the tree was built with for hashing leaf nodes.
+/// * `tag_branch`: The tag the tree was built with for hashing branch nodes.
+///
+/// # Returns
+///
+/// `Ok(())` if every proof holds, otherwise the first `InvariantViolation` from the left.
+pub fn check_proofs<T>(
+    tree: &MerkleTree<T>,
+    tag_leaf: &str,
+    tag_branch: &str,
+) -> Result<(), InvariantViolation>
+where
+    T: Clone + LeafEncode,
+{
+    let Some(root) = tree.root() else {
+        return Ok(());
+    };
+    let domain_separation = tree.domain_separation();
+
+    let mut leaves = Vec::with_capacity(tree.len());
+    for (index, data, _hash) in tree.leaves() {
+        let proof = tree
+            .proof(index)
+            .map_err(|_| InvariantViolation::ProofRejected { index })?;
+        if !proof.verify_with(
+            domain_separation,
+            tree.len(),
+            tag_leaf,
+            tag_branch,
+            data,
+            &root,
+        ) {
+            return Err(InvariantViolation::ProofRejected { index });
+        }
+
+        let decoded = proof
+            .to_bytes()
+            .and_then(|bytes| MerkleProof::from_bytes(&bytes));
+        if decoded.as_ref() != Ok(&proof) {
+            return Err(InvariantViolation::ProofEncoding { index });
+        }
+        leaves.push(data.clone());
+    }
+
+    let indices: Vec<usize> = (0..tree.len()).collect();
+    let verified = tree.proof_for_indices(&indices).is_ok_and(|proof| {
+        proof.verify_with(domain_separation, tag_leaf, tag_branch, &leaves, &root)
+    });
+    if !verified {
+        return Err(InvariantViolation::MultiProofRejected);
+    }
+
+    Ok(())
+}
+
+/// Checks that deserializing the serialized user data gives it back.
+///
+/// # Returns
+///
+/// `Ok(())` if the user data serializes to the same bytes after the round trip,
+/// `InvariantViolation::LeafEncoding` otherwise.
+pub fn check_leaf_encoding<T>(data: &T) -> Result<(), InvariantViolation>
+where
+    T: LeafEncode + SnapshotData,
+{
+    let bytes = data.serialize();
+    match T::deserialize(&bytes) {
+        Some(restored) if restored.serialize() == bytes => Ok(()),
+        _ => Err(InvariantViolation::LeafEncoding),
+    }
+}
+
+/// Checks that restoring a snapshot of the tree gives back the same tree.
+///
+/// # Returns
+///
+/// `Ok(())` if the restored tree has the same root and writes the same snapshot,
+/// `InvariantViolation::Snapshot` otherwise.
+pub fn check_snapshot<T>(tree: &MerkleTree<T>) -> Result<(), InvariantViolation>
+where
+    T: Clone + LeafEncode + SnapshotData,
+{
+    let bytes = tree.to_bytes();
+    match MerkleTree::<T>::from_bytes(&bytes) {
+        Ok(restored) if restored.root() == tree.root() && restored.to_bytes() == bytes => Ok(()),
+        _ => Err(InvariantViolation::Snapshot),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{generate_random_user_data, UserData};
+    use crate::{DomainSeparation, OddLeafPolicy};
+    use rstest::rstest;
+
+    const TAG_LEAF: &str = "ProofOfReserve_Leaf";
+    const TAG_BRANCH: &str = "ProofOfReserve_Branch";
+
+    #[rstest]
+    #[case(DomainSeparation::Tags, OddLeafPolicy::DuplicateLast)]
+    #[case(DomainSeparation::Tags, OddLeafPolicy::PromoteOdd)]
+    #[case(DomainSeparation::Prefixed, OddLeafPolicy::PadWithEmptyHash)]
+    fn it_can_check_the_invariants_of_a_built_tree(
+        #[case] domain_separation: DomainSeparation,
+        #[case] odd_leaf_policy: OddLeafPolicy,
+    ) {
+        for n in [0, 1, 2, 5, 11] {
+            let user_data = generate_random_user_data(n);
+            let tree = MerkleTree::build_with(
+                TAG_LEAF,
+                TAG_BRANCH,
+                &user_data,
+                domain_separation,
+                odd_leaf_policy,
+            );
+
+            assert_eq!(check_proofs(&tree, TAG_LEAF, TAG_BRANCH), Ok(()));
+            assert_eq!(check_snapshot(&tree), Ok(()));
+            for data in &user_data {
+                assert_eq!(check_leaf_encoding(data), Ok(()));
+            }
+        }
+    }
+
+    #[test]
+    fn it_can_report_a_violated_invariant() {
+        let tree = MerkleTree::build(TAG_LEAF, TAG_BRANCH, &generate_random_user_data(5));
+        assert_eq!(
+            check_proofs(&tree, TAG_BRANCH, TAG_BRANCH),
+            Err(InvariantViolation::ProofRejected { index: 0 })
+        );
+
+        // flips a bit of the hash of the last leaf, stored right before the 6 branch hashes
+        let mut bytes = tree.to_bytes();
+        let position = bytes.len() - 7 * 32;
+        bytes[position] ^= 1;
+        let tampered = MerkleTree::<UserData>::from_bytes(&bytes).unwrap();
+        assert_eq!(
+            check_proofs(&tampered, TAG_LEAF, TAG_BRANCH),
+            Err(InvariantViolation::ProofRejected { index: 4 })
+        );
+
+        struct Truncated(String);
+
+        impl LeafEncode for Truncated {
+            fn serialize(&self) -> Vec<u8> {
+                self.0.as_bytes().to_vec()
+            }
+        }
+
+        impl SnapshotData for Truncated {
+            fn deserialize(bytes: &[u8]) -> Option<Self> {
+                let text = std::str::from_utf8(bytes).ok()?;
+                Some(Truncated(text.chars().take(3).collect()))
+            }
+        }
+
+        assert_eq!(check_leaf_encoding(&Truncated("abc".to_string())), Ok(()));
+        assert_eq!(
+            check_leaf_encoding(&Truncated("abcd".to_string())),
+            Err(InvariantViolation::LeafEncoding)
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::{LeafEncode, LeafRef, MerkleProof, MerkleTree, MerkleTreeError};
+
+/// A Merkle Tree with an index from a key to the leaf position.
+///
+/// Built with `MerkleTree::build_with_key`. Lookups by key are a hash map lookup followed
+/// by a walk from the root to the leaf, instead of a predicate scan over the whole tree.
+pub struct KeyedMerkleTree<T, K> {
+    tree: MerkleTree<T>,
+    index: HashMap<K, usize>,
+}
+
+impl<T, K> KeyedMerkleTree<T, K>
+where
+    T: Clone + LeafEncode,
+    K: Eq + Hash,
+{
+    /// Indexes the leaves of an existing tree, e.g. one loaded from a snapshot.
+    ///
+    /// # Arguments
+    ///
+    /// * `tree`: The tree to index.
+    /// * `key_fn`: A function that extracts the lookup key from the user data.
+    pub fn from_tree<F>(tree: MerkleTree<T>, key_fn: F) -> Self
+    where
+        F: Fn(&T) -> K,
+    {
+        let mut index = HashMap::with_capacity(tree.leaf_count);
+        if let Some(leaves) = tree.levels().first() {
+            for (i, leaf) in leaves.iter().enumerate() {
+                if let Some(data) = &leaf.user_data {
+                    index.entry(key_fn(data)).or_insert(i);
+                }
+            }
+        }
+
+        KeyedMerkleTree { tree, index }
+    }
+
+    /// Returns the underlying Merkle Tree.
+    pub fn tree(&self) -> &MerkleTree<T> {
+        &self.tree
+    }
+
+    /// Returns the position of the leaf with the given key.
+    pub fn index_of(&self, key: &K) -> Option<usize> {
+        self.index.get(key).copied()
+    }
+
+    /// Searches for the leaf with the given key.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the `LeafRef` if the key is indexed, `None` otherwise.
+    pub fn search_with_path(&self, key: &K) -> Option<LeafRef<'_, T>> {
+        self.index_of(key)
+            .and_then(|index| self.tree.search_index_with_path(index))
+    }
+
+    /// Generates an inclusion proof for the leaf with the given key.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `MerkleProof`, `MerkleTreeError::KeyNotFound` if the key is not indexed.
+    pub fn proof_for_key(&self, key: &K) -> Result<MerkleProof, MerkleTreeError> {
+        self.tree
+            .proof(self.index_of(key).ok_or(MerkleTreeError::KeyNotFound)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::generate_random_user_data;
+    use crate::{MerkleTree, MerkleTreeError};
+    use rstest::rstest;
+
+    const TAG_LEAF: &str = "ProofOfReserve_Leaf";
+    const TAG_BRANCH: &str = "ProofOfReserve_Branch";
+
+    #[rstest]
+    #[case(1)]
+    #[case(3)]
+    #[case(5)]
+    #[case(100)]
+    fn it_can_search_by_key_like_predicate_search(#[case] id: u32) {
+        let user_data = generate_random_user_data(100);
+        let keyed = MerkleTree::build_with_key(TAG_LEAF, TAG_BRANCH, &user_data, |data| data.id);
+
+        let expected = keyed
+            .tree()
+            .search_with_path(|user_data| user_data.id == id)
+            .unwrap();
+        let leaf = keyed.search_with_path(&id).unwrap();
+
+        assert_eq!(leaf.index(), expected.index());
+        assert_eq!(leaf.hash(), expected.hash());
+        assert_eq!(leaf.path().to_vec(), expected.path().to_vec());
+    }
+
+    #[test]
+    fn it_can_generate_proof_for_key() {
+        let user_data = generate_random_user_data(7);
+        let keyed = MerkleTree::build_with_key(TAG_LEAF, TAG_BRANCH, &user_data, |data| data.id);
+        let root = keyed.tree().root().unwrap();
+
+        let proof = keyed.proof_for_key(&4).unwrap();
+
+        assert_eq!(proof.leaf_index, 3);
+        assert!(proof.verify(TAG_LEAF, TAG_BRANCH, &user_data[3], &root));
+        assert_eq!(keyed.proof_for_key(&8), Err(MerkleTreeError::KeyNotFound));
+    }
+}
@@ -1,27 +1,100 @@
-use sha2::{Digest, Sha256};
 use std::fmt;
+use std::sync::Arc;
 
+mod aggregate;
+mod audit;
+pub mod bitcoin;
+mod builder;
+mod delegation;
+#[cfg(test)]
+mod determinism;
+mod domain;
+mod envelope;
+mod error;
+#[cfg(feature = "arbitrary")]
+#[doc(hidden)]
+pub mod fuzz;
+#[doc(hidden)]
+pub mod invariants;
+mod keyed;
+mod mmr;
+mod multiproof;
+mod odd_leaf;
+#[cfg(feature = "openzeppelin")]
+pub mod openzeppelin;
+mod partial;
+pub mod prelude;
+mod proof;
+mod reference;
+mod root_hasher;
+#[cfg(feature = "salted")]
+mod salted;
+mod snapshot;
+mod sorted;
+mod store;
+mod sum_tree;
+#[cfg(any(test, feature = "util"))]
+#[doc(hidden)]
 pub mod util;
+mod versioned;
+
+pub use aggregate::{Aggregate, AggregatedMerkleTree};
+pub use audit::TreeStats;
+pub use builder::{BuildProgress, MerkleTreeBuilder, ParallelLeaf};
+pub use delegation::DelegationBundle;
+pub use domain::DomainSeparation;
+pub use envelope::{ProofEnvelope, ProofMetadata};
+pub use error::MerkleTreeError;
+pub use keyed::KeyedMerkleTree;
+pub use merkle_hash::{tagged_hash, TagHasher};
+pub use mmr::{Mmr, MmrProof};
+pub use multiproof::MultiProof;
+pub use odd_leaf::OddLeafPolicy;
+pub use partial::PartialMerkleTree;
+pub use proof::MerkleProof;
+pub use reference::{recompute_root_from_leaves, Hash, TreeDescriptor};
+pub use root_hasher::MerkleRootHasher;
+#[cfg(feature = "salted")]
+pub use salted::Salted;
+pub use snapshot::{verify_snapshot_file, SnapshotData, SnapshotVerifyError};
+pub use sorted::{BoundingLeaf, NonInclusionProof, SortedMerkleTree};
+#[cfg(feature = "mmap")]
+pub use store::FileNodeStore;
+pub use store::{MemoryNodeStore, NodeStore, StoredMerkleTree};
+pub use sum_tree::{MerkleSumProof, MerkleSumTree, SumTreeData};
+pub use versioned::VersionedMerkleTree;
+
+/// The position of a node in the arena of its tree.
+type NodeId = u32;
 
 #[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MerkleNode<T> {
-    hash: Vec<u8>,
-    left: Option<Box<MerkleNode<T>>>,
-    right: Option<Box<MerkleNode<T>>>,
+    #[cfg_attr(feature = "serde", serde(with = "hex::serde"))]
+    hash: Hash,
+    left: Option<NodeId>,
+    right: Option<NodeId>,
     pub user_data: Option<T>,
 }
 
-impl<T> MerkleNode<T>
-where
-    T: Clone + fmt::Debug,
-{
+/// Where the level a tree is built from sits in the arena, see `MerkleNode::new_parent`.
+#[derive(Clone, Copy)]
+struct LevelLinks {
+    /// The id of the first node of the level.
+    start: NodeId,
+    /// The id of the node the last node of odd levels is padded with.
+    pad: NodeId,
+    odd_leaf_policy: OddLeafPolicy,
+}
+
+impl<T> MerkleNode<T> {
     /// Creates a new leaf node with the given hash and user data.
     ///
     /// # Arguments
     ///
     /// * `hash`: The hash of the leaf node's data.
     /// * `user_data`: The user data associated with the leaf node.
-    fn new_leaf(hash: Vec<u8>, user_data: Option<T>) -> Self {
+    fn new_leaf(hash: Hash, user_data: Option<T>) -> Self {
         MerkleNode {
             hash,
             left: None,
@@ -30,27 +103,67 @@ where
         }
     }
 
-    /// Creates a new branch node with the given left and right children and tag.
-    /// The hash of the branch node is calculated by concatenating the hashes of its children
-    /// and applying the `tagged_hash` function witsh the provided tag.
+    /// Creates the parent at `position` of the level above `level`, linked to its children
+    /// by their ids.
     ///
     /// # Arguments
     ///
-    /// * `left`: The left child node.
-    /// * `right`: The right child node.
-    /// * `tag`: The tag used for calculating the branch node's hash.
-    fn new_branch(left: MerkleNode<T>, right: MerkleNode<T>, tag: &str) -> Self {
-        let combined = vec![left.hash.clone(), right.hash.clone()].concat();
-        let hash = tagged_hash(tag, &combined);
+    /// * `level`: The nodes of the level below.
+    /// * `links`: Where `level` sits in the arena and how its last node is paired.
+    /// * `position`: The position of the parent within its own level.
+    /// * `branch_hash`: A function that calculates the hash of the parent from the hashes
+    ///   of its left and right child.
+    fn new_parent<F>(
+        level: &[MerkleNode<T>],
+        links: LevelLinks,
+        position: usize,
+        branch_hash: F,
+    ) -> Self
+    where
+        T: Clone,
+        F: FnOnce(&[u8], &[u8]) -> Hash,
+    {
+        let left_id = links.start + 2 * position as NodeId;
+        let left = &level[2 * position];
+        let (right_id, right_hash) = match (level.get(2 * position + 1), links.odd_leaf_policy) {
+            (Some(right), _) => (left_id + 1, &right.hash),
+            (None, OddLeafPolicy::DuplicateLast) => (left_id, &left.hash),
+            (None, OddLeafPolicy::PromoteOdd) => return left.clone(),
+            (None, OddLeafPolicy::PadWithEmptyHash) => (links.pad, &odd_leaf::EMPTY_HASH),
+        };
+
         MerkleNode {
-            hash,
-            left: Some(Box::new(left)),
-            right: Some(Box::new(right)),
+            hash: branch_hash(&left.hash, right_hash),
+            left: Some(left_id),
+            right: Some(right_id),
             user_data: None,
         }
     }
 }
 
+/// Turns a hash computed by `DomainSeparation` into the fixed size hash of a node.
+pub(crate) fn to_hash(hash: Vec<u8>) -> Hash {
+    hash.try_into().expect("node hashes are 32 bytes")
+}
+
+impl<T> MerkleNode<T> {
+    /// Returns the hash of the node.
+    pub fn hash(&self) -> &Hash {
+        &self.hash
+    }
+
+    /// Returns the hex encoded hash of the node.
+    pub fn hash_hex(&self) -> String {
+        hex::encode(self.hash)
+    }
+
+    /// Returns `true` if the node has no children.
+    pub fn is_leaf(&self) -> bool {
+        self.left.is_none() && self.right.is_none()
+    }
+}
+
+#[cfg(feature = "render")]
 impl<T> fmt::Display for MerkleNode<T>
 where
     T: fmt::Display,
@@ -59,8 +172,8 @@ where
         let formatted = self
             .user_data
             .as_ref()
-            .map_or(hex::encode(&self.hash), |user_data| {
-                format!("{} ({})", hex::encode(&self.hash), user_data)
+            .map_or(hex::encode(self.hash), |user_data| {
+                format!("{} ({})", hex::encode(self.hash), user_data)
             });
 
         write!(f, "{}", formatted)
@@ -68,6 +181,7 @@ where
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum NodeDirection {
     Left,
     Right,
@@ -84,6 +198,19 @@ impl NodeDirection {
     }
 }
 
+impl TryFrom<u8> for NodeDirection {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(NodeDirection::Left),
+            1 => Ok(NodeDirection::Right),
+            2 => Ok(NodeDirection::Root),
+            _ => Err(value),
+        }
+    }
+}
+
 impl fmt::Display for NodeDirection {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -94,7 +221,42 @@ impl fmt::Display for NodeDirection {
     }
 }
 
+/// The longest path `TraversePath::parse` accepts. A tree indexed by a 64 bit `usize` can
+/// never be deeper than this, and the limit is the same on every platform so a path parses
+/// everywhere or nowhere.
+pub const MAX_TRAVERSE_PATH_LEN: usize = 64;
+
+/// The reasons a user supplied path is rejected by `TraversePath::parse`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraversePathError {
+    /// The path has more steps than any tree can have levels.
+    TooLong { len: usize, max: usize },
+    /// The direction of a step is neither Left (0) nor Right (1).
+    InvalidDirection { step: usize, value: u8 },
+    /// The hash of a step is not 32 bytes of hex.
+    InvalidHash { step: usize },
+}
+
+impl fmt::Display for TraversePathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraversePathError::TooLong { len, max } => {
+                write!(f, "path has {len} steps, at most {max} are allowed")
+            }
+            TraversePathError::InvalidDirection { step, value } => {
+                write!(f, "step {step} has invalid direction {value}")
+            }
+            TraversePathError::InvalidHash { step } => {
+                write!(f, "step {step} does not have a 32 byte hex hash")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TraversePathError {}
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TraversePath {
     pub hashes: Vec<String>,
     pub directions: Vec<NodeDirection>,
@@ -119,6 +281,53 @@ impl TraversePath {
         self.directions.push(direction);
     }
 
+    /// Parses a path from (hash, direction) tuples, the format produced by `to_vec`.
+    ///
+    /// Meant for paths supplied by users, so every step is validated and nothing panics:
+    /// the path must not be longer than `MAX_TRAVERSE_PATH_LEN`, every direction must be
+    /// Left (0) or Right (1) and every hash must be 64 hex characters.
+    ///
+    /// # Arguments
+    ///
+    /// * `steps`: The (hash, direction) tuples, ordered from the root down.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `TraversePath`, or the `TraversePathError` of the first invalid step.
+    pub fn parse<S>(steps: &[(S, u8)]) -> Result<Self, TraversePathError>
+    where
+        S: AsRef<str>,
+    {
+        if steps.len() > MAX_TRAVERSE_PATH_LEN {
+            return Err(TraversePathError::TooLong {
+                len: steps.len(),
+                max: MAX_TRAVERSE_PATH_LEN,
+            });
+        }
+
+        let mut path = TraversePath::new();
+        for (step, (hash, value)) in steps.iter().enumerate() {
+            let direction = match NodeDirection::try_from(*value) {
+                Ok(direction @ (NodeDirection::Left | NodeDirection::Right)) => direction,
+                _ => {
+                    return Err(TraversePathError::InvalidDirection {
+                        step,
+                        value: *value,
+                    })
+                }
+            };
+
+            let hash = hash.as_ref();
+            if hash.len() != 64 || hex::decode(hash).is_err() {
+                return Err(TraversePathError::InvalidHash { step });
+            }
+
+            path.add_step(hash.to_ascii_lowercase(), direction);
+        }
+
+        Ok(path)
+    }
+
     /// Converts the `TraversePath` to a vector of (hash, direction) tuples.
     /// The direction is represented as a `u8` (0 for Left, 1 for Right, 2 for Root).
     ///
@@ -132,27 +341,324 @@ impl TraversePath {
             .map(|(hash, direction)| (hash.to_string(), direction.value()))
             .collect()
     }
+
+    /// Decodes the hashes of the steps, from the root down.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the 32 byte hashes, `TraversePathError::InvalidHash` for the first
+    /// step whose hash is not 32 bytes of hex.
+    pub fn hash_bytes(&self) -> Result<Vec<Hash>, TraversePathError> {
+        self.hashes
+            .iter()
+            .enumerate()
+            .map(|(step, hash)| {
+                hex::decode(hash)
+                    .ok()
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .ok_or(TraversePathError::InvalidHash { step })
+            })
+            .collect()
+    }
 }
 
+/// A Merkle Tree whose nodes are stored in a single `Vec`, linked by their positions.
+///
+/// The nodes are laid out level by level, from the leaves up to the root, so leaf `i` is
+/// node `i`, the root is the last node of the tree and the parent and sibling of any node
+/// are found with a little arithmetic instead of a walk from the root. The copy of the last
+/// node of an odd level is not stored, the parent links that node twice. The node a
+/// `OddLeafPolicy::PadWithEmptyHash` tree pads odd levels with is stored once, after the root.
+///
+/// The nodes are shared behind an `Arc`, so cloning a tree is O(1) and the clones read the
+/// same nodes. A tree is `Send` and `Sync` whenever its user data is, so one thread can
+/// build a new tree while others keep reading a clone of the old one.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        try_from = "MerkleTreeParts<T>",
+        bound(deserialize = "T: serde::Deserialize<'de>")
+    )
+)]
 pub struct MerkleTree<T> {
-    root: Option<Box<MerkleNode<T>>>,
+    nodes: Arc<Vec<MerkleNode<T>>>,
+    leaf_count: usize,
+    #[cfg_attr(feature = "serde", serde(default))]
+    domain_separation: DomainSeparation,
+    #[cfg_attr(feature = "serde", serde(default))]
+    odd_leaf_policy: OddLeafPolicy,
+}
+
+impl<T> Clone for MerkleTree<T> {
+    /// Returns a tree sharing the nodes of this one, without copying them.
+    fn clone(&self) -> Self {
+        MerkleTree {
+            nodes: Arc::clone(&self.nodes),
+            leaf_count: self.leaf_count,
+            domain_separation: self.domain_separation,
+            odd_leaf_policy: self.odd_leaf_policy,
+        }
+    }
+}
+
+/// A deserialized `MerkleTree` whose nodes are not yet known to be linked as a tree.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct MerkleTreeParts<T> {
+    nodes: Vec<MerkleNode<T>>,
+    leaf_count: usize,
+    #[serde(default)]
+    domain_separation: DomainSeparation,
+    #[serde(default)]
+    odd_leaf_policy: OddLeafPolicy,
+}
+
+#[cfg(feature = "serde")]
+impl<T> TryFrom<MerkleTreeParts<T>> for MerkleTree<T> {
+    type Error = MerkleTreeError;
+
+    /// Checks the links of the nodes and that every leaf has user data, which `leaves` and
+    /// the searches rely on.
+    fn try_from(parts: MerkleTreeParts<T>) -> Result<Self, Self::Error> {
+        let tree = MerkleTree {
+            nodes: Arc::new(parts.nodes),
+            leaf_count: parts.leaf_count,
+            domain_separation: parts.domain_separation,
+            odd_leaf_policy: parts.odd_leaf_policy,
+        };
+
+        if !tree.has_valid_links() {
+            return Err(MerkleTreeError::MalformedTree);
+        }
+        if let Some(index) = tree.nodes[..tree.leaf_count]
+            .iter()
+            .position(|leaf| leaf.user_data.is_none())
+        {
+            return Err(MerkleTreeError::MissingLeafData { index });
+        }
+
+        Ok(tree)
+    }
+}
+
+/// A node visited while walking the tree, together with where it sits in the tree.
+pub struct TraverseStep<'a, T> {
+    /// The parent of the node, `None` for the root.
+    pub parent_node: Option<&'a MerkleNode<T>>,
+    pub current_node: &'a MerkleNode<T>,
+    /// The depth of the node, counted from the root at 0.
+    pub level: u32,
+    /// The side of the parent the node hangs on, `NodeDirection::Root` for the root.
+    pub direction: NodeDirection,
+}
+
+/// Selects the part of a tree the `display_*_with` functions render.
+///
+/// The defaults render the whole tree with shortened hashes, like the `display_*`
+/// functions do. Large trees are best rendered a few levels at a time, either from the
+/// root or from the node a previous render ended at.
+#[cfg(feature = "render")]
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    /// The number of levels rendered below the starting node, `None` renders all of them.
+    /// Nodes whose children are cut off get a placeholder child naming the hidden levels.
+    pub max_depth: Option<u32>,
+    /// The hex encoded hash of the node to start rendering from instead of the root.
+    pub subtree_root: Option<String>,
+    /// Shortens node hashes to their first and last characters.
+    pub collapse_hashes: bool,
+}
+
+#[cfg(feature = "render")]
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            max_depth: None,
+            subtree_root: None,
+            collapse_hashes: true,
+        }
+    }
+}
+
+#[cfg(feature = "render")]
+impl RenderOptions {
+    fn format_hash(&self, hash: &str) -> String {
+        if self.collapse_hashes {
+            truncate_middle(hash, 10)
+        } else {
+            hash.to_string()
+        }
+    }
+
+    /// Returns the number of levels hidden below the node, `None` if none are.
+    fn hidden_levels<T>(&self, nodes: &[MerkleNode<T>], step: &TraverseStep<T>) -> Option<u32> {
+        if self.max_depth != Some(step.level) || step.current_node.is_leaf() {
+            return None;
+        }
+
+        // every branch has two children and all leaves sit on the same level, so the left
+        // most path is as long as any other
+        let mut levels = 0;
+        let mut node = step.current_node;
+        while let Some(left) = node.left {
+            levels += 1;
+            node = &nodes[left as usize];
+        }
+
+        Some(levels)
+    }
+}
+
+/// Iterates over the nodes of a tree depth first, each node before its children and left
+/// children before right ones.
+///
+/// Created by `MerkleTree::iter_nodes`.
+pub struct NodeIter<'a, T> {
+    nodes: &'a [MerkleNode<T>],
+    stack: Vec<TraverseStep<'a, T>>,
+    max_depth: Option<u32>,
+}
+
+impl<'a, T> Iterator for NodeIter<'a, T> {
+    type Item = TraverseStep<'a, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let step = self.stack.pop()?;
+        if self
+            .max_depth
+            .is_some_and(|max_depth| step.level >= max_depth)
+        {
+            return Some(step);
+        }
+
+        if let Some(right) = step.current_node.right {
+            self.stack.push(TraverseStep {
+                parent_node: Some(step.current_node),
+                current_node: &self.nodes[right as usize],
+                level: step.level + 1,
+                direction: NodeDirection::Right,
+            });
+        }
+
+        if let Some(left) = step.current_node.left {
+            self.stack.push(TraverseStep {
+                parent_node: Some(step.current_node),
+                current_node: &self.nodes[left as usize],
+                level: step.level + 1,
+                direction: NodeDirection::Left,
+            });
+        }
+
+        Some(step)
+    }
+}
+
+/// Iterates over the leaves of a tree from left to right, yielding the position, the user
+/// data and the hash of every leaf.
+///
+/// Created by `MerkleTree::leaves`.
+pub struct Leaves<'a, T> {
+    leaves: std::iter::Enumerate<std::slice::Iter<'a, MerkleNode<T>>>,
+}
+
+impl<'a, T> Iterator for Leaves<'a, T> {
+    type Item = (usize, &'a T, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (index, leaf) = self.leaves.next()?;
+
+        Some((index, leaf.user_data.as_ref()?, leaf.hash.as_slice()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.leaves.size_hint()
+    }
+}
+
+impl<T> ExactSizeIterator for Leaves<'_, T> {}
+
+/// A leaf found by a search.
+///
+/// Finding a leaf is a lookup by position, the path and the inclusion proof are read from
+/// the levels above it when asked for.
+///
+/// Created by `MerkleTree::search_with_path` and `MerkleTree::search_index_with_path`.
+pub struct LeafRef<'a, T> {
+    tree: &'a MerkleTree<T>,
+    index: usize,
+    leaf: &'a MerkleNode<T>,
+    data: &'a T,
 }
 
-struct TraverseStep<'a, T> {
-    parent_node: Option<&'a MerkleNode<T>>,
-    current_node: &'a MerkleNode<T>,
-    level: u32,
-    direction: NodeDirection,
+impl<'a, T> LeafRef<'a, T> {
+    /// Returns the position of the leaf, counted from the left starting at 0.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the hash of the leaf.
+    pub fn hash(&self) -> &'a Hash {
+        &self.leaf.hash
+    }
+
+    /// Returns the hex encoded hash of the leaf.
+    pub fn hash_hex(&self) -> String {
+        self.leaf.hash_hex()
+    }
+
+    /// Returns the user data stored in the leaf.
+    pub fn data(&self) -> &'a T {
+        self.data
+    }
+
+    /// Returns the branch nodes from the root down to the leaf and the direction taken at
+    /// each of them.
+    pub fn path(&self) -> TraversePath {
+        let mut path = TraversePath::new();
+        for (node, direction) in self.tree.ancestors(self.index) {
+            path.add_step(node.hash_hex(), direction);
+        }
+
+        path
+    }
+
+    /// Generates the inclusion proof of the leaf.
+    ///
+    /// The proof is identical to the one `MerkleTree::proof` returns for `index()`.
+    pub fn proof(&self) -> MerkleProof {
+        self.tree.proof_of(self.index)
+    }
 }
 
-pub trait MerkleTreeData {
+/// User data that can be stored in the leaves of a tree.
+pub trait LeafEncode {
+    /// Returns the bytes the hash of the leaf is computed from.
     fn serialize(&self) -> Vec<u8>;
+}
+
+/// How user data is labelled in rendered diagrams.
+///
+/// Only `display_mermaid_diagram` and `display_dot` need it, trees of data without labels
+/// can still be built, proven and displayed with `display_tree`.
+pub trait NodeLabel {
+    /// The label of the leaf in Mermaid output, following the node hash.
+    ///
+    /// It starts with a `<br>` line break, or is empty to show the hash only.
     fn mermaid_node_label(&self) -> String;
+
+    /// The label of the leaf in Graphviz DOT output, it may span several lines.
+    ///
+    /// Like the Mermaid label it starts with a line break, as it follows the node hash.
+    /// Defaults to the Mermaid label with its `<br>` line breaks turned into new lines.
+    fn dot_node_label(&self) -> String {
+        self.mermaid_node_label().replace("<br>", "\n")
+    }
 }
 
 impl<T> MerkleTree<T>
 where
-    T: Clone + fmt::Debug + MerkleTreeData + Default,
+    T: Clone + LeafEncode,
 {
     /// Builds a Merkle Tree from the given user data.
     ///
@@ -161,199 +667,726 @@ where
     /// * `tag_leaf`: The tag used for hashing leaf nodes.
     /// * `tag_branch`: The tag used for hashing branch nodes.
     /// * `user_data`: A slice of tuples, where each tuple contains a user ID and balance.
-    pub fn build(tag_leaf: &str, tag_branch: &str, input: &Vec<T>) -> Self {
-        if input.is_empty() {
-            return MerkleTree { root: None };
-        }
+    pub fn build(tag_leaf: &str, tag_branch: &str, input: &[T]) -> Self {
+        Self::build_with_domain_separation(tag_leaf, tag_branch, input, DomainSeparation::Tags)
+    }
 
-        let mut nodes: Vec<MerkleNode<T>> = input
-            .iter()
+    /// Builds a Merkle Tree whose leaves, branches and root are hashed as `domain_separation`
+    /// tells.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_leaf`: The tag used for hashing leaf nodes.
+    /// * `tag_branch`: The tag used for hashing branch nodes.
+    /// * `input`: The user data stored in the leaves.
+    /// * `domain_separation`: How leaf, branch and root hashes are kept apart.
+    pub fn build_with_domain_separation(
+        tag_leaf: &str,
+        tag_branch: &str,
+        input: &[T],
+        domain_separation: DomainSeparation,
+    ) -> Self {
+        Self::build_with(
+            tag_leaf,
+            tag_branch,
+            input,
+            domain_separation,
+            OddLeafPolicy::DuplicateLast,
+        )
+    }
+
+    /// Builds a Merkle Tree whose odd levels are paired up as `odd_leaf_policy` tells.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_leaf`: The tag used for hashing leaf nodes.
+    /// * `tag_branch`: The tag used for hashing branch nodes.
+    /// * `input`: The user data stored in the leaves.
+    /// * `odd_leaf_policy`: What the last node of a level with an odd number of nodes is paired with.
+    pub fn build_with_odd_leaf_policy(
+        tag_leaf: &str,
+        tag_branch: &str,
+        input: &[T],
+        odd_leaf_policy: OddLeafPolicy,
+    ) -> Self {
+        Self::build_with(
+            tag_leaf,
+            tag_branch,
+            input,
+            DomainSeparation::Tags,
+            odd_leaf_policy,
+        )
+    }
+
+    /// Builds a Merkle Tree from user data that is moved into the leaves instead of copied.
+    ///
+    /// The leaves are hashed as the iterator yields them, so an iterator over the records of
+    /// a file is never collected on its own. The tree is the same `build` returns.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_leaf`: The tag used for hashing leaf nodes.
+    /// * `tag_branch`: The tag used for hashing branch nodes.
+    /// * `input`: The user data stored in the leaves, in order.
+    pub fn build_from_iter<I>(tag_leaf: &str, tag_branch: &str, input: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+    {
+        Self::build_iter_with(
+            tag_leaf,
+            tag_branch,
+            input,
+            DomainSeparation::Tags,
+            OddLeafPolicy::DuplicateLast,
+        )
+    }
+
+    fn build_with(
+        tag_leaf: &str,
+        tag_branch: &str,
+        input: &[T],
+        domain_separation: DomainSeparation,
+        odd_leaf_policy: OddLeafPolicy,
+    ) -> Self {
+        Self::build_iter_with(
+            tag_leaf,
+            tag_branch,
+            input.iter().cloned(),
+            domain_separation,
+            odd_leaf_policy,
+        )
+    }
+
+    fn build_iter_with<I>(
+        tag_leaf: &str,
+        tag_branch: &str,
+        input: I,
+        domain_separation: DomainSeparation,
+        odd_leaf_policy: OddLeafPolicy,
+    ) -> Self
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let (tag_leaf, tag_branch) = (TagHasher::new(tag_leaf), TagHasher::new(tag_branch));
+        let leaves = input
+            .into_iter()
             .map(|data| {
                 MerkleNode::new_leaf(
-                    tagged_hash(tag_leaf, data.serialize().as_slice()),
-                    Some(data.clone()),
+                    to_hash(domain_separation.leaf_hash_with(&tag_leaf, &data.serialize())),
+                    Some(data),
                 )
             })
             .collect();
 
-        while nodes.len() > 1 {
-            nodes = nodes
-                .chunks_mut(2)
-                .map(|pair| {
-                    let [left, right] = match pair {
-                        [l, r] => [std::mem::take(l), std::mem::take(r)],
-                        [l] => [l.clone(), std::mem::take(l)],
-                        _ => panic!(),
-                    };
-
-                    MerkleNode::new_branch(left, right, tag_branch)
-                })
-                .collect();
+        Self::from_leaf_nodes(
+            leaves,
+            domain_separation,
+            odd_leaf_policy,
+            |level, links| {
+                (0..level.len().div_ceil(2))
+                    .map(|position| {
+                        MerkleNode::new_parent(level, links, position, |left, right| {
+                            to_hash(domain_separation.branch_hash_with(&tag_branch, left, right))
+                        })
+                    })
+                    .collect()
+            },
+        )
+    }
+
+    /// Builds the levels above the given leaves up to the root.
+    ///
+    /// Every level is appended to the arena the leaves start out in, followed by the padding
+    /// node if `odd_leaf_policy` pads and some level needs it.
+    ///
+    /// # Arguments
+    ///
+    /// * `nodes`: The hashed leaves.
+    /// * `domain_separation`: How the leaves and `next_level` were hashed.
+    /// * `odd_leaf_policy`: What `next_level` pairs the last node of odd levels with.
+    /// * `next_level`: A function that turns the nodes of a level into their parents.
+    ///
+    /// # Panics
+    ///
+    /// If the tree has more nodes than a `u32` can count.
+    fn from_leaf_nodes<F>(
+        nodes: Vec<MerkleNode<T>>,
+        domain_separation: DomainSeparation,
+        odd_leaf_policy: OddLeafPolicy,
+        mut next_level: F,
+    ) -> Self
+    where
+        F: FnMut(&[MerkleNode<T>], LevelLinks) -> Vec<MerkleNode<T>>,
+    {
+        let built =
+            Self::try_from_leaf_nodes(nodes, domain_separation, odd_leaf_policy, |level, links| {
+                Ok::<_, std::convert::Infallible>(next_level(level, links))
+            });
+
+        match built {
+            Ok(tree) => tree,
+            Err(never) => match never {},
         }
+    }
 
-        MerkleTree {
-            root: Some(Box::new(nodes[0].clone())),
+    /// Builds the levels above the given leaves like `from_leaf_nodes`, stopping at the
+    /// first level `next_level` fails to build.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the tree, or the first error returned by `next_level`.
+    fn try_from_leaf_nodes<F, E>(
+        mut nodes: Vec<MerkleNode<T>>,
+        domain_separation: DomainSeparation,
+        odd_leaf_policy: OddLeafPolicy,
+        mut next_level: F,
+    ) -> Result<Self, E>
+    where
+        F: FnMut(&[MerkleNode<T>], LevelLinks) -> Result<Vec<MerkleNode<T>>, E>,
+    {
+        let leaf_count = nodes.len();
+        let sizes = level_sizes(leaf_count);
+        let node_count: usize = sizes.iter().sum();
+        let pad = NodeId::try_from(node_count).expect("a tree has fewer than 2^32 nodes");
+        nodes.reserve_exact(node_count + 1 - leaf_count);
+
+        let mut start = 0;
+        for &size in &sizes[..sizes.len() - 1] {
+            let links = LevelLinks {
+                start: start as NodeId,
+                pad,
+                odd_leaf_policy,
+            };
+            let parents = next_level(&nodes[start..start + size], links)?;
+            nodes.extend(parents);
+            start += size;
         }
+
+        if needs_pad(&sizes, odd_leaf_policy) {
+            nodes.push(MerkleNode::new_leaf(odd_leaf::EMPTY_HASH, None));
+        }
+
+        Ok(MerkleTree {
+            nodes: Arc::new(nodes),
+            leaf_count,
+            domain_separation,
+            odd_leaf_policy,
+        })
+    }
+
+    /// Builds a Merkle Tree together with an index from a key to the leaf position,
+    /// so that lookups by key do not have to scan the whole tree.
+    ///
+    /// When several leaves produce the same key, the left most leaf is indexed, which is
+    /// the same leaf `search_with_path` would find.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_leaf`: The tag used for hashing leaf nodes.
+    /// * `tag_branch`: The tag used for hashing branch nodes.
+    /// * `input`: The user data stored in the leaves.
+    /// * `key_fn`: A function that extracts the lookup key from the user data.
+    pub fn build_with_key<K, F>(
+        tag_leaf: &str,
+        tag_branch: &str,
+        input: &[T],
+        key_fn: F,
+    ) -> KeyedMerkleTree<T, K>
+    where
+        K: Eq + std::hash::Hash,
+        F: Fn(&T) -> K,
+    {
+        KeyedMerkleTree::from_tree(Self::build(tag_leaf, tag_branch, input), key_fn)
     }
+}
 
+impl<T> MerkleTree<T> {
     /// Returns the hash of the root node of the Merkle Tree.
+    ///
+    /// For `DomainSeparation::Prefixed` trees this is the commitment to the top node and
+    /// the leaf count, see `DomainSeparation::root_hash`.
     pub fn root(&self) -> Option<String> {
-        self.root.as_ref().map(|node| hex::encode(&node.hash))
+        self.root_bytes().map(hex::encode)
+    }
+
+    /// Returns the root hash like `root`, as bytes instead of hex, e.g. to sign it or embed
+    /// it in a binary protocol.
+    pub fn root_bytes(&self) -> Option<Hash> {
+        self.root_node().map(|node| {
+            to_hash(
+                self.domain_separation
+                    .root_hash(self.leaf_count, &node.hash),
+            )
+        })
+    }
+
+    /// Returns how the hashes of the tree are separated from each other.
+    pub fn domain_separation(&self) -> DomainSeparation {
+        self.domain_separation
+    }
+
+    /// Returns what the last node of odd levels is paired with.
+    pub fn odd_leaf_policy(&self) -> OddLeafPolicy {
+        self.odd_leaf_policy
+    }
+
+    /// Returns an iterator over all nodes of the tree, depth first from the root.
+    ///
+    /// Every node is visited before its children and left children before right ones. How
+    /// the last node of an odd level shows up depends on the `OddLeafPolicy`: with
+    /// `DuplicateLast` it is paired with a copy of itself, so it is visited twice, with
+    /// `PromoteOdd` it is visited once, at the level it was promoted to, and with
+    /// `PadWithEmptyHash` the padding node it is paired with is visited after it.
+    pub fn iter_nodes(&self) -> NodeIter<'_, T> {
+        NodeIter {
+            nodes: &self.nodes,
+            stack: self
+                .root_node()
+                .map(|root| TraverseStep {
+                    parent_node: None,
+                    current_node: root,
+                    level: 0,
+                    direction: NodeDirection::Root,
+                })
+                .into_iter()
+                .collect(),
+            max_depth: None,
+        }
+    }
+
+    /// Returns an iterator over the leaves from left to right, as `(index, &user_data, hash)`.
+    ///
+    /// Unlike `iter_nodes`, every leaf is visited exactly once and padding nodes are skipped,
+    /// whatever the `OddLeafPolicy`.
+    pub fn leaves(&self) -> Leaves<'_, T> {
+        Leaves {
+            leaves: self.nodes[..self.leaf_count].iter().enumerate(),
+        }
     }
 
-    /// Iterates over the tree level by level and applies the given function to each node.
+    /// Returns the number of leaves.
+    pub fn len(&self) -> usize {
+        self.leaf_count
+    }
+
+    /// Returns `true` if the tree has no leaves.
+    pub fn is_empty(&self) -> bool {
+        self.leaf_count == 0
+    }
+
+    /// Returns the number of levels below the root.
+    ///
+    /// This is the length of every proof, except with `OddLeafPolicy::PromoteOdd`, where a
+    /// node promoted past a level has no sibling there and the leaves below it get shorter
+    /// proofs. A tree with a single leaf, or none, has a depth of 0.
+    pub fn depth(&self) -> u32 {
+        self.leaf_count.next_power_of_two().trailing_zeros()
+    }
+
+    /// Walks over all nodes of the tree in the order of `iter_nodes` and calls the given
+    /// function for each of them.
+    ///
+    /// # Arguments
+    ///
+    /// * `visit_fn`: A function that takes a `&TraverseStep`, it may capture and update state.
+    pub fn traverse<F>(&self, mut visit_fn: F)
+    where
+        F: FnMut(&TraverseStep<T>),
+    {
+        for step in self.iter_nodes() {
+            visit_fn(&step);
+        }
+    }
+
+    /// Iterates over the part of the tree selected by `options`, in the order of `iter_nodes`,
+    /// and applies the given function to each node.
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: The starting node and the number of levels to visit.
+    /// * `map_fn`: A function that takes a `&TraverseStep` and the number of levels hidden
+    ///   below the node, and returns a value. This function is called for each visited node.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a `Vec<R>` with the result of applying `map_fn` to every visited
+    /// node, or the message to render if the tree is empty or `subtree_root` is not in it.
+    #[cfg(feature = "render")]
+    fn iterate_tree<R, F>(&self, options: &RenderOptions, mut map_fn: F) -> Result<Vec<R>, String>
+    where
+        F: FnMut(&TraverseStep<T>, Option<u32>) -> R,
+    {
+        let Some(root) = self.root_node() else {
+            return Err("Tree is empty.".to_string());
+        };
+
+        let start = match &options.subtree_root {
+            Some(hash) => {
+                let hash = hash.to_lowercase();
+                self.iter_nodes()
+                    .find(|step| step.current_node.hash_hex() == hash)
+                    .ok_or_else(|| format!("Node {hash} not found."))?
+                    .current_node
+            }
+            None => root,
+        };
+
+        let nodes = NodeIter {
+            nodes: &self.nodes,
+            stack: vec![TraverseStep {
+                parent_node: None,
+                current_node: start,
+                level: 0,
+                direction: NodeDirection::Root,
+            }],
+            max_depth: options.max_depth,
+        };
+
+        Ok(nodes
+            .map(|step| map_fn(&step, options.hidden_levels(&self.nodes, &step)))
+            .collect())
+    }
+
+    /// Displays the Merkle Tree in an indented format.
+    #[cfg(feature = "render")]
+    pub fn display_tree(&self) -> String {
+        self.display_tree_with(&RenderOptions::default())
+    }
+
+    /// Displays the part of the Merkle Tree selected by `options` in an indented format.
+    #[cfg(feature = "render")]
+    pub fn display_tree_with(&self, options: &RenderOptions) -> String {
+        match self.iterate_tree(options, |step, hidden_levels| {
+            let indent = " ".repeat(step.level as usize);
+            let node_text = format!(
+                "{}{}: {}",
+                indent,
+                step.direction,
+                options.format_hash(&step.current_node.hash_hex())
+            );
+
+            match hidden_levels {
+                Some(levels) => format!("{node_text}\n{indent} ... {levels} levels collapsed"),
+                None => node_text,
+            }
+        }) {
+            Ok(output) => output.join("\n"),
+            Err(message) => message,
+        }
+    }
+
+    /// Searches for the leaf at the given position.
+    ///
+    /// # Arguments
+    ///
+    /// * `index`: The position of the leaf, counted from the left starting at 0.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the `LeafRef` if the index is in range, `None` otherwise.
+    /// The path is identical to the one `search_with_path` returns for the same leaf.
+    pub fn search_index_with_path(&self, index: usize) -> Option<LeafRef<'_, T>> {
+        let leaf = self.nodes[..self.leaf_count].get(index)?;
+
+        Some(LeafRef {
+            tree: self,
+            index,
+            leaf,
+            data: leaf.user_data.as_ref()?,
+        })
+    }
+
+    /// Generates an inclusion proof for the leaf at the given position.
+    ///
+    /// # Arguments
+    ///
+    /// * `index`: The position of the leaf, counted from the left starting at 0.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `MerkleProof`, `MerkleTreeError::LeafIndexOutOfRange` if there
+    /// is no leaf at `index`.
+    pub fn proof(&self, index: usize) -> Result<MerkleProof, MerkleTreeError> {
+        if index >= self.leaf_count {
+            return Err(MerkleTreeError::LeafIndexOutOfRange {
+                index,
+                leaf_count: self.leaf_count,
+            });
+        }
+
+        Ok(self.proof_of(index))
+    }
+
+    /// Searches for a user with the given predicate.
+    ///
+    /// # Arguments
+    ///
+    /// * `predicate`: A function that takes a `&UserData` and returns a boolean.
+    ///   It returns true if the user data matches the search criteria, false otherwise.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the `LeafRef` of the left most matching leaf, `None` if no leaf matches.
+    pub fn search_with_path<F>(&self, predicate: F) -> Option<LeafRef<'_, T>>
+    where
+        F: Fn(&T) -> bool,
+    {
+        let (index, _data, _hash) = self
+            .leaves()
+            .find(|(_index, data, _hash)| predicate(data))?;
+        self.search_index_with_path(index)
+    }
+
+    /// Searches for every user matching the given predicate in a single pass over the leaves.
     ///
     /// # Arguments
     ///
-    /// * `map_fn`: A function that takes a `&TraverseStep` and returns a String.
-    ///              This function is called for each node in the tree.
+    /// * `predicate`: A function that takes a `&UserData` and returns a boolean.
+    ///   It returns true if the user data matches the search criteria, false otherwise.
     ///
     /// # Returns
     ///
-    /// An `Option` containing a `Vec<String>` if the tree is not empty, `None` otherwise.
-    /// Each string in the vector is the result of applying `map_fn` to a node.
-    fn iterate_tree(&self, map_fn: fn(&TraverseStep<T>) -> String) -> Option<Vec<String>> {
-        self.root.as_ref().map(|root| {
-            let mut output = Vec::new();
+    /// A `Vec` containing the `LeafRef` of every matching leaf from left to right, empty if
+    /// no leaf matches.
+    pub fn search_all_with_path<F>(&self, predicate: F) -> Vec<LeafRef<'_, T>>
+    where
+        F: Fn(&T) -> bool,
+    {
+        self.leaves()
+            .filter(|(_index, data, _hash)| predicate(data))
+            .filter_map(|(index, _data, _hash)| self.search_index_with_path(index))
+            .collect()
+    }
+}
 
-            let mut stack: Vec<TraverseStep<T>> = vec![TraverseStep {
-                parent_node: None,
-                current_node: root,
-                level: 0,
-                direction: NodeDirection::Root,
-            }];
-
-            while let Some(step) = stack.pop() {
-                output.push(map_fn(&step));
-
-                if let Some(right) = &step.current_node.right {
-                    stack.push(TraverseStep {
-                        parent_node: Some(step.current_node),
-                        current_node: right,
-                        level: step.level + 1,
-                        direction: NodeDirection::Right,
-                    });
-                }
+#[cfg(feature = "render")]
+impl<T> MerkleTree<T>
+where
+    T: NodeLabel,
+{
+    /// Displays the Merkle Tree as a Mermaid diagram.
+    /// Use the mermaid editor to visualize the diagram https://mermaid.live/
+    #[cfg(feature = "render")]
+    pub fn display_mermaid_diagram(&self) -> String {
+        self.display_mermaid_diagram_with(&RenderOptions::default())
+    }
+
+    /// Displays the part of the Merkle Tree selected by `options` as a Mermaid diagram.
+    #[cfg(feature = "render")]
+    pub fn display_mermaid_diagram_with(&self, options: &RenderOptions) -> String {
+        match self.iterate_tree(options, |step, hidden_levels| {
+            let current_node_hash = step.current_node.hash_hex();
+            let truncated_current_node_hash = options.format_hash(&current_node_hash);
+            let current_node_label = (step.current_node.user_data.as_ref())
+                .map_or(String::from(""), |item| item.mermaid_node_label());
+            let node_mermaid = format!(
+                "Node_{current_node_hash}[{truncated_current_node_hash}{current_node_label}]",
+            );
 
-                if let Some(left) = &step.current_node.left {
-                    stack.push(TraverseStep {
-                        parent_node: Some(step.current_node),
-                        current_node: left,
-                        level: step.level + 1,
-                        direction: NodeDirection::Left,
-                    });
+            let node_connection_mermaid = match step.parent_node {
+                Some(parent_node) => {
+                    format!("\nNode_{} --> Node_{}", parent_node.hash_hex(), current_node_hash)
                 }
-            }
+                None => String::new(),
+            };
 
-            output
-        })
-    }
+            let collapsed_mermaid = match hidden_levels {
+                Some(levels) => format!(
+                    "\nCollapsed_{current_node_hash}([{levels} levels collapsed])\nNode_{current_node_hash} -.-> Collapsed_{current_node_hash}"
+                ),
+                None => String::new(),
+            };
 
-    /// Displays the Merkle Tree in an indented format.
-    pub fn display_tree(&self) -> String {
-        match self.iterate_tree(|step| {
-            let indent = " ".repeat(step.level as usize);
-            format!(
-                "{}{}: {}",
-                indent,
-                step.direction,
-                truncate_middle(hex::encode(&step.current_node.hash).as_str(), 10)
-            )
+            format!("{node_mermaid}{node_connection_mermaid}{collapsed_mermaid}")
         }) {
-            Some(output) => output.join("\n"),
-            None => format!("Tree is empty."),
+            Ok(output) => format!("flowchart TD\n{}", output.join("\n")),
+            Err(message) => message,
         }
     }
 
-    /// Displays the Merkle Tree as a Mermaid diagram.
-    /// Use the mermaid editor to visualize the diagram https://mermaid.live/
-    pub fn display_mermaid_diagram(&self) -> String {
-        match self.iterate_tree(|step| {
-            let current_node_hash = hex::encode(&step.current_node.hash);
-            let truncated_current_node_hash = truncate_middle(current_node_hash.as_str(), 10);
+    /// Displays the Merkle Tree as a Graphviz DOT digraph.
+    /// Render it with the graphviz toolchain, for example `dot -Tsvg tree.dot -o tree.svg`.
+    #[cfg(feature = "render")]
+    pub fn display_dot(&self) -> String {
+        self.display_dot_with(&RenderOptions::default())
+    }
+
+    /// Displays the part of the Merkle Tree selected by `options` as a Graphviz DOT digraph.
+    #[cfg(feature = "render")]
+    pub fn display_dot_with(&self, options: &RenderOptions) -> String {
+        match self.iterate_tree(options, |step, hidden_levels| {
+            let current_node_hash = step.current_node.hash_hex();
+            let truncated_current_node_hash = options.format_hash(&current_node_hash);
             let current_node_label = (step.current_node.user_data.as_ref())
-                .map_or(String::from(""), |item| item.mermaid_node_label());
-            println!("{current_node_label} lable");
-            let node_mermaid = format!(
-                "Node_{current_node_hash}[{truncated_current_node_hash}{current_node_label}]",
+                .map_or(String::from(""), |item| item.dot_node_label());
+            let node_dot = format!(
+                "    \"Node_{current_node_hash}\" [label=\"{}\"];",
+                escape_dot_label(&format!(
+                    "{truncated_current_node_hash}{current_node_label}"
+                ))
             );
 
-            let node_connection_mermaid = (step.direction != NodeDirection::Root)
-                .then(|| {
-                    let parent_node_hash = hex::encode(&step.parent_node.unwrap().hash);
+            let node_connection_dot = match step.parent_node {
+                Some(parent_node) => format!(
+                    "\n    \"Node_{}\" -> \"Node_{current_node_hash}\";",
+                    parent_node.hash_hex()
+                ),
+                None => String::new(),
+            };
 
-                    format!("\nNode_{} --> Node_{}", parent_node_hash, current_node_hash)
-                })
-                .unwrap_or_default();
+            let collapsed_dot = match hidden_levels {
+                Some(levels) => format!(
+                    "\n    \"Collapsed_{current_node_hash}\" [label=\"{levels} levels collapsed\", shape=plaintext];\n    \"Node_{current_node_hash}\" -> \"Collapsed_{current_node_hash}\" [style=dashed];"
+                ),
+                None => String::new(),
+            };
 
-            format!("{node_mermaid}{node_connection_mermaid}")
+            format!("{node_dot}{node_connection_dot}{collapsed_dot}")
         }) {
-            Some(output) => format!("flowchart TD\n{}", output.join("\n")),
-            None => format!("Tree is empty."),
+            Ok(output) => format!(
+                "digraph MerkleTree {{\n    node [shape=box];\n{}\n}}",
+                output.join("\n")
+            ),
+            Err(message) => message,
         }
     }
+}
 
-    /// Searches for a user with the given predicate.
+impl<T> MerkleTree<T> {
+    /// Returns the root node, the last node of the top level.
+    fn root_node(&self) -> Option<&MerkleNode<T>> {
+        self.levels().last()?.first()
+    }
+
+    /// Returns the nodes of the tree level by level, starting with the leaves.
     ///
-    /// # Arguments
+    /// The copy or the padding a level with an odd number of nodes is paired up with is not
+    /// part of any level, so every node shows up exactly once. A promoted node shows up on
+    /// both levels.
     ///
-    /// * `predicate`: A function that takes a `&UserData` and returns a boolean.
-    ///              It returns true if the user data matches the search criteria, false otherwise.
+    /// # Returns
+    ///
+    /// A `Vec` of levels, leaves first and the root last. Empty if the tree is empty.
+    pub(crate) fn levels(&self) -> Vec<&[MerkleNode<T>]> {
+        let mut start = 0;
+        level_sizes(self.leaf_count)
+            .into_iter()
+            .filter(|&size| size > 0)
+            .map(|size| {
+                let level = &self.nodes[start..start + size];
+                start += size;
+                level
+            })
+            .collect()
+    }
+
+    /// Collects the branch nodes above the leaf at the given position, which must be in
+    /// range.
     ///
     /// # Returns
     ///
-    /// An `Option` containing a tuple of `(&MerkleNode, TraversePath)` if a matching user is found, `None` otherwise.
-    pub fn search_with_path<F>(&self, predicate: F) -> Option<(&MerkleNode<T>, TraversePath)>
-    where
-        F: Fn(&T) -> bool,
-    {
-        if let Some(root) = &self.root {
-            let mut path = TraversePath::new();
-            Self::search_node_with_path(root, &predicate, &mut path)
-        } else {
-            None
+    /// The branch nodes with the direction taken from each of them towards the leaf, ordered
+    /// from the root down.
+    fn ancestors(&self, index: usize) -> Vec<(&MerkleNode<T>, NodeDirection)> {
+        let levels = self.levels();
+        let mut steps = Vec::with_capacity(levels.len());
+
+        for level in (1..levels.len()).rev() {
+            // a promoted node is the same node on both levels, there is no branch to take
+            let position = index >> (level - 1);
+            if self.odd_leaf_policy == OddLeafPolicy::PromoteOdd
+                && position & 1 == 0
+                && position + 1 == levels[level - 1].len()
+            {
+                continue;
+            }
+
+            let direction = if position & 1 == 0 {
+                NodeDirection::Left
+            } else {
+                NodeDirection::Right
+            };
+            steps.push((&levels[level][position >> 1], direction));
         }
+
+        steps
     }
 
-    fn search_node_with_path<'a, F>(
-        node: &'a MerkleNode<T>,
-        predicate: &F,
-        path: &mut TraversePath,
-    ) -> Option<(&'a MerkleNode<T>, TraversePath)>
-    where
-        F: Fn(&T) -> bool,
-    {
-        if let Some(user_data) = &node.user_data {
-            if predicate(user_data) {
-                return Some((
-                    node,
-                    TraversePath {
-                        directions: path.directions.clone(),
-                        hashes: path.hashes.clone(),
-                    },
-                ));
-            }
+    /// Collects the siblings of the leaf at the given position and of the nodes above it,
+    /// from the leaf up to the root. The position must be in range.
+    fn proof_of(&self, index: usize) -> MerkleProof {
+        let levels = self.levels();
+        let mut proof = MerkleProof::new(index);
+
+        for (level, nodes) in levels.iter().enumerate().take(levels.len() - 1) {
+            let position = index >> level;
+            let (sibling, direction) = if position & 1 == 1 {
+                (&nodes[position - 1].hash, NodeDirection::Left)
+            } else if let Some(right) = nodes.get(position + 1) {
+                (&right.hash, NodeDirection::Right)
+            } else {
+                match self.odd_leaf_policy {
+                    OddLeafPolicy::DuplicateLast => (&nodes[position].hash, NodeDirection::Right),
+                    OddLeafPolicy::PromoteOdd => continue,
+                    OddLeafPolicy::PadWithEmptyHash => {
+                        (&odd_leaf::EMPTY_HASH, NodeDirection::Right)
+                    }
+                }
+            };
+
+            proof.add_sibling(hex::encode(sibling), direction);
         }
 
-        if let Some(left) = &node.left {
-            path.add_step(hex::encode(&node.hash), NodeDirection::Left);
-            if let Some(result) = Self::search_node_with_path(left, predicate, path) {
-                return Some(result);
-            }
-            path.hashes.pop();
-            path.directions.pop();
+        proof
+    }
+
+    /// Checks that the nodes are laid out and linked the way `from_leaf_nodes` builds them,
+    /// so that no lookup can go out of bounds. The hashes are not checked.
+    #[cfg(feature = "serde")]
+    fn has_valid_links(&self) -> bool {
+        let sizes = level_sizes(self.leaf_count);
+        let node_count: usize = sizes.iter().sum();
+        let Ok(pad) = NodeId::try_from(node_count) else {
+            return false;
+        };
+
+        let padded = needs_pad(&sizes, self.odd_leaf_policy);
+        if self.nodes.len() != node_count + usize::from(padded)
+            || !self.nodes[..self.leaf_count]
+                .iter()
+                .all(MerkleNode::is_leaf)
+            || (padded && !self.nodes[node_count].is_leaf())
+        {
+            return false;
         }
 
-        if let Some(right) = &node.right {
-            path.add_step(hex::encode(&node.hash), NodeDirection::Right);
-            if let Some(result) = Self::search_node_with_path(right, predicate, path) {
-                return Some(result);
+        let mut start = 0;
+        for (&size, &parent_count) in sizes.iter().zip(sizes.iter().skip(1)) {
+            let parents = &self.nodes[start + size..start + size + parent_count];
+            for (position, parent) in parents.iter().enumerate() {
+                let left = start + 2 * position;
+                let links = if 2 * position + 1 < size {
+                    (Some(left as NodeId), Some(left as NodeId + 1))
+                } else {
+                    match self.odd_leaf_policy {
+                        OddLeafPolicy::DuplicateLast => {
+                            (Some(left as NodeId), Some(left as NodeId))
+                        }
+                        OddLeafPolicy::PromoteOdd => {
+                            (self.nodes[left].left, self.nodes[left].right)
+                        }
+                        OddLeafPolicy::PadWithEmptyHash => (Some(left as NodeId), Some(pad)),
+                    }
+                };
+
+                if (parent.left, parent.right) != links {
+                    return false;
+                }
             }
-            path.hashes.pop();
-            path.directions.pop();
+            start += size;
         }
 
-        None
+        true
     }
 }
 
@@ -371,6 +1404,7 @@ where
 /// # Returns
 ///
 /// A string of truncated text.
+#[cfg(feature = "render")]
 fn truncate_middle(input: &str, max_len: usize) -> String {
     let len = input.len();
     if len <= max_len {
@@ -384,29 +1418,37 @@ fn truncate_middle(input: &str, max_len: usize) -> String {
     format!("{}...{}", start, end)
 }
 
-/// Calculates a tagged hash using SHA256.
-///
-/// This function takes a tag and an input byte slice, calculates the SHA256 hash of the tag,
-/// then calculates the SHA256 hash of the concatenation of the tag's hash (twice) and the input.
+/// Calculates the number of nodes on every level of a tree with the given number of leaves.
 ///
-/// # Arguments
-///
-/// * `tag`: The tag string.
-/// * `input`: The input byte slice.
+/// Each level has half the nodes of the one below, rounded up, because the last node of a
+/// level with an odd number of nodes is paired with itself.
 ///
 /// # Returns
 ///
-/// The tagged SHA256 hash as a `Vec<u8>`.
-pub fn tagged_hash(tag: &str, input: &[u8]) -> Vec<u8> {
-    let mut hasher = Sha256::new();
-    hasher.update(tag.as_bytes());
-    let tag_hash = hasher.finalize();
+/// A `Vec` of level sizes, leaves first and the root (1) last.
+pub(crate) fn level_sizes(leaf_count: usize) -> Vec<usize> {
+    let mut sizes = vec![leaf_count];
+    while let Some(&size) = sizes.last().filter(|&&size| size > 1) {
+        sizes.push(size.div_ceil(2));
+    }
+
+    sizes
+}
+
+/// Returns `true` if a tree with the given level sizes stores a padding node, which is the
+/// case if it pads and some level below the root has an odd number of nodes.
+fn needs_pad(sizes: &[usize], odd_leaf_policy: OddLeafPolicy) -> bool {
+    odd_leaf_policy == OddLeafPolicy::PadWithEmptyHash
+        && sizes.iter().any(|&size| size > 1 && size & 1 == 1)
+}
 
-    let mut hasher = Sha256::new();
-    hasher.update(&tag_hash);
-    hasher.update(&tag_hash);
-    hasher.update(input);
-    hasher.finalize().to_vec()
+/// Escapes a label for a quoted DOT string, new lines become DOT line breaks.
+#[cfg(feature = "render")]
+fn escape_dot_label(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
 }
 
 #[cfg(test)]
@@ -414,6 +1456,7 @@ mod tests {
     use super::*;
     use rstest::rstest;
 
+    #[cfg(feature = "render")]
     #[rstest]
     #[case("abcdefghijklmnopqrstuvwxyz", 10, "abcde...vwxyz")]
     #[case("abcdefghijklmnopqrstuvwxyz", 5, "ab...xyz")]
@@ -451,11 +1494,13 @@ mod tests {
         value: String,
     }
 
-    impl MerkleTreeData for UserItem_A {
+    impl LeafEncode for UserItem_A {
         fn serialize(&self) -> Vec<u8> {
-            format!("{}", self.value).as_bytes().to_vec()
+            self.value.as_bytes().to_vec()
         }
+    }
 
+    impl NodeLabel for UserItem_A {
         fn mermaid_node_label(&self) -> String {
             format!("<br>{}", self.value)
         }
@@ -477,13 +1522,15 @@ mod tests {
         pub balance: u32,
     }
 
-    impl MerkleTreeData for UserItem_B {
+    impl LeafEncode for UserItem_B {
         fn serialize(&self) -> Vec<u8> {
             format!("({},{})", self.id, self.balance)
                 .as_bytes()
                 .to_vec()
         }
+    }
 
+    impl NodeLabel for UserItem_B {
         fn mermaid_node_label(&self) -> String {
             format!("<br>User ID: {}<br>Balance: {}", self.id, self.balance)
         }
@@ -508,6 +1555,44 @@ mod tests {
         assert!(tree.root().is_none());
     }
 
+    #[test]
+    fn it_can_build_a_tree_from_an_iterator() {
+        let user_data = generate_user_item_a();
+
+        let tree = MerkleTree::build_from_iter(
+            "Bitcoin_Transaction",
+            "Bitcoin_Transaction",
+            user_data.clone(),
+        );
+
+        assert_eq!(
+            tree.root(),
+            MerkleTree::build("Bitcoin_Transaction", "Bitcoin_Transaction", &user_data).root()
+        );
+        assert_eq!(tree.len(), user_data.len());
+    }
+
+    #[test]
+    fn it_can_build_a_tree_of_data_without_labels() {
+        #[derive(Clone)]
+        struct Record(u32);
+
+        impl LeafEncode for Record {
+            fn serialize(&self) -> Vec<u8> {
+                self.0.to_be_bytes().to_vec()
+            }
+        }
+
+        let records: Vec<Record> = (0..5).map(Record).collect();
+        let tree = MerkleTree::build("Record_Leaf", "Record_Branch", &records);
+
+        let root = tree.root().unwrap();
+        let leaf = tree.search_with_path(|record| record.0 == 3).unwrap();
+        assert!(leaf
+            .proof()
+            .verify("Record_Leaf", "Record_Branch", leaf.data(), &root));
+    }
+
     #[test]
     fn it_can_build_a_tree_user_item_a() {
         let user_data = generate_user_item_a();
@@ -538,6 +1623,161 @@ mod tests {
         );
     }
 
+    #[test]
+    fn it_can_traverse_all_nodes() {
+        let user_data = generate_user_item_a();
+        let tree = MerkleTree::build("Bitcoin_Transaction", "Bitcoin_Transaction", &user_data);
+
+        let root = tree.iter_nodes().next().unwrap();
+        assert!(root.parent_node.is_none());
+        assert_eq!(root.direction, NodeDirection::Root);
+        assert_eq!(Some(root.current_node.hash_hex()), tree.root());
+
+        // the odd leaf "eee" is paired with itself, and so is the branch above it
+        let mut leaves = Vec::new();
+        let mut deepest = 0;
+        tree.traverse(|step| {
+            deepest = deepest.max(step.level);
+            if let Some(item) = &step.current_node.user_data {
+                leaves.push(item.value.clone());
+            }
+        });
+
+        assert_eq!(tree.iter_nodes().count(), 15);
+        assert_eq!(deepest, 3);
+        assert_eq!(
+            leaves,
+            vec!["aaa", "bbb", "ccc", "ddd", "eee", "eee", "eee", "eee"]
+        );
+        assert!(tree
+            .iter_nodes()
+            .filter(|step| step.current_node.is_leaf())
+            .all(|step| step.level == 3));
+    }
+
+    #[rstest]
+    #[case(0, 0)]
+    #[case(1, 0)]
+    #[case(2, 1)]
+    #[case(5, 3)]
+    #[case(8, 3)]
+    #[case(9, 4)]
+    fn it_can_iterate_leaves_in_order(#[case] n: usize, #[case] depth: u32) {
+        let user_data = crate::util::generate_random_user_data(n);
+        let tree = MerkleTree::build("ProofOfReserve_Leaf", "ProofOfReserve_Branch", &user_data);
+
+        assert_eq!(tree.len(), n);
+        assert_eq!(tree.is_empty(), n == 0);
+        assert_eq!(tree.depth(), depth);
+        assert_eq!(tree.leaves().len(), n);
+        assert_eq!(tree.leaves().count(), n);
+
+        for (index, data, hash) in tree.leaves() {
+            assert_eq!(data.id, user_data[index].id);
+            assert_eq!(
+                hash,
+                tagged_hash("ProofOfReserve_Leaf", &user_data[index].serialize())
+            );
+            assert_eq!(tree.proof(index).unwrap().hashes.len(), depth as usize);
+        }
+    }
+
+    #[cfg(feature = "render")]
+    #[test]
+    fn it_can_display_dot() {
+        let user_data = generate_user_item_b();
+        let tree = MerkleTree::build("ProofOfReserve_Leaf", "ProofOfReserve_Branch", &user_data);
+
+        let dot = tree.display_dot();
+        let lines: Vec<&str> = dot.lines().collect();
+
+        assert_eq!(lines[0], "digraph MerkleTree {");
+        assert_eq!(lines.last(), Some(&"}"));
+        // one statement per node and one edge per node except the root
+        assert_eq!(
+            lines.iter().filter(|line| line.contains("[label=")).count(),
+            15
+        );
+        assert_eq!(
+            lines.iter().filter(|line| line.contains(" -> ")).count(),
+            14
+        );
+        assert!(dot.contains("\\nUser ID: 3\\nBalance: 3333\"];"));
+
+        let empty: Vec<UserItem_B> = vec![];
+        assert_eq!(
+            MerkleTree::build("ProofOfReserve_Leaf", "ProofOfReserve_Branch", &empty).display_dot(),
+            "Tree is empty."
+        );
+    }
+
+    #[cfg(feature = "render")]
+    #[test]
+    fn it_can_render_with_options() {
+        let user_data = generate_user_item_b();
+        let tree = MerkleTree::build("ProofOfReserve_Leaf", "ProofOfReserve_Branch", &user_data);
+        let root = tree.root().unwrap();
+
+        assert_eq!(
+            tree.display_tree_with(&RenderOptions::default()),
+            tree.display_tree()
+        );
+
+        let top = RenderOptions {
+            max_depth: Some(1),
+            ..RenderOptions::default()
+        };
+        assert_eq!(
+            tree.display_tree_with(&top),
+            [
+                "Root: e752d...66c85",
+                " Left: fafe4...30439",
+                "  ... 2 levels collapsed",
+                " Right: 4cddf...2fbeb",
+                "  ... 2 levels collapsed",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            tree.display_dot_with(&top)
+                .matches("levels collapsed")
+                .count(),
+            2
+        );
+        assert_eq!(
+            tree.display_mermaid_diagram_with(&top)
+                .matches("-.-> Collapsed_")
+                .count(),
+            2
+        );
+
+        let left = tree.iter_nodes().nth(1).unwrap().current_node.hash_hex();
+        let subtree = RenderOptions {
+            subtree_root: Some(left.to_uppercase()),
+            collapse_hashes: false,
+            ..RenderOptions::default()
+        };
+        let rendered = tree.display_tree_with(&subtree);
+        assert!(rendered.starts_with(&format!("Root: {left}\n")));
+        assert!(!rendered.contains(&root));
+        assert_eq!(rendered.lines().count(), 7);
+
+        let missing = RenderOptions {
+            subtree_root: Some("00".repeat(32)),
+            ..RenderOptions::default()
+        };
+        assert_eq!(
+            tree.display_tree_with(&missing),
+            format!("Node {} not found.", "00".repeat(32))
+        );
+    }
+
+    #[cfg(feature = "render")]
+    #[test]
+    fn it_can_escape_dot_label() {
+        assert_eq!(escape_dot_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+
     #[test]
     fn it_can_search_with_path_user_item_a() {
         let user_data = generate_user_item_a();
@@ -547,9 +1787,10 @@ mod tests {
 
         let tree = MerkleTree::build(tag_leaf, tag_branch, &user_data);
         let user_id = "aaa";
-        let (_node, path) = tree
+        let path = tree
             .search_with_path(|user_data| user_data.value == user_id)
-            .unwrap();
+            .unwrap()
+            .path();
 
         assert_eq!(
             path.to_vec(),
@@ -579,9 +1820,10 @@ mod tests {
 
         let tree = MerkleTree::build(tag_leaf, tag_branch, &user_data);
         let user_id = 3u32;
-        let (_node, path) = tree
+        let path = tree
             .search_with_path(|user_data| user_data.id == user_id)
-            .unwrap();
+            .unwrap()
+            .path();
 
         assert_eq!(
             path.to_vec(),
@@ -601,4 +1843,310 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn it_can_search_all_with_path() {
+        let user_data = generate_user_item_b();
+
+        let tag_leaf = "ProofOfReserve_Leaf";
+        let tag_branch = "ProofOfReserve_Branch";
+
+        let tree = MerkleTree::build(tag_leaf, tag_branch, &user_data);
+        let root = tree.root().unwrap();
+        let found = tree.search_all_with_path(|user_data| user_data.balance > 2222);
+
+        assert_eq!(
+            found.iter().map(LeafRef::index).collect::<Vec<_>>(),
+            vec![2, 3, 4]
+        );
+        for leaf in &found {
+            assert_eq!(
+                leaf.path().to_vec(),
+                tree.search_index_with_path(leaf.index())
+                    .unwrap()
+                    .path()
+                    .to_vec()
+            );
+            assert!(leaf
+                .proof()
+                .verify(tag_leaf, tag_branch, leaf.data(), &root));
+        }
+        assert!(tree
+            .search_all_with_path(|user_data| user_data.balance > 9999)
+            .is_empty());
+    }
+
+    #[rstest]
+    #[case(1)]
+    #[case(2)]
+    #[case(3)]
+    fn it_can_search_a_shuffled_dataset_with_duplicate_ids(#[case] seed: u64) {
+        let user_data = util::generate_random_user_data_with(
+            37,
+            &util::DatasetOptions {
+                seed,
+                duplicate_ids: 0.3,
+                shuffle_ids: true,
+                ..Default::default()
+            },
+        );
+
+        let tag_leaf = "ProofOfReserve_Leaf";
+        let tag_branch = "ProofOfReserve_Branch";
+
+        let tree = MerkleTree::build(tag_leaf, tag_branch, &user_data);
+        let root = tree.root().unwrap();
+
+        for data in &user_data {
+            let expected: Vec<usize> = (0..user_data.len())
+                .filter(|&index| user_data[index].id == data.id)
+                .collect();
+            let found = tree.search_all_with_path(|user_data| user_data.id == data.id);
+
+            assert_eq!(
+                found.iter().map(LeafRef::index).collect::<Vec<_>>(),
+                expected
+            );
+            assert_eq!(
+                tree.search_with_path(|user_data| user_data.id == data.id)
+                    .map(|leaf| leaf.index()),
+                Some(expected[0])
+            );
+            for leaf in &found {
+                assert!(leaf
+                    .proof()
+                    .verify(tag_leaf, tag_branch, leaf.data(), &root));
+            }
+        }
+    }
+
+    #[test]
+    fn it_can_prove_a_found_leaf() {
+        let user_data = generate_user_item_b();
+        let tree = MerkleTree::build("ProofOfReserve_Leaf", "ProofOfReserve_Branch", &user_data);
+
+        let leaf = tree
+            .search_with_path(|user_data| user_data.id == 3)
+            .unwrap();
+
+        assert_eq!(leaf.index(), 2);
+        assert_eq!(leaf.data().id, 3);
+        assert_eq!(
+            leaf.hash_hex(),
+            hex::encode(tagged_hash(
+                "ProofOfReserve_Leaf",
+                &user_data[2].serialize()
+            ))
+        );
+        assert_eq!(leaf.proof(), tree.proof(2).unwrap());
+        assert!(leaf.proof().verify(
+            "ProofOfReserve_Leaf",
+            "ProofOfReserve_Branch",
+            leaf.data(),
+            &tree.root().unwrap()
+        ));
+        assert!(tree.search_index_with_path(user_data.len()).is_none());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn it_can_round_trip_tree_and_proof_through_serde() {
+        let user_data = util::generate_random_user_data(5);
+
+        let tag_leaf = "ProofOfReserve_Leaf";
+        let tag_branch = "ProofOfReserve_Branch";
+
+        let tree = MerkleTree::build(tag_leaf, tag_branch, &user_data);
+        let json = serde_json::to_string(&tree).unwrap();
+        let restored: MerkleTree<util::UserData> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(restored.root(), tree.root());
+
+        let proof = restored.proof(3).unwrap();
+        let json = serde_json::to_string(&proof).unwrap();
+        let restored_proof: MerkleProof = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(restored_proof, proof);
+        assert!(restored_proof.verify(tag_leaf, tag_branch, &user_data[3], &tree.root().unwrap()));
+
+        let path = restored
+            .search_with_path(|data| data.id == 2)
+            .unwrap()
+            .path();
+        let json = serde_json::to_string(&path).unwrap();
+        let restored_path: TraversePath = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(restored_path.to_vec(), path.to_vec());
+    }
+
+    #[cfg(feature = "serde")]
+    #[rstest]
+    #[case(OddLeafPolicy::DuplicateLast)]
+    #[case(OddLeafPolicy::PromoteOdd)]
+    #[case(OddLeafPolicy::PadWithEmptyHash)]
+    fn it_can_reject_a_deserialized_tree_with_broken_links(#[case] odd_leaf_policy: OddLeafPolicy) {
+        let user_data = util::generate_random_user_data(5);
+        let tree = MerkleTree::build_with_odd_leaf_policy(
+            "ProofOfReserve_Leaf",
+            "ProofOfReserve_Branch",
+            &user_data,
+            odd_leaf_policy,
+        );
+        let json = serde_json::to_value(&tree).unwrap();
+        let restore =
+            |json: serde_json::Value| serde_json::from_value::<MerkleTree<util::UserData>>(json);
+
+        let restored = restore(json.clone()).unwrap();
+        assert_eq!(restored.root(), tree.root());
+        assert_eq!(restored.proof(4).unwrap(), tree.proof(4).unwrap());
+
+        let mut out_of_bounds = json.clone();
+        out_of_bounds["nodes"][5]["right"] = 99.into();
+        assert!(restore(out_of_bounds).is_err());
+
+        let mut missing_node = json.clone();
+        missing_node["nodes"].as_array_mut().unwrap().remove(0);
+        assert!(restore(missing_node).is_err());
+
+        let mut more_leaves = json;
+        more_leaves["leaf_count"] = 6.into();
+        assert!(restore(more_leaves).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn it_can_reject_a_deserialized_leaf_without_user_data() {
+        let tree = MerkleTree::build(
+            "ProofOfReserve_Leaf",
+            "ProofOfReserve_Branch",
+            &util::generate_random_user_data(5),
+        );
+        let mut json = serde_json::to_value(&tree).unwrap();
+        json["nodes"][3]["user_data"] = serde_json::Value::Null;
+
+        let err = serde_json::from_value::<MerkleTree<util::UserData>>(json)
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            MerkleTreeError::MissingLeafData { index: 3 }.to_string()
+        );
+    }
+
+    #[test]
+    fn it_can_share_a_tree_between_threads() {
+        fn assert_send_sync<S: Send + Sync>(_: &S) {}
+
+        let user_data = util::generate_random_user_data(9);
+        let tree = MerkleTree::build("ProofOfReserve_Leaf", "ProofOfReserve_Branch", &user_data);
+        let shared = tree.clone();
+        assert_send_sync(&shared);
+        assert!(Arc::ptr_eq(&tree.nodes, &shared.nodes));
+
+        let reader = std::thread::spawn(move || (shared.root(), shared.proof(8).unwrap()));
+        let (root, proof) = reader.join().unwrap();
+        assert_eq!(root, tree.root());
+        assert_eq!(proof, tree.proof(8).unwrap());
+    }
+
+    #[rstest]
+    #[case(OddLeafPolicy::DuplicateLast)]
+    #[case(OddLeafPolicy::PromoteOdd)]
+    #[case(OddLeafPolicy::PadWithEmptyHash)]
+    fn it_can_lay_out_nodes_level_by_level(#[case] odd_leaf_policy: OddLeafPolicy) {
+        for n in 1..=9 {
+            let user_data = util::generate_random_user_data(n);
+            let tree = MerkleTree::build_with_odd_leaf_policy(
+                "ProofOfReserve_Leaf",
+                "ProofOfReserve_Branch",
+                &user_data,
+                odd_leaf_policy,
+            );
+            let sizes = level_sizes(n);
+            let node_count: usize = sizes.iter().sum();
+
+            let padded = usize::from(needs_pad(&sizes, odd_leaf_policy));
+            assert_eq!(tree.nodes.len(), node_count + padded);
+            assert_eq!(
+                tree.levels()
+                    .iter()
+                    .map(|level| level.len())
+                    .collect::<Vec<_>>(),
+                sizes
+            );
+            assert_eq!(
+                tree.iter_nodes().next().unwrap().current_node.hash,
+                tree.nodes[node_count - 1].hash
+            );
+
+            for (index, data) in user_data.iter().enumerate() {
+                assert!(tree.nodes[index].is_leaf());
+                assert_eq!(tree.nodes[index].user_data.as_ref().unwrap().id, data.id);
+            }
+        }
+    }
+
+    #[test]
+    fn it_can_parse_traverse_path() {
+        let user_data = generate_user_item_b();
+        let tree = MerkleTree::build("ProofOfReserve_Leaf", "ProofOfReserve_Branch", &user_data);
+        let path = tree
+            .search_with_path(|user_data| user_data.id == 3)
+            .unwrap()
+            .path();
+
+        let parsed = TraversePath::parse(&path.to_vec()).unwrap();
+        assert_eq!(parsed.to_vec(), path.to_vec());
+
+        let upper_case: Vec<(String, u8)> = path
+            .to_vec()
+            .into_iter()
+            .map(|(hash, direction)| (hash.to_uppercase(), direction))
+            .collect();
+        assert_eq!(
+            TraversePath::parse(&upper_case).unwrap().to_vec(),
+            path.to_vec()
+        );
+    }
+
+    #[test]
+    fn it_can_return_raw_hashes() {
+        let user_data = generate_user_item_b();
+        let tree = MerkleTree::build("ProofOfReserve_Leaf", "ProofOfReserve_Branch", &user_data);
+        assert_eq!(tree.root_bytes().map(hex::encode), tree.root());
+
+        let leaf = tree
+            .search_with_path(|user_data| user_data.id == 3)
+            .unwrap();
+        assert_eq!(hex::encode(leaf.hash()), leaf.hash_hex());
+
+        let path = leaf.path();
+        let hashes = path.hash_bytes().unwrap();
+        assert_eq!(hashes.len(), path.hashes.len());
+        assert_eq!(Some(hashes[0]), tree.root_node().map(|node| *node.hash()));
+
+        let mut malformed = path.clone();
+        malformed.hashes[1].pop();
+        assert_eq!(
+            malformed.hash_bytes(),
+            Err(TraversePathError::InvalidHash { step: 1 })
+        );
+    }
+
+    #[rstest]
+    #[case(vec![("ab".repeat(32), 2)], TraversePathError::InvalidDirection { step: 0, value: 2 })]
+    #[case(vec![("ab".repeat(32), 0), ("ab".repeat(32), 7)], TraversePathError::InvalidDirection { step: 1, value: 7 })]
+    #[case(vec![("ab".repeat(31), 0)], TraversePathError::InvalidHash { step: 0 })]
+    #[case(vec![("zz".repeat(32), 1)], TraversePathError::InvalidHash { step: 0 })]
+    #[case(vec![("é".repeat(32), 1)], TraversePathError::InvalidHash { step: 0 })]
+    #[case(
+        vec![("ab".repeat(32), 0); MAX_TRAVERSE_PATH_LEN + 1],
+        TraversePathError::TooLong { len: MAX_TRAVERSE_PATH_LEN + 1, max: MAX_TRAVERSE_PATH_LEN }
+    )]
+    fn it_can_reject_malformed_traverse_path(
+        #[case] steps: Vec<(String, u8)>,
+        #[case] expected: TraversePathError,
+    ) {
+        assert_eq!(TraversePath::parse(&steps).unwrap_err(), expected);
+    }
 }
@@ -0,0 +1,360 @@
+use crate::{tagged_hash, LeafEncode, MerkleProof, MerkleTreeError, NodeDirection, TagHasher};
+
+/// A Merkle Mountain Range, an append only list of leaves with inclusion proofs.
+///
+/// The leaves form a row of perfect binary trees, the mountains, one per bit set in the
+/// leaf count, from the highest on the left to the lowest on the right. Appending a leaf
+/// only adds nodes, it never changes one, so the root and every proof of an earlier size
+/// can still be reproduced with `root_at` and `proof_at`.
+///
+/// The root bags the peaks from right to left, `tagged_hash(tag_branch, peak || bagged)`.
+/// With a power of two leaves there is a single peak, which is the same root
+/// `MerkleTree::build` computes for those leaves.
+#[derive(Debug, Clone)]
+pub struct Mmr<T> {
+    tag_leaf: TagHasher,
+    tag_branch: TagHasher,
+    leaves: Vec<T>,
+    /// The roots of all perfect subtrees of each height, from left to right.
+    levels: Vec<Vec<Vec<u8>>>,
+}
+
+/// An inclusion proof for a leaf of a `Mmr`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MmrProof {
+    /// The number of leaves of the range the proof was generated for.
+    pub leaf_count: usize,
+    /// The path from the leaf up to the peak of its mountain.
+    pub proof: MerkleProof,
+    /// The hex encoded peaks of all mountains, from left to right.
+    pub peaks: Vec<String>,
+}
+
+/// Returns the heights of the mountains of a range with `leaf_count` leaves, from left to right.
+fn mountain_heights(leaf_count: usize) -> impl Iterator<Item = u32> {
+    (0..usize::BITS)
+        .rev()
+        .filter(move |height| leaf_count >> height & 1 == 1)
+}
+
+/// Finds the mountain a leaf is in.
+///
+/// # Returns
+///
+/// An `Option` containing the position of the mountain from the left, its height and the
+/// position of the leaf within it, `None` if the leaf is out of range.
+fn mountain_of(leaf_count: usize, index: usize) -> Option<(usize, u32, usize)> {
+    let mut start = 0;
+    for (mountain, height) in mountain_heights(leaf_count).enumerate() {
+        if index < start + (1 << height) {
+            return Some((mountain, height, index - start));
+        }
+        start += 1 << height;
+    }
+
+    None
+}
+
+/// Bags the peaks from right to left into the root.
+fn bag_peaks(tag_branch: &TagHasher, peaks: &[Vec<u8>]) -> Option<Vec<u8>> {
+    let (last, rest) = peaks.split_last()?;
+
+    Some(rest.iter().rev().fold(last.clone(), |bagged, peak| {
+        tag_branch.hash(&[peak.as_slice(), &bagged].concat())
+    }))
+}
+
+impl<T> Mmr<T>
+where
+    T: LeafEncode,
+{
+    /// Creates an empty range.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_leaf`: The tag used for hashing leaf nodes.
+    /// * `tag_branch`: The tag used for hashing branch nodes and bagging the peaks.
+    pub fn new(tag_leaf: &str, tag_branch: &str) -> Self {
+        Mmr {
+            tag_leaf: TagHasher::new(tag_leaf),
+            tag_branch: TagHasher::new(tag_branch),
+            leaves: Vec::new(),
+            levels: Vec::new(),
+        }
+    }
+
+    /// Returns the number of leaves.
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// Returns `true` if no leaf has been appended.
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// Returns the user data of the leaf at the given position.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.leaves.get(index)
+    }
+
+    /// Appends a leaf and merges the mountains of equal height it completes.
+    ///
+    /// # Returns
+    ///
+    /// The position of the new leaf.
+    pub fn append(&mut self, data: T) -> usize {
+        let mut hash = self.tag_leaf.hash(&data.serialize());
+        self.leaves.push(data);
+
+        let mut height = 0;
+        loop {
+            if self.levels.len() == height {
+                self.levels.push(Vec::new());
+            }
+            let level = &mut self.levels[height];
+            level.push(hash);
+
+            if level.len() & 1 == 1 {
+                break;
+            }
+            hash = self.tag_branch.hash(&level[level.len() - 2..].concat());
+            height += 1;
+        }
+
+        self.leaves.len() - 1
+    }
+
+    fn peaks_at(&self, leaf_count: usize) -> Vec<Vec<u8>> {
+        mountain_heights(leaf_count)
+            .map(|height| self.levels[height as usize][(leaf_count >> height) - 1].clone())
+            .collect()
+    }
+
+    /// Returns the hex encoded peaks of all mountains, from left to right.
+    pub fn peaks(&self) -> Vec<String> {
+        self.peaks_at(self.len()).iter().map(hex::encode).collect()
+    }
+
+    /// Returns the hex encoded root hash.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the root hash, `None` if the range is empty.
+    pub fn root(&self) -> Option<String> {
+        self.root_at(self.len())
+    }
+
+    /// Returns the hex encoded root hash the range had when it held `leaf_count` leaves.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the root hash, `None` if `leaf_count` is 0 or more than the
+    /// number of leaves.
+    pub fn root_at(&self, leaf_count: usize) -> Option<String> {
+        if leaf_count > self.len() {
+            return None;
+        }
+
+        bag_peaks(&self.tag_branch, &self.peaks_at(leaf_count)).map(hex::encode)
+    }
+
+    /// Generates an inclusion proof for the leaf at the given position.
+    pub fn proof(&self, index: usize) -> Result<MmrProof, MerkleTreeError> {
+        self.proof_at(index, self.len())
+    }
+
+    /// Generates an inclusion proof for the leaf at the given position against the root the
+    /// range had when it held `leaf_count` leaves.
+    ///
+    /// # Arguments
+    ///
+    /// * `index`: The position of the leaf.
+    /// * `leaf_count`: The number of leaves of the root to prove against.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `MmrProof`, `MerkleTreeError::LeafIndexOutOfRange` if the
+    /// leaf was not appended before `leaf_count` leaves, or `leaf_count` is more than the
+    /// number of leaves.
+    pub fn proof_at(&self, index: usize, leaf_count: usize) -> Result<MmrProof, MerkleTreeError> {
+        let out_of_range = MerkleTreeError::LeafIndexOutOfRange { index, leaf_count };
+        if leaf_count > self.len() {
+            return Err(out_of_range);
+        }
+        let (_, height, _) = mountain_of(leaf_count, index).ok_or(out_of_range)?;
+
+        let mut proof = MerkleProof::new(index);
+        for level in &self.levels[..height as usize] {
+            let position = index >> proof.hashes.len();
+            let direction = if position & 1 == 0 {
+                NodeDirection::Right
+            } else {
+                NodeDirection::Left
+            };
+            proof.add_sibling(hex::encode(&level[position ^ 1]), direction);
+        }
+
+        Ok(MmrProof {
+            leaf_count,
+            proof,
+            peaks: self.peaks_at(leaf_count).iter().map(hex::encode).collect(),
+        })
+    }
+}
+
+impl MmrProof {
+    /// Verifies that the given user data is included in the range with the given root.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_leaf`: The tag used for hashing leaf nodes.
+    /// * `tag_branch`: The tag used for hashing branch nodes and bagging the peaks.
+    /// * `data`: The user data the proof was generated for.
+    /// * `root`: The hex encoded root hash of the range.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the data hashes up to the peak of the mountain of `leaf_index` and the peaks
+    /// bag into `root`, `false` otherwise.
+    pub fn verify<T>(&self, tag_leaf: &str, tag_branch: &str, data: &T, root: &str) -> bool
+    where
+        T: LeafEncode,
+    {
+        let Some((mountain, height, offset)) = mountain_of(self.leaf_count, self.proof.leaf_index)
+        else {
+            return false;
+        };
+        let Ok(peaks) = self
+            .peaks
+            .iter()
+            .map(hex::decode)
+            .collect::<Result<Vec<_>, _>>()
+        else {
+            return false;
+        };
+
+        let on_path = self.proof.hashes.len() == height as usize
+            && self
+                .proof
+                .directions
+                .iter()
+                .enumerate()
+                .all(|(level, direction)| {
+                    *direction
+                        == if offset >> level & 1 == 0 {
+                            NodeDirection::Right
+                        } else {
+                            NodeDirection::Left
+                        }
+                });
+
+        on_path
+            && peaks.len() == self.leaf_count.count_ones() as usize
+            && self
+                .proof
+                .compute_root(tag_branch, &tagged_hash(tag_leaf, &data.serialize()))
+                .is_some_and(|peak| peak == peaks[mountain])
+            && bag_peaks(&TagHasher::new(tag_branch), &peaks)
+                .is_some_and(|bagged| hex::encode(bagged) == root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{generate_random_user_data, UserData};
+    use crate::MerkleTree;
+    use rstest::rstest;
+
+    const TAG_LEAF: &str = "ProofOfReserve_Leaf";
+    const TAG_BRANCH: &str = "ProofOfReserve_Branch";
+
+    fn mmr_of(user_data: &[UserData]) -> Mmr<UserData> {
+        let mut mmr = Mmr::new(TAG_LEAF, TAG_BRANCH);
+        for data in user_data {
+            mmr.append(data.clone());
+        }
+
+        mmr
+    }
+
+    #[rstest]
+    #[case(1)]
+    #[case(2)]
+    #[case(3)]
+    #[case(7)]
+    #[case(8)]
+    #[case(13)]
+    fn it_can_prove_every_leaf_of_a_mmr(#[case] n: usize) {
+        let user_data = generate_random_user_data(n);
+        let mmr = mmr_of(&user_data);
+        let root = mmr.root().unwrap();
+
+        assert_eq!(mmr.len(), n);
+        assert_eq!(mmr.peaks().len(), n.count_ones() as usize);
+        for (index, data) in user_data.iter().enumerate() {
+            let proof = mmr.proof(index).unwrap();
+            assert!(proof.verify(TAG_LEAF, TAG_BRANCH, data, &root));
+            assert_eq!(mmr.get(index).unwrap().id, data.id);
+        }
+
+        if n > 1 {
+            let proof = mmr.proof(0).unwrap();
+            assert!(!proof.verify(TAG_LEAF, TAG_BRANCH, &user_data[n - 1], &root));
+        }
+        assert_eq!(
+            mmr.proof(n),
+            Err(MerkleTreeError::LeafIndexOutOfRange {
+                index: n,
+                leaf_count: n
+            })
+        );
+    }
+
+    #[test]
+    fn it_can_keep_earlier_proofs_valid() {
+        let user_data = generate_random_user_data(11);
+        let mut mmr = mmr_of(&user_data[..5]);
+        let earlier_root = mmr.root().unwrap();
+        let earlier_proof = mmr.proof(3).unwrap();
+
+        for data in &user_data[5..] {
+            mmr.append(data.clone());
+        }
+
+        assert_ne!(mmr.root().unwrap(), earlier_root);
+        assert_eq!(mmr.root_at(5).unwrap(), earlier_root);
+        assert_eq!(mmr.proof_at(3, 5).unwrap(), earlier_proof);
+        assert!(earlier_proof.verify(TAG_LEAF, TAG_BRANCH, &user_data[3], &earlier_root));
+        assert!(mmr.proof_at(5, 5).is_err());
+        assert!(mmr.root_at(12).is_none());
+    }
+
+    #[test]
+    fn it_can_match_the_tree_root_with_a_single_peak() {
+        let user_data = generate_random_user_data(8);
+
+        assert_eq!(
+            mmr_of(&user_data).root(),
+            MerkleTree::build(TAG_LEAF, TAG_BRANCH, &user_data).root()
+        );
+        assert!(Mmr::<UserData>::new(TAG_LEAF, TAG_BRANCH).root().is_none());
+    }
+
+    #[test]
+    fn it_can_reject_a_proof_moved_to_another_leaf() {
+        let user_data = generate_random_user_data(6);
+        let mmr = mmr_of(&user_data);
+        let root = mmr.root().unwrap();
+
+        let mut moved = mmr.proof(1).unwrap();
+        moved.proof.leaf_index = 5;
+        assert!(!moved.verify(TAG_LEAF, TAG_BRANCH, &user_data[1], &root));
+
+        let mut dropped_peak = mmr.proof(1).unwrap();
+        dropped_peak.peaks.pop();
+        assert!(!dropped_peak.verify(TAG_LEAF, TAG_BRANCH, &user_data[1], &root));
+    }
+}
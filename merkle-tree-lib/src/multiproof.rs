@@ -0,0 +1,329 @@
+use crate::{
+    level_sizes, DomainSeparation, LeafEncode, MerkleTree, MerkleTreeError, OddLeafPolicy,
+    TagHasher,
+};
+
+/// An inclusion proof covering several leaves at once.
+///
+/// Siblings shared by more than one leaf, or that can be computed from the proven leaves
+/// themselves, are only included once or not at all. `hashes` holds the remaining siblings
+/// in the order the verifier consumes them: level by level from the leaves up, and from left
+/// to right within a level.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MultiProof {
+    pub leaf_count: usize,
+    pub leaf_indices: Vec<usize>,
+    pub hashes: Vec<String>,
+    /// How the tree pairs the last node of odd levels, proofs without it are `DuplicateLast`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub odd_leaf_policy: OddLeafPolicy,
+}
+
+impl<T> MerkleTree<T>
+where
+    T: Clone + LeafEncode,
+{
+    /// Generates a single proof for all leaves at the given positions.
+    ///
+    /// # Arguments
+    ///
+    /// * `indices`: The positions of the leaves, in any order. Duplicates are ignored.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `MultiProof`, a `MerkleTreeError` if `indices` is empty or out
+    /// of range. The proof lists the leaf positions sorted ascending.
+    pub fn proof_for_indices(&self, indices: &[usize]) -> Result<MultiProof, MerkleTreeError> {
+        let mut known = indices.to_vec();
+        known.sort_unstable();
+        known.dedup();
+
+        let last = *known.last().ok_or(MerkleTreeError::NoLeafIndices)?;
+        if last >= self.leaf_count {
+            return Err(MerkleTreeError::LeafIndexOutOfRange {
+                index: last,
+                leaf_count: self.leaf_count,
+            });
+        }
+
+        let mut proof = MultiProof {
+            leaf_count: self.leaf_count,
+            leaf_indices: known.clone(),
+            hashes: Vec::new(),
+            odd_leaf_policy: self.odd_leaf_policy,
+        };
+
+        let levels = self.levels();
+        for level in &levels[..levels.len() - 1] {
+            let mut parents = Vec::with_capacity(known.len());
+            let mut i = 0;
+
+            while i < known.len() {
+                let position = known[i];
+                if position & 1 == 0 {
+                    if known.get(i + 1) == Some(&(position + 1)) {
+                        i += 1;
+                    } else if let Some(sibling) = level.get(position + 1) {
+                        proof.hashes.push(hex::encode(sibling.hash));
+                    }
+                } else {
+                    proof.hashes.push(hex::encode(level[position - 1].hash));
+                }
+
+                parents.push(position / 2);
+                i += 1;
+            }
+
+            known = parents;
+        }
+
+        Ok(proof)
+    }
+}
+
+impl MultiProof {
+    /// Recomputes the root hash from the hashes of the proven leaves.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_branch`: The tag used for hashing branch nodes.
+    /// * `leaf_hashes`: The hashes of the leaves, in the order of `leaf_indices`.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the root hash, `None` if the proof is malformed or does not
+    /// match the number of leaf hashes.
+    pub fn compute_root(&self, tag_branch: &str, leaf_hashes: &[Vec<u8>]) -> Option<Vec<u8>> {
+        self.compute_top(DomainSeparation::Tags, tag_branch, leaf_hashes)
+    }
+
+    /// Recomputes the hash of the top node, which is the root unless the root commits to
+    /// the leaf count.
+    fn compute_top(
+        &self,
+        domain_separation: DomainSeparation,
+        tag_branch: &str,
+        leaf_hashes: &[Vec<u8>],
+    ) -> Option<Vec<u8>> {
+        if leaf_hashes.len() != self.leaf_indices.len()
+            || self.leaf_indices.is_empty()
+            || self.leaf_indices.windows(2).any(|pair| pair[0] >= pair[1])
+            || *self.leaf_indices.last()? >= self.leaf_count
+        {
+            return None;
+        }
+
+        let mut siblings = self
+            .hashes
+            .iter()
+            .map(hex::decode)
+            .collect::<Result<Vec<_>, _>>()
+            .ok()?
+            .into_iter();
+
+        let tag_branch = TagHasher::new(tag_branch);
+        let mut known: Vec<(usize, Vec<u8>)> = self
+            .leaf_indices
+            .iter()
+            .copied()
+            .zip(leaf_hashes.iter().cloned())
+            .collect();
+
+        let sizes = level_sizes(self.leaf_count);
+        for &size in &sizes[..sizes.len() - 1] {
+            let mut parents = Vec::with_capacity(known.len());
+            let mut i = 0;
+
+            while i < known.len() {
+                let (position, hash) = &known[i];
+                let parent = if position & 1 == 0 {
+                    if known.get(i + 1).map(|(next, _)| *next) == Some(position + 1) {
+                        i += 1;
+                        domain_separation.branch_hash_with(&tag_branch, hash, &known[i].1)
+                    } else if position + 1 < size {
+                        domain_separation.branch_hash_with(&tag_branch, hash, &siblings.next()?)
+                    } else {
+                        self.odd_leaf_policy
+                            .lone_parent_hash(domain_separation, &tag_branch, hash)
+                    }
+                } else {
+                    domain_separation.branch_hash_with(&tag_branch, &siblings.next()?, hash)
+                };
+
+                parents.push((position / 2, parent));
+                i += 1;
+            }
+
+            known = parents;
+        }
+
+        if siblings.next().is_some() {
+            return None;
+        }
+
+        known.pop().map(|(_position, hash)| hash)
+    }
+
+    /// Verifies that all the given user data is included in the tree with the given root.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_leaf`: The tag used for hashing leaf nodes.
+    /// * `tag_branch`: The tag used for hashing branch nodes.
+    /// * `leaves`: The user data of the proven leaves, in the order of `leaf_indices`.
+    /// * `root`: The hex encoded root hash of the tree.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the leaves and the proof reproduce `root`, `false` otherwise.
+    pub fn verify<T>(&self, tag_leaf: &str, tag_branch: &str, leaves: &[T], root: &str) -> bool
+    where
+        T: LeafEncode,
+    {
+        self.verify_with(DomainSeparation::Tags, tag_leaf, tag_branch, leaves, root)
+    }
+
+    /// Verifies that all the given user data is included in a tree built with the given
+    /// domain separation.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain_separation`: How the tree separates leaf, branch and root hashes.
+    /// * `tag_leaf`: The tag used for hashing leaf nodes.
+    /// * `tag_branch`: The tag used for hashing branch nodes.
+    /// * `leaves`: The user data of the proven leaves, in the order of `leaf_indices`.
+    /// * `root`: The hex encoded root hash of the tree.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the leaves and the proof reproduce `root`, `false` otherwise.
+    pub fn verify_with<T>(
+        &self,
+        domain_separation: DomainSeparation,
+        tag_leaf: &str,
+        tag_branch: &str,
+        leaves: &[T],
+        root: &str,
+    ) -> bool
+    where
+        T: LeafEncode,
+    {
+        let leaf_hashes: Vec<Vec<u8>> = leaves
+            .iter()
+            .map(|data| domain_separation.leaf_hash(tag_leaf, data.serialize().as_slice()))
+            .collect();
+
+        self.compute_top(domain_separation, tag_branch, &leaf_hashes)
+            .is_some_and(|top| {
+                hex::encode(domain_separation.root_hash(self.leaf_count, &top)) == root
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::{generate_random_user_data, UserData};
+    use crate::{DomainSeparation, MerkleTree, MerkleTreeError};
+    use rstest::rstest;
+
+    const TAG_LEAF: &str = "ProofOfReserve_Leaf";
+    const TAG_BRANCH: &str = "ProofOfReserve_Branch";
+
+    #[rstest]
+    #[case(1)]
+    #[case(2)]
+    #[case(5)]
+    #[case(7)]
+    #[case(8)]
+    fn it_can_verify_multi_proof_for_every_subset(#[case] n: usize) {
+        let user_data = generate_random_user_data(n);
+        let tree = MerkleTree::build(TAG_LEAF, TAG_BRANCH, &user_data);
+        let root = tree.root().unwrap();
+
+        for subset in 1..(1u32 << n) {
+            let indices: Vec<usize> = (0..n).filter(|i| subset & (1 << i) != 0).collect();
+            let leaves: Vec<UserData> = indices.iter().map(|&i| user_data[i].clone()).collect();
+
+            let proof = tree.proof_for_indices(&indices).unwrap();
+
+            assert_eq!(proof.leaf_indices, indices);
+            assert!(proof.verify(TAG_LEAF, TAG_BRANCH, &leaves, &root));
+        }
+    }
+
+    #[test]
+    fn it_can_verify_prefixed_multi_proof() {
+        let user_data = generate_random_user_data(7);
+        let tree = MerkleTree::build_with_domain_separation(
+            TAG_LEAF,
+            TAG_BRANCH,
+            &user_data,
+            DomainSeparation::Prefixed,
+        );
+        let root = tree.root().unwrap();
+        let leaves = [
+            user_data[1].clone(),
+            user_data[4].clone(),
+            user_data[6].clone(),
+        ];
+
+        let proof = tree.proof_for_indices(&[1, 4, 6]).unwrap();
+
+        assert!(proof.verify_with(
+            DomainSeparation::Prefixed,
+            TAG_LEAF,
+            TAG_BRANCH,
+            &leaves,
+            &root
+        ));
+        assert!(!proof.verify(TAG_LEAF, TAG_BRANCH, &leaves, &root));
+    }
+
+    #[test]
+    fn it_can_deduplicate_shared_siblings() {
+        let user_data = generate_random_user_data(16);
+        let tree = MerkleTree::build(TAG_LEAF, TAG_BRANCH, &user_data);
+
+        let proof = tree.proof_for_indices(&[0, 1, 2, 3]).unwrap();
+
+        // the four leaves form a complete subtree, only the two uncles above it are needed
+        assert_eq!(proof.hashes.len(), 2);
+        assert_eq!(tree.proof_for_indices(&[3, 1, 2, 0, 1]).unwrap(), proof);
+    }
+
+    #[test]
+    fn it_can_reject_invalid_multi_proof() {
+        let user_data = generate_random_user_data(7);
+        let tree = MerkleTree::build(TAG_LEAF, TAG_BRANCH, &user_data);
+        let root = tree.root().unwrap();
+
+        assert_eq!(
+            tree.proof_for_indices(&[]),
+            Err(MerkleTreeError::NoLeafIndices)
+        );
+        assert_eq!(
+            tree.proof_for_indices(&[2, 7]),
+            Err(MerkleTreeError::LeafIndexOutOfRange {
+                index: 7,
+                leaf_count: 7
+            })
+        );
+
+        let proof = tree.proof_for_indices(&[1, 4]).unwrap();
+        let leaves = vec![user_data[1].clone(), user_data[4].clone()];
+        assert!(proof.verify(TAG_LEAF, TAG_BRANCH, &leaves, &root));
+
+        let swapped = vec![user_data[4].clone(), user_data[1].clone()];
+        assert!(!proof.verify(TAG_LEAF, TAG_BRANCH, &swapped, &root));
+        assert!(!proof.verify(TAG_LEAF, TAG_BRANCH, &leaves[..1], &root));
+
+        let mut extra_hash = proof.clone();
+        extra_hash.hashes.push(proof.hashes[0].clone());
+        assert!(!extra_hash.verify(TAG_LEAF, TAG_BRANCH, &leaves, &root));
+
+        let mut missing_hash = proof.clone();
+        missing_hash.hashes.pop();
+        assert!(!missing_hash.verify(TAG_LEAF, TAG_BRANCH, &leaves, &root));
+    }
+}
@@ -0,0 +1,147 @@
+use crate::{DomainSeparation, TagHasher};
+
+/// The hash the last node of an odd level is paired with by `OddLeafPolicy::PadWithEmptyHash`.
+pub(crate) const EMPTY_HASH: [u8; 32] = [0; 32];
+
+/// How the last node of a level with an odd number of nodes is paired up.
+///
+/// `DuplicateLast` is what Bitcoin does and what trees are built with by default. It gives
+/// `[a, b, c]` and `[a, b, c, c]` the same root (CVE-2012-2459), so a published root does
+/// not pin down the leaves unless the leaf count is committed as well, see
+/// `DomainSeparation::Prefixed`. The other policies do not have that ambiguity.
+///
+/// Inclusion proofs carry whatever sibling the policy produced, so `MerkleProof::verify`
+/// works the same for every policy. `MultiProof` records the policy of the tree it was
+/// generated from. `MerkleRootHasher`, `DelegationBundle` and the `reference` module only
+/// follow `DuplicateLast`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum OddLeafPolicy {
+    /// The last node is paired with a copy of itself.
+    #[default]
+    DuplicateLast,
+    /// The last node moves up to the next level unchanged, like in RFC 6962 trees. Its
+    /// leaves sit closer to the root and their proofs are shorter.
+    PromoteOdd,
+    /// The last node is paired with a node whose hash is 32 zero bytes.
+    PadWithEmptyHash,
+}
+
+impl OddLeafPolicy {
+    /// Calculates the parent of the last node of an odd level.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain_separation`: How the children's hashes are combined.
+    /// * `tag_branch`: The hasher of the tag used for hashing branch nodes.
+    /// * `hash`: The hash of the last node.
+    ///
+    /// # Returns
+    ///
+    /// The hash of the node one level up, which is `hash` itself for `PromoteOdd`.
+    pub(crate) fn lone_parent_hash(
+        self,
+        domain_separation: DomainSeparation,
+        tag_branch: &TagHasher,
+        hash: &[u8],
+    ) -> Vec<u8> {
+        match self {
+            OddLeafPolicy::DuplicateLast => {
+                domain_separation.branch_hash_with(tag_branch, hash, hash)
+            }
+            OddLeafPolicy::PromoteOdd => hash.to_vec(),
+            OddLeafPolicy::PadWithEmptyHash => {
+                domain_separation.branch_hash_with(tag_branch, hash, &EMPTY_HASH)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{generate_random_user_data, UserData};
+    use crate::{tagged_hash, LeafEncode, MerkleTree};
+    use rstest::rstest;
+
+    const TAG_LEAF: &str = "ProofOfReserve_Leaf";
+    const TAG_BRANCH: &str = "ProofOfReserve_Branch";
+
+    fn build(user_data: &[UserData], odd_leaf_policy: OddLeafPolicy) -> MerkleTree<UserData> {
+        MerkleTree::build_with_odd_leaf_policy(TAG_LEAF, TAG_BRANCH, user_data, odd_leaf_policy)
+    }
+
+    #[rstest]
+    #[case(OddLeafPolicy::DuplicateLast)]
+    #[case(OddLeafPolicy::PromoteOdd)]
+    #[case(OddLeafPolicy::PadWithEmptyHash)]
+    fn it_can_prove_every_leaf(#[case] odd_leaf_policy: OddLeafPolicy) {
+        for n in 1..=11 {
+            let user_data = generate_random_user_data(n);
+            let tree = build(&user_data, odd_leaf_policy);
+            let root = tree.root().unwrap();
+
+            assert_eq!(tree.leaves().len(), n);
+            for (index, data) in user_data.iter().enumerate() {
+                let proof = tree.proof(index).unwrap();
+                assert!(proof.verify(TAG_LEAF, TAG_BRANCH, data, &root));
+                assert_eq!(
+                    tree.search_index_with_path(index).unwrap().data().id,
+                    data.id
+                );
+            }
+
+            let indices: Vec<usize> = (0..n).step_by(2).collect();
+            let leaves: Vec<UserData> = indices.iter().map(|&i| user_data[i].clone()).collect();
+            let multi_proof = tree.proof_for_indices(&indices).unwrap();
+            assert!(multi_proof.verify(TAG_LEAF, TAG_BRANCH, &leaves, &root));
+
+            let restored = MerkleTree::<UserData>::from_bytes(&tree.to_bytes()).unwrap();
+            assert_eq!(restored.root(), tree.root());
+            assert_eq!(restored.odd_leaf_policy(), odd_leaf_policy);
+        }
+    }
+
+    #[rstest]
+    #[case(OddLeafPolicy::DuplicateLast, true)]
+    #[case(OddLeafPolicy::PromoteOdd, false)]
+    #[case(OddLeafPolicy::PadWithEmptyHash, false)]
+    fn it_can_tell_a_duplicated_leaf_apart(
+        #[case] odd_leaf_policy: OddLeafPolicy,
+        #[case] same_root: bool,
+    ) {
+        let user_data = generate_random_user_data(3);
+        let mut duplicated = user_data.clone();
+        duplicated.push(user_data[2].clone());
+
+        assert_eq!(
+            build(&user_data, odd_leaf_policy).root() == build(&duplicated, odd_leaf_policy).root(),
+            same_root
+        );
+    }
+
+    #[test]
+    fn it_can_pair_the_last_node_as_told() {
+        let user_data = generate_random_user_data(3);
+        let [a, b, c] = [0, 1, 2].map(|i| tagged_hash(TAG_LEAF, &user_data[i].serialize()));
+        let branch = |left: &[u8], right: &[u8]| tagged_hash(TAG_BRANCH, &[left, right].concat());
+        let ab = branch(&a, &b);
+
+        assert_eq!(
+            build(&user_data, OddLeafPolicy::DuplicateLast).root(),
+            Some(hex::encode(branch(&ab, &branch(&c, &c))))
+        );
+        assert_eq!(
+            build(&user_data, OddLeafPolicy::PromoteOdd).root(),
+            Some(hex::encode(branch(&ab, &c)))
+        );
+        assert_eq!(
+            build(&user_data, OddLeafPolicy::PadWithEmptyHash).root(),
+            Some(hex::encode(branch(&ab, &branch(&c, &EMPTY_HASH))))
+        );
+
+        let promoted = build(&user_data, OddLeafPolicy::PromoteOdd);
+        assert_eq!(promoted.proof(2).unwrap().hashes, vec![hex::encode(&ab)]);
+    }
+}
@@ -0,0 +1,266 @@
+//! OpenZeppelin compatible Merkle trees.
+//!
+//! Contracts verify inclusion with OpenZeppelin's `MerkleProof.verify(proof, root, leaf)`,
+//! which hashes with keccak256 and no tags, and sorts every pair before hashing it, so a
+//! proof is only the list of sibling hashes. Trees are laid out like the `StandardMerkleTree`
+//! of `@openzeppelin/merkle-tree`: every leaf is hashed twice, the leaves are sorted by their
+//! hash and stored in a complete binary tree, so the root matches the one it computes when
+//! `LeafEncode::serialize` returns the ABI encoding of the leaf values.
+
+pub use merkle_hash::keccak256;
+
+use crate::{Hash, LeafEncode, MerkleTreeError};
+
+/// Calculates `keccak256(keccak256(data))`, the leaf hash of `StandardMerkleTree`.
+pub fn leaf_hash(data: &[u8]) -> Hash {
+    keccak256(&keccak256(data))
+}
+
+/// Calculates the parent of two nodes, the smaller hash goes first.
+pub fn hash_pair(a: &Hash, b: &Hash) -> Hash {
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+    keccak256(&[first.as_slice(), second.as_slice()].concat())
+}
+
+/// Formats a hash as a Solidity `bytes32` literal, `0x` followed by 64 hex digits.
+pub fn to_bytes32(hash: &Hash) -> String {
+    format!("0x{}", hex::encode(hash))
+}
+
+/// A Merkle Tree whose proofs verify with OpenZeppelin's `MerkleProof`.
+pub struct SortedPairTree {
+    /// The nodes in the order of a binary heap, the root first and the leaves last.
+    nodes: Vec<Hash>,
+    /// The position in `nodes` of the leaf of every input, in input order.
+    leaf_positions: Vec<usize>,
+}
+
+impl SortedPairTree {
+    /// Builds a tree from the given user data.
+    ///
+    /// # Arguments
+    ///
+    /// * `input`: The user data of the leaves, each hashed with `leaf_hash`.
+    pub fn build<T>(input: &[T]) -> Self
+    where
+        T: LeafEncode,
+    {
+        Self::from_leaf_hashes(
+            input
+                .iter()
+                .map(|data| leaf_hash(&data.serialize()))
+                .collect(),
+        )
+    }
+
+    /// Builds a tree from leaves that are already hashed.
+    ///
+    /// # Arguments
+    ///
+    /// * `leaves`: The leaf hashes, in input order. The tree stores them sorted, `proof`
+    ///   still takes the position in `leaves`.
+    pub fn from_leaf_hashes(leaves: Vec<Hash>) -> Self {
+        let mut order: Vec<usize> = (0..leaves.len()).collect();
+        order.sort_by_key(|&index| leaves[index]);
+
+        let node_count = (2 * leaves.len()).saturating_sub(1);
+        let mut nodes = vec![[0; 32]; node_count];
+        let mut leaf_positions = vec![0; leaves.len()];
+        for (rank, &index) in order.iter().enumerate() {
+            let position = node_count - 1 - rank;
+            nodes[position] = leaves[index];
+            leaf_positions[index] = position;
+        }
+        for position in (0..node_count.saturating_sub(leaves.len())).rev() {
+            nodes[position] = hash_pair(&nodes[2 * position + 1], &nodes[2 * position + 2]);
+        }
+
+        SortedPairTree {
+            nodes,
+            leaf_positions,
+        }
+    }
+
+    /// Returns the root hash, `None` if the tree has no leaves.
+    pub fn root(&self) -> Option<Hash> {
+        self.nodes.first().copied()
+    }
+
+    /// Returns the number of leaves.
+    pub fn len(&self) -> usize {
+        self.leaf_positions.len()
+    }
+
+    /// Returns `true` if the tree has no leaves.
+    pub fn is_empty(&self) -> bool {
+        self.leaf_positions.is_empty()
+    }
+
+    /// Generates the inclusion proof of a leaf.
+    ///
+    /// # Arguments
+    ///
+    /// * `index`: The position of the leaf in the input the tree was built from.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `SortedPairProof`, `MerkleTreeError::LeafIndexOutOfRange` if
+    /// there is no leaf at `index`.
+    pub fn proof(&self, index: usize) -> Result<SortedPairProof, MerkleTreeError> {
+        let mut position = self.leaf_positions.get(index).copied().ok_or(
+            MerkleTreeError::LeafIndexOutOfRange {
+                index,
+                leaf_count: self.len(),
+            },
+        )?;
+
+        let mut hashes = Vec::new();
+        while position > 0 {
+            let sibling = if position & 1 == 1 {
+                position + 1
+            } else {
+                position - 1
+            };
+            hashes.push(self.nodes[sibling]);
+            position = (position - 1) / 2;
+        }
+
+        Ok(SortedPairProof {
+            leaf_index: index,
+            hashes,
+        })
+    }
+}
+
+/// An inclusion proof of a `SortedPairTree`, the sibling hashes from the leaf upwards.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortedPairProof {
+    pub leaf_index: usize,
+    pub hashes: Vec<Hash>,
+}
+
+impl SortedPairProof {
+    /// Recomputes the root from a leaf hash like `MerkleProof.processProof` does.
+    pub fn compute_root(&self, leaf: &Hash) -> Hash {
+        self.hashes
+            .iter()
+            .fold(*leaf, |current, sibling| hash_pair(&current, sibling))
+    }
+
+    /// Verifies that the given user data is included in the tree with the given root.
+    ///
+    /// # Arguments
+    ///
+    /// * `data`: The user data the proof was generated for.
+    /// * `root`: The root hash of the tree.
+    ///
+    /// # Returns
+    ///
+    /// `true` if hashing the data up the proof reproduces `root`, `false` otherwise.
+    pub fn verify<T>(&self, data: &T, root: &Hash) -> bool
+    where
+        T: LeafEncode,
+    {
+        self.compute_root(&leaf_hash(&data.serialize())) == *root
+    }
+
+    /// Formats the proof as a `bytes32[]` array, e.g. `["0x12…", "0xab…"]`, which can be
+    /// passed as it is to a Solidity verifier from `cast`, Remix or ethers.
+    pub fn to_bytes32_array(&self) -> String {
+        let hashes: Vec<String> = self
+            .hashes
+            .iter()
+            .map(|hash| format!("\"{}\"", to_bytes32(hash)))
+            .collect();
+
+        format!("[{}]", hashes.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::generate_random_user_data;
+    use rstest::rstest;
+
+    /// An `(address, uint256)` leaf, ABI encoded like `abi.encode(account, amount)`.
+    struct Allowance {
+        account: [u8; 20],
+        amount: u128,
+    }
+
+    impl LeafEncode for Allowance {
+        fn serialize(&self) -> Vec<u8> {
+            let mut encoded = vec![0; 64];
+            encoded[12..32].copy_from_slice(&self.account);
+            encoded[48..].copy_from_slice(&self.amount.to_be_bytes());
+            encoded
+        }
+    }
+
+    #[test]
+    fn it_can_reproduce_the_standard_merkle_tree_root() {
+        // the example of the `@openzeppelin/merkle-tree` readme
+        let allowances = [
+            Allowance {
+                account: [0x11; 20],
+                amount: 5_000_000_000_000_000_000,
+            },
+            Allowance {
+                account: [0x22; 20],
+                amount: 2_500_000_000_000_000_000,
+            },
+        ];
+        let tree = SortedPairTree::build(&allowances);
+        let root = tree.root().unwrap();
+
+        assert_eq!(
+            to_bytes32(&root),
+            "0xd4dee0beab2d53f2cc83e567171bd2820e49898130a22622b10ead383e90bd77"
+        );
+        for (index, allowance) in allowances.iter().enumerate() {
+            assert!(tree.proof(index).unwrap().verify(allowance, &root));
+        }
+    }
+
+    #[rstest]
+    #[case(1)]
+    #[case(2)]
+    #[case(5)]
+    #[case(8)]
+    #[case(13)]
+    fn it_can_verify_proof_for_every_leaf(#[case] n: usize) {
+        let user_data = generate_random_user_data(n);
+        let tree = SortedPairTree::build(&user_data);
+        let root = tree.root().unwrap();
+
+        for (index, data) in user_data.iter().enumerate() {
+            let proof = tree.proof(index).unwrap();
+            assert_eq!(proof.leaf_index, index);
+            assert!(proof.verify(data, &root));
+            assert!(!proof.verify(&user_data[(index + 1) % n], &root) || n == 1);
+        }
+        assert_eq!(
+            tree.proof(n),
+            Err(MerkleTreeError::LeafIndexOutOfRange {
+                index: n,
+                leaf_count: n
+            })
+        );
+    }
+
+    #[test]
+    fn it_can_export_proof_as_bytes32_array() {
+        let tree = SortedPairTree::from_leaf_hashes(vec![[1; 32], [2; 32], [3; 32]]);
+        let proof = tree.proof(0).unwrap();
+
+        assert_eq!(hash_pair(&[1; 32], &[2; 32]), hash_pair(&[2; 32], &[1; 32]));
+        assert_eq!(
+            proof.to_bytes32_array(),
+            format!("[\"0x{}\",\"0x{}\"]", "02".repeat(32), "03".repeat(32))
+        );
+        assert!(SortedPairTree::from_leaf_hashes(Vec::new())
+            .root()
+            .is_none());
+    }
+}
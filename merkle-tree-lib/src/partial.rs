@@ -0,0 +1,175 @@
+use crate::{DomainSeparation, LeafEncode, MerkleTree, MerkleTreeError, MultiProof};
+
+/// A tree pruned down to some of its leaves, like the merkleblock of Bitcoin.
+///
+/// Only the user data of the kept leaves, the root and the hashes needed to recompute it
+/// from them are retained, so the subset can be shipped to a light client and checked there
+/// without the rest of the tree. The hashes are stored as a `MultiProof`, siblings shared by
+/// several kept leaves are included once.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PartialMerkleTree<T> {
+    /// The user data of the kept leaves, in the order of `proof.leaf_indices`.
+    pub leaves: Vec<T>,
+    pub proof: MultiProof,
+    pub root: String,
+    /// How the tree separates leaf, branch and root hashes, trees without it are `Tags`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub domain_separation: DomainSeparation,
+}
+
+impl<T> MerkleTree<T>
+where
+    T: Clone + LeafEncode,
+{
+    /// Prunes the tree down to the leaves at the given positions.
+    ///
+    /// # Arguments
+    ///
+    /// * `keep`: The positions of the leaves to keep, in any order. Duplicates are ignored.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `PartialMerkleTree`, a `MerkleTreeError` if `keep` is empty or
+    /// out of range. The kept leaves are sorted by their position.
+    pub fn prune(&self, keep: &[usize]) -> Result<PartialMerkleTree<T>, MerkleTreeError> {
+        let proof = self.proof_for_indices(keep)?;
+        let leaves = self.levels()[0];
+
+        Ok(PartialMerkleTree {
+            leaves: proof
+                .leaf_indices
+                .iter()
+                .filter_map(|&index| leaves[index].user_data.clone())
+                .collect(),
+            root: self.root().unwrap_or_default(),
+            domain_separation: self.domain_separation,
+            proof,
+        })
+    }
+}
+
+impl<T> PartialMerkleTree<T>
+where
+    T: LeafEncode,
+{
+    /// Returns the number of leaves of the whole tree.
+    pub fn leaf_count(&self) -> usize {
+        self.proof.leaf_count
+    }
+
+    /// Returns the positions of the kept leaves in the whole tree, sorted ascending.
+    pub fn leaf_indices(&self) -> &[usize] {
+        &self.proof.leaf_indices
+    }
+
+    /// Returns the user data of the kept leaf at the given position of the whole tree.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the user data, `None` if the leaf was pruned.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.proof
+            .leaf_indices
+            .binary_search(&index)
+            .ok()
+            .and_then(|position| self.leaves.get(position))
+    }
+
+    /// Verifies that the kept leaves are included in the tree with the given root.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_leaf`: The tag used for hashing leaf nodes.
+    /// * `tag_branch`: The tag used for hashing branch nodes.
+    /// * `root`: The hex encoded root hash the partial tree is expected to commit to.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the stored root is `root` and the kept leaves hash up to it, `false` otherwise.
+    pub fn verify(&self, tag_leaf: &str, tag_branch: &str, root: &str) -> bool {
+        self.root == root
+            && self.proof.verify_with(
+                self.domain_separation,
+                tag_leaf,
+                tag_branch,
+                &self.leaves,
+                root,
+            )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::generate_random_user_data;
+    use crate::OddLeafPolicy;
+    use rstest::rstest;
+
+    const TAG_LEAF: &str = "ProofOfReserve_Leaf";
+    const TAG_BRANCH: &str = "ProofOfReserve_Branch";
+
+    #[rstest]
+    #[case(DomainSeparation::Tags, OddLeafPolicy::DuplicateLast)]
+    #[case(DomainSeparation::Tags, OddLeafPolicy::PromoteOdd)]
+    #[case(DomainSeparation::Prefixed, OddLeafPolicy::PadWithEmptyHash)]
+    fn it_can_prune_a_tree_to_some_leaves(
+        #[case] domain_separation: DomainSeparation,
+        #[case] odd_leaf_policy: OddLeafPolicy,
+    ) {
+        let user_data = generate_random_user_data(11);
+        let tree = MerkleTree::build_with(
+            TAG_LEAF,
+            TAG_BRANCH,
+            &user_data,
+            domain_separation,
+            odd_leaf_policy,
+        );
+        let root = tree.root().unwrap();
+
+        let partial = tree.prune(&[9, 2, 3, 9]).unwrap();
+
+        assert_eq!(partial.leaf_count(), 11);
+        assert_eq!(partial.leaf_indices(), &[2, 3, 9]);
+        assert_eq!(
+            partial.get(3).map(LeafEncode::serialize),
+            Some(user_data[3].serialize())
+        );
+        assert!(partial.get(4).is_none());
+        assert!(partial.verify(TAG_LEAF, TAG_BRANCH, &root));
+        assert!(!partial.verify(TAG_BRANCH, TAG_BRANCH, &root));
+
+        let mut tampered = partial.clone();
+        tampered.leaves[1].balance += 1;
+        assert!(!tampered.verify(TAG_LEAF, TAG_BRANCH, &root));
+    }
+
+    #[test]
+    fn it_can_reject_pruning_to_no_leaves() {
+        let tree = MerkleTree::build(TAG_LEAF, TAG_BRANCH, &generate_random_user_data(4));
+
+        assert_eq!(tree.prune(&[]).err(), Some(MerkleTreeError::NoLeafIndices));
+        assert_eq!(
+            tree.prune(&[1, 4]).err(),
+            Some(MerkleTreeError::LeafIndexOutOfRange {
+                index: 4,
+                leaf_count: 4
+            })
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn it_can_round_trip_partial_tree_through_serde() {
+        let tree = MerkleTree::build(TAG_LEAF, TAG_BRANCH, &generate_random_user_data(6));
+        let partial = tree.prune(&[0, 5]).unwrap();
+
+        let json = serde_json::to_string(&partial).unwrap();
+        let restored: PartialMerkleTree<crate::util::UserData> =
+            serde_json::from_str(&json).unwrap();
+
+        assert_eq!(restored.proof, partial.proof);
+        assert_eq!(restored.root, partial.root);
+        assert!(restored.verify(TAG_LEAF, TAG_BRANCH, &tree.root().unwrap()));
+    }
+}
@@ -0,0 +1,43 @@
+//! The stable public surface of the crate.
+//!
+//! Everything needed to build trees, generate proofs and verify them is re-exported here,
+//! so `use merkle_tree_lib::prelude::*;` keeps compiling across minor releases. Items that
+//! are only reachable from the crate root may still change.
+
+pub use crate::{
+    recompute_root_from_leaves, tagged_hash, Hash, LeafEncode, LeafRef, MerkleProof,
+    MerkleRootHasher, MerkleTree, MerkleTreeBuilder, MerkleTreeError, MultiProof, NodeDirection,
+    NodeLabel, TraversePath, TreeDescriptor,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::UserData;
+
+    // Pins the signatures of the prelude: any breaking change to them fails to compile here.
+    #[test]
+    fn it_can_keep_the_prelude_signatures() {
+        let _: fn(&str, &str, &[UserData]) -> MerkleTree<UserData> = MerkleTree::build;
+        let _: fn(&str, &str) -> MerkleTreeBuilder<UserData> = MerkleTree::builder;
+        let _: fn(&MerkleTreeBuilder<UserData>, &[UserData]) -> MerkleTree<UserData> =
+            MerkleTreeBuilder::build;
+        let _: fn(&MerkleTree<UserData>) -> Option<String> = MerkleTree::root;
+        let _: fn(&MerkleTree<UserData>, usize) -> Result<MerkleProof, MerkleTreeError> =
+            MerkleTree::proof;
+        let _: fn(&MerkleTree<UserData>, &[usize]) -> Result<MultiProof, MerkleTreeError> =
+            MerkleTree::proof_for_indices;
+        let _: fn(&MerkleTree<UserData>, usize) -> Option<LeafRef<'_, UserData>> =
+            MerkleTree::search_index_with_path;
+        let _: fn(&MerkleProof, &str, &str, &UserData, &str) -> bool = MerkleProof::verify;
+        let _: fn(&MultiProof, &str, &str, &[UserData], &str) -> bool = MultiProof::verify;
+        let _: fn(&TreeDescriptor, &[Hash]) -> Option<Hash> = recompute_root_from_leaves;
+        let _: fn(&str, &[u8]) -> Vec<u8> = tagged_hash;
+        let _: fn(&str, &str) -> MerkleRootHasher = MerkleRootHasher::new;
+        let _: fn(MerkleRootHasher) -> Option<String> = MerkleRootHasher::finalize;
+        let _: fn(&UserData) -> Vec<u8> = <UserData as LeafEncode>::serialize;
+        let _: fn(&UserData) -> String = <UserData as NodeLabel>::mermaid_node_label;
+        let _: fn(u8) -> Result<NodeDirection, u8> = NodeDirection::try_from;
+        let _: Option<TraversePath> = None;
+    }
+}
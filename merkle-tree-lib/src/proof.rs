@@ -0,0 +1,414 @@
+use crate::{DomainSeparation, Hash, LeafEncode, MerkleTreeError, NodeDirection, TagHasher};
+
+const HASH_LEN: usize = 32;
+/// The leaf index as `u64` and the number of siblings as `u8`.
+const HEADER_LEN: usize = 9;
+
+/// An inclusion proof for a single leaf.
+///
+/// The proof holds the sibling hashes on the path from the leaf up to the root, ordered
+/// from the leaf upwards. The direction tells on which side the sibling sits, so the
+/// parent hash is `tagged_hash(tag_branch, sibling || current)` for `Left` and
+/// `tagged_hash(tag_branch, current || sibling)` for `Right`, or their prefixed variants in
+/// `DomainSeparation::Prefixed` trees.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MerkleProof {
+    pub leaf_index: usize,
+    pub hashes: Vec<String>,
+    pub directions: Vec<NodeDirection>,
+}
+
+impl MerkleProof {
+    pub(crate) fn new(leaf_index: usize) -> Self {
+        MerkleProof {
+            leaf_index,
+            hashes: Vec::new(),
+            directions: Vec::new(),
+        }
+    }
+
+    /// Adds the next sibling on the way up to the root.
+    ///
+    /// # Arguments
+    ///
+    /// * `hash`: The hex encoded hash of the sibling node.
+    /// * `direction`: The side of the parent the sibling sits on (Left or Right).
+    pub(crate) fn add_sibling(&mut self, hash: String, direction: NodeDirection) {
+        self.hashes.push(hash);
+        self.directions.push(direction);
+    }
+
+    /// Recomputes the root hash from the hash of the leaf and the siblings in the proof.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_branch`: The tag used for hashing branch nodes.
+    /// * `leaf_hash`: The hash of the leaf the proof was generated for.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the root hash, `None` if a sibling is not valid hex.
+    pub fn compute_root(&self, tag_branch: &str, leaf_hash: &[u8]) -> Option<Vec<u8>> {
+        self.compute_top(DomainSeparation::Tags, tag_branch, leaf_hash)
+    }
+
+    /// Recomputes the hash of the top node, which is the root unless the root commits to
+    /// the leaf count.
+    fn compute_top(
+        &self,
+        domain_separation: DomainSeparation,
+        tag_branch: &str,
+        leaf_hash: &[u8],
+    ) -> Option<Vec<u8>> {
+        let tag_branch = TagHasher::new(tag_branch);
+        self.hashes.iter().zip(self.directions.iter()).try_fold(
+            leaf_hash.to_vec(),
+            |current, (hash, direction)| {
+                let sibling = hex::decode(hash).ok()?;
+
+                Some(match direction {
+                    NodeDirection::Left => {
+                        domain_separation.branch_hash_with(&tag_branch, &sibling, &current)
+                    }
+                    _ => domain_separation.branch_hash_with(&tag_branch, &current, &sibling),
+                })
+            },
+        )
+    }
+
+    /// Verifies that the given user data is included in the tree with the given root.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_leaf`: The tag used for hashing leaf nodes.
+    /// * `tag_branch`: The tag used for hashing branch nodes.
+    /// * `data`: The user data the proof was generated for.
+    /// * `root`: The hex encoded root hash of the tree.
+    ///
+    /// # Returns
+    ///
+    /// `true` if hashing the data up the proof reproduces `root`, `false` otherwise.
+    pub fn verify<T>(&self, tag_leaf: &str, tag_branch: &str, data: &T, root: &str) -> bool
+    where
+        T: LeafEncode,
+    {
+        self.verify_with(DomainSeparation::Tags, 0, tag_leaf, tag_branch, data, root)
+    }
+
+    /// Verifies that the given user data is included in a tree built with the given domain
+    /// separation.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain_separation`: How the tree separates leaf, branch and root hashes.
+    /// * `leaf_count`: The number of leaves of the tree, the `Prefixed` root commits to it.
+    /// * `tag_leaf`: The tag used for hashing leaf nodes.
+    /// * `tag_branch`: The tag used for hashing branch nodes.
+    /// * `data`: The user data the proof was generated for.
+    /// * `root`: The hex encoded root hash of the tree.
+    ///
+    /// # Returns
+    ///
+    /// `true` if hashing the data up the proof reproduces `root`, `false` otherwise.
+    pub fn verify_with<T>(
+        &self,
+        domain_separation: DomainSeparation,
+        leaf_count: usize,
+        tag_leaf: &str,
+        tag_branch: &str,
+        data: &T,
+        root: &str,
+    ) -> bool
+    where
+        T: LeafEncode,
+    {
+        let leaf_hash = domain_separation.leaf_hash(tag_leaf, data.serialize().as_slice());
+
+        self.compute_top(domain_separation, tag_branch, &leaf_hash)
+            .is_some_and(|top| hex::encode(domain_separation.root_hash(leaf_count, &top)) == root)
+    }
+
+    /// Converts the `MerkleProof` to a vector of (hash, direction) tuples.
+    /// The direction is represented as a `u8` (0 for Left, 1 for Right).
+    ///
+    /// # Returns
+    ///
+    /// A `Vec<(String, u8)>` representing the proof.
+    pub fn to_vec(&self) -> Vec<(String, u8)> {
+        self.hashes
+            .iter()
+            .zip(self.directions.iter())
+            .map(|(hash, direction)| (hash.to_string(), direction.value()))
+            .collect()
+    }
+
+    /// Encodes the proof into its compact binary form.
+    ///
+    /// The layout is the leaf index as a little endian `u64`, the number of siblings as `u8`,
+    /// a bitfield of the directions with one bit per sibling, least significant bit first and
+    /// set for `Right`, followed by the 32 byte sibling hashes from the leaf upwards. A proof
+    /// takes a little more than 32 bytes per level.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the encoded proof, `MerkleTreeError::MalformedProof` if a sibling
+    /// is not a hex encoded 32 byte hash or the proof has more than 255 siblings.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, MerkleTreeError> {
+        let len = u8::try_from(self.hashes.len()).map_err(|_| MerkleTreeError::MalformedProof)?;
+        if self.directions.len() != self.hashes.len() {
+            return Err(MerkleTreeError::MalformedProof);
+        }
+
+        let mut bytes = Vec::with_capacity(Self::encoded_len(len));
+        bytes.extend_from_slice(&(self.leaf_index as u64).to_le_bytes());
+        bytes.push(len);
+
+        let mut directions = vec![0; self.directions.len().div_ceil(8)];
+        for (step, direction) in self.directions.iter().enumerate() {
+            if *direction != NodeDirection::Left {
+                directions[step / 8] |= 1 << (step % 8);
+            }
+        }
+        bytes.extend_from_slice(&directions);
+
+        for hash in self.hash_bytes()? {
+            bytes.extend_from_slice(&hash);
+        }
+
+        Ok(bytes)
+    }
+
+    /// Decodes the sibling hashes, from the leaf upwards.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the 32 byte hashes, `MerkleTreeError::MalformedProof` if a sibling
+    /// is not a hex encoded 32 byte hash.
+    pub fn hash_bytes(&self) -> Result<Vec<Hash>, MerkleTreeError> {
+        self.hashes
+            .iter()
+            .map(|hash| {
+                hex::decode(hash)
+                    .ok()
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .ok_or(MerkleTreeError::MalformedProof)
+            })
+            .collect()
+    }
+
+    /// Returns the siblings as (hash, direction) tuples of bytes, like `to_vec` does with hex
+    /// strings.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the tuples, `MerkleTreeError::MalformedProof` if a sibling is not
+    /// a hex encoded 32 byte hash.
+    pub fn to_byte_vec(&self) -> Result<Vec<(Hash, u8)>, MerkleTreeError> {
+        Ok(self
+            .hash_bytes()?
+            .into_iter()
+            .zip(self.directions.iter().map(NodeDirection::value))
+            .collect())
+    }
+
+    /// Decodes a proof encoded by `to_bytes`.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes`: The encoded proof.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the proof, `MerkleTreeError::MalformedProof` if `bytes` is
+    /// truncated, has trailing bytes, sets a direction bit beyond the last sibling or holds a
+    /// leaf index that does not fit in a `usize`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MerkleTreeError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(MerkleTreeError::MalformedProof);
+        }
+        let (header, body) = bytes.split_at(HEADER_LEN);
+        let len = header[8];
+        if bytes.len() != Self::encoded_len(len) {
+            return Err(MerkleTreeError::MalformedProof);
+        }
+
+        let leaf_index = u64::from_le_bytes(header[..8].try_into().unwrap());
+        let (directions, hashes) = body.split_at((len as usize).div_ceil(8));
+        let unused_bits = directions.len() * 8 - len as usize;
+        if unused_bits > 0 && directions[directions.len() - 1] >> (8 - unused_bits) != 0 {
+            return Err(MerkleTreeError::MalformedProof);
+        }
+
+        let mut proof = MerkleProof::new(
+            usize::try_from(leaf_index).map_err(|_| MerkleTreeError::MalformedProof)?,
+        );
+        for (step, hash) in hashes.chunks(HASH_LEN).enumerate() {
+            let direction = match directions[step / 8] >> (step % 8) & 1 {
+                0 => NodeDirection::Left,
+                _ => NodeDirection::Right,
+            };
+            proof.add_sibling(hex::encode(hash), direction);
+        }
+
+        Ok(proof)
+    }
+
+    /// Encodes the proof as the hex string of `to_bytes`.
+    pub fn to_hex(&self) -> Result<String, MerkleTreeError> {
+        self.to_bytes().map(hex::encode)
+    }
+
+    /// Decodes a proof encoded by `to_hex`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the proof, `MerkleTreeError::MalformedProof` if `hex` is not
+    /// valid hex or not a valid encoded proof, see `from_bytes`.
+    pub fn from_hex(hex: &str) -> Result<Self, MerkleTreeError> {
+        let bytes = hex::decode(hex).map_err(|_| MerkleTreeError::MalformedProof)?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Returns the length of an encoded proof with `len` siblings.
+    fn encoded_len(len: u8) -> usize {
+        HEADER_LEN + (len as usize).div_ceil(8) + len as usize * HASH_LEN
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::{generate_random_user_data, UserData};
+    use crate::{MerkleProof, MerkleTree, MerkleTreeError};
+    use rstest::rstest;
+
+    const TAG_LEAF: &str = "ProofOfReserve_Leaf";
+    const TAG_BRANCH: &str = "ProofOfReserve_Branch";
+
+    #[rstest]
+    #[case(1)]
+    #[case(2)]
+    #[case(5)]
+    #[case(8)]
+    #[case(13)]
+    fn it_can_verify_proof_for_every_leaf(#[case] n: usize) {
+        let user_data = generate_random_user_data(n);
+        let tree = MerkleTree::build(TAG_LEAF, TAG_BRANCH, &user_data);
+        let root = tree.root().unwrap();
+
+        for (index, data) in user_data.iter().enumerate() {
+            let proof = tree.proof(index).unwrap();
+            assert_eq!(proof.leaf_index, index);
+            assert!(proof.verify(TAG_LEAF, TAG_BRANCH, data, &root));
+        }
+
+        assert_eq!(
+            tree.proof(n),
+            Err(MerkleTreeError::LeafIndexOutOfRange {
+                index: n,
+                leaf_count: n
+            })
+        );
+    }
+
+    #[rstest]
+    #[case(1)]
+    #[case(2)]
+    #[case(5)]
+    #[case(9)]
+    #[case(200)]
+    #[case(300)]
+    fn it_can_round_trip_proof_through_bytes_and_hex(#[case] n: usize) {
+        let user_data = generate_random_user_data(n);
+        let tree = MerkleTree::build(TAG_LEAF, TAG_BRANCH, &user_data);
+        let root = tree.root().unwrap();
+
+        for index in [0, n / 2, n - 1] {
+            let proof = tree.proof(index).unwrap();
+            let bytes = proof.to_bytes().unwrap();
+            assert_eq!(
+                bytes.len(),
+                9 + proof.hashes.len().div_ceil(8) + 32 * proof.hashes.len()
+            );
+
+            let decoded = MerkleProof::from_bytes(&bytes).unwrap();
+            assert_eq!(decoded, proof);
+            assert!(decoded.verify(TAG_LEAF, TAG_BRANCH, &user_data[index], &root));
+            assert_eq!(
+                MerkleProof::from_hex(&proof.to_hex().unwrap()).unwrap(),
+                proof
+            );
+        }
+    }
+
+    #[test]
+    fn it_can_return_raw_sibling_hashes() {
+        let tree = MerkleTree::build(TAG_LEAF, TAG_BRANCH, &generate_random_user_data(5));
+        let mut proof = tree.proof(4).unwrap();
+
+        let hashes = proof.hash_bytes().unwrap();
+        assert_eq!(
+            hashes.iter().map(hex::encode).collect::<Vec<_>>(),
+            proof.hashes
+        );
+        assert_eq!(
+            proof
+                .to_byte_vec()
+                .unwrap()
+                .into_iter()
+                .map(|(hash, direction)| (hex::encode(hash), direction))
+                .collect::<Vec<_>>(),
+            proof.to_vec()
+        );
+
+        proof.hashes[2] = "ab".repeat(31);
+        assert_eq!(proof.hash_bytes(), Err(MerkleTreeError::MalformedProof));
+    }
+
+    #[test]
+    fn it_can_reject_malformed_encoded_proof() {
+        let tree = MerkleTree::build(TAG_LEAF, TAG_BRANCH, &generate_random_user_data(5));
+        let bytes = tree.proof(4).unwrap().to_bytes().unwrap();
+
+        let mut unused_direction = bytes.clone();
+        unused_direction[9] |= 0x80;
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+
+        for malformed in [
+            &bytes[..bytes.len() - 1],
+            &bytes[..5],
+            &trailing,
+            &unused_direction,
+        ] {
+            assert_eq!(
+                MerkleProof::from_bytes(malformed),
+                Err(MerkleTreeError::MalformedProof)
+            );
+        }
+        assert_eq!(
+            MerkleProof::from_hex("not hex"),
+            Err(MerkleTreeError::MalformedProof)
+        );
+
+        let mut proof = tree.proof(0).unwrap();
+        proof.hashes[0].truncate(62);
+        assert_eq!(proof.to_bytes(), Err(MerkleTreeError::MalformedProof));
+    }
+
+    #[test]
+    fn it_can_reject_proof_for_other_data() {
+        let user_data = generate_random_user_data(5);
+        let tree = MerkleTree::build(TAG_LEAF, TAG_BRANCH, &user_data);
+        let root = tree.root().unwrap();
+
+        let proof = tree.proof(2).unwrap();
+        let forged = UserData {
+            id: 3,
+            balance: 1_000_000,
+        };
+
+        assert!(!proof.verify(TAG_LEAF, TAG_BRANCH, &forged, &root));
+        assert!(!proof.verify(TAG_LEAF, TAG_BRANCH, &user_data[1], &root));
+    }
+}
@@ -0,0 +1,100 @@
+//! A small reference implementation of the root computation.
+//!
+//! Everything in here is a pure function over hashes that only depends on `merkle-hash`, so
+//! it can be compiled on its own (including to `wasm32-unknown-unknown`) and used by
+//! external parties to check the roots produced by the optimized builders in this crate.
+
+pub use merkle_hash::Hash;
+
+use crate::tagged_hash;
+
+/// Describes how a tree combines its nodes.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TreeDescriptor {
+    pub tag_leaf: String,
+    pub tag_branch: String,
+}
+
+impl TreeDescriptor {
+    pub fn new(tag_leaf: &str, tag_branch: &str) -> Self {
+        TreeDescriptor {
+            tag_leaf: tag_leaf.to_string(),
+            tag_branch: tag_branch.to_string(),
+        }
+    }
+}
+
+/// Recomputes the root hash of a tree from the hashes of its leaves.
+///
+/// Pairs of nodes are combined with `tagged_hash(tag_branch, left || right)` level by level,
+/// and the last node of a level with an odd number of nodes is paired with itself, the same
+/// way `MerkleTree::build` does.
+///
+/// # Arguments
+///
+/// * `descriptor`: The description of the tree the leaves belong to.
+/// * `leaf_hashes`: The hashes of the leaves, from left to right.
+///
+/// # Returns
+///
+/// An `Option` containing the root hash, `None` if there are no leaves.
+pub fn recompute_root_from_leaves(
+    descriptor: &TreeDescriptor,
+    leaf_hashes: &[Hash],
+) -> Option<Hash> {
+    let mut level = leaf_hashes.to_vec();
+
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| {
+                let (left, right) = (pair[0], *pair.last().unwrap());
+                let hash = tagged_hash(&descriptor.tag_branch, &[left, right].concat());
+                hash.try_into().unwrap()
+            })
+            .collect();
+    }
+
+    level.first().copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::generate_random_user_data;
+    use crate::{LeafEncode, MerkleTree};
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(1)]
+    #[case(2)]
+    #[case(3)]
+    #[case(5)]
+    #[case(8)]
+    #[case(1_000)]
+    fn it_can_recompute_root_like_build(#[case] n: usize) {
+        let descriptor = TreeDescriptor::new("ProofOfReserve_Leaf", "ProofOfReserve_Branch");
+        let user_data = generate_random_user_data(n);
+        let tree = MerkleTree::build(&descriptor.tag_leaf, &descriptor.tag_branch, &user_data);
+
+        let leaf_hashes: Vec<Hash> = user_data
+            .iter()
+            .map(|data| {
+                tagged_hash(&descriptor.tag_leaf, &data.serialize())
+                    .try_into()
+                    .unwrap()
+            })
+            .collect();
+        let root = recompute_root_from_leaves(&descriptor, &leaf_hashes).unwrap();
+
+        assert_eq!(hex::encode(root), tree.root().unwrap());
+    }
+
+    #[test]
+    fn it_can_recompute_root_with_empty_input() {
+        let descriptor = TreeDescriptor::new("ProofOfReserve_Leaf", "ProofOfReserve_Branch");
+
+        assert!(recompute_root_from_leaves(&descriptor, &[]).is_none());
+    }
+}
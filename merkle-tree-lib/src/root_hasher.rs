@@ -0,0 +1,144 @@
+use crate::{LeafEncode, TagHasher};
+
+/// Computes the root hash of a tree from a stream of leaves without building the tree.
+///
+/// Only the roots of the complete subtrees seen so far are kept, at most one per level,
+/// so memory grows with the height of the tree instead of the number of leaves. The root
+/// is the same as the one of `MerkleTree::build` over the same leaves.
+#[derive(Debug, Clone)]
+pub struct MerkleRootHasher {
+    tag_leaf: TagHasher,
+    tag_branch: TagHasher,
+    /// The roots of the complete subtrees with their level, highest level first.
+    peaks: Vec<(u32, Vec<u8>)>,
+    leaf_count: usize,
+}
+
+impl MerkleRootHasher {
+    pub fn new(tag_leaf: &str, tag_branch: &str) -> Self {
+        MerkleRootHasher {
+            tag_leaf: TagHasher::new(tag_leaf),
+            tag_branch: TagHasher::new(tag_branch),
+            peaks: Vec::new(),
+            leaf_count: 0,
+        }
+    }
+
+    /// Computes the root hash of the given leaves in a single pass.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_leaf`: The tag used for hashing leaf nodes.
+    /// * `tag_branch`: The tag used for hashing branch nodes.
+    /// * `input`: The user data of the leaves, in order.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the hex encoded root hash, `None` if there are no leaves.
+    pub fn root_of<T, I>(tag_leaf: &str, tag_branch: &str, input: I) -> Option<String>
+    where
+        T: LeafEncode,
+        I: IntoIterator<Item = T>,
+    {
+        let mut hasher = Self::new(tag_leaf, tag_branch);
+        for data in input {
+            hasher.push(&data);
+        }
+        hasher.finalize()
+    }
+
+    /// Returns the number of leaves pushed so far.
+    pub fn leaf_count(&self) -> usize {
+        self.leaf_count
+    }
+
+    /// Hashes the user data of the next leaf and adds it to the tree.
+    pub fn push<T: LeafEncode>(&mut self, data: &T) {
+        let hash = self.tag_leaf.hash(&data.serialize());
+        self.push_leaf_hash(hash);
+    }
+
+    /// Adds the next leaf by its already computed hash.
+    pub fn push_leaf_hash(&mut self, hash: Vec<u8>) {
+        self.leaf_count += 1;
+
+        let mut level = 0;
+        let mut hash = hash;
+        while self.peaks.last().is_some_and(|(peak, _)| *peak == level) {
+            let (_, left) = self.peaks.pop().unwrap();
+            hash = self.tag_branch.hash_parts(&[&left, &hash]);
+            level += 1;
+        }
+
+        self.peaks.push((level, hash));
+    }
+
+    /// Completes the tree and returns its root hash.
+    ///
+    /// The remaining subtrees are joined from the lowest level up. A subtree without a left
+    /// neighbour on its own level is the last node of an odd level, so it is paired with
+    /// itself, exactly like `MerkleTree::build` does.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the hex encoded root hash, `None` if no leaf has been pushed.
+    pub fn finalize(mut self) -> Option<String> {
+        let (mut level, mut hash) = self.peaks.pop()?;
+
+        while let Some((peak, left)) = self.peaks.last() {
+            hash = if *peak == level {
+                let combined = self.tag_branch.hash_parts(&[left, &hash]);
+                self.peaks.pop();
+                combined
+            } else {
+                self.tag_branch.hash_parts(&[&hash, &hash])
+            };
+            level += 1;
+        }
+
+        Some(hex::encode(hash))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::generate_random_user_data;
+    use crate::MerkleTree;
+    use rstest::rstest;
+
+    const TAG_LEAF: &str = "ProofOfReserve_Leaf";
+    const TAG_BRANCH: &str = "ProofOfReserve_Branch";
+
+    #[rstest]
+    #[case(1)]
+    #[case(2)]
+    #[case(3)]
+    #[case(5)]
+    #[case(8)]
+    #[case(13)]
+    #[case(100)]
+    #[case(1025)]
+    fn it_can_stream_the_same_root_as_build(#[case] n: usize) {
+        let user_data = generate_random_user_data(n);
+
+        assert_eq!(
+            MerkleRootHasher::root_of(TAG_LEAF, TAG_BRANCH, user_data.iter().cloned()),
+            MerkleTree::build(TAG_LEAF, TAG_BRANCH, &user_data).root()
+        );
+    }
+
+    #[test]
+    fn it_can_keep_one_peak_per_level() {
+        let mut hasher = MerkleRootHasher::new(TAG_LEAF, TAG_BRANCH);
+        for data in generate_random_user_data(1023) {
+            hasher.push(&data);
+        }
+
+        assert_eq!(hasher.leaf_count(), 1023);
+        assert_eq!(hasher.peaks.len(), 10);
+        assert!(MerkleRootHasher::new(TAG_LEAF, TAG_BRANCH)
+            .finalize()
+            .is_none());
+    }
+}
@@ -0,0 +1,180 @@
+use merkle_hash::hmac_sha256;
+
+use crate::{Hash, LeafEncode, NodeLabel, SnapshotData};
+
+/// User data mixed with a per leaf salt before it is hashed.
+///
+/// The serialized form of most user data is easy to guess, e.g. `id,balance` pairs, so
+/// published leaf hashes can be brute forced back into the data. Prefixing every leaf with
+/// 32 secret bytes makes that infeasible. The salt is part of the leaf, so it is stored in
+/// snapshots and has to be handed out together with the proof, the leaf does not verify
+/// without it.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct Salted<T> {
+    #[cfg_attr(feature = "serde", serde(with = "hex::serde"))]
+    salt: Hash,
+    data: T,
+}
+
+impl<T> Salted<T> {
+    /// Salts the user data with the given salt, which should come from a secure random source.
+    pub fn new(salt: Hash, data: T) -> Self {
+        Salted { salt, data }
+    }
+
+    /// Returns the salt.
+    pub fn salt(&self) -> &Hash {
+        &self.salt
+    }
+
+    /// Returns the user data.
+    pub fn data(&self) -> &T {
+        &self.data
+    }
+
+    /// Returns the user data, dropping the salt.
+    pub fn into_data(self) -> T {
+        self.data
+    }
+}
+
+impl<T> Salted<T>
+where
+    T: LeafEncode,
+{
+    /// Salts the user data with `HMAC-SHA256(key, data.serialize())`.
+    ///
+    /// The salt can be derived again from the key at any time, and nobody without the key
+    /// can derive the salt of another leaf. Equal user data gets equal salts.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The secret key of the tree owner.
+    /// * `data`: The user data to salt.
+    pub fn with_key(key: &[u8], data: T) -> Self {
+        Salted {
+            salt: hmac_sha256(key, &data.serialize()),
+            data,
+        }
+    }
+}
+
+impl<T> LeafEncode for Salted<T>
+where
+    T: LeafEncode,
+{
+    fn serialize(&self) -> Vec<u8> {
+        [self.salt.as_slice(), &self.data.serialize()].concat()
+    }
+}
+
+impl<T> NodeLabel for Salted<T>
+where
+    T: NodeLabel,
+{
+    /// The label of the user data, the salt is not shown.
+    fn mermaid_node_label(&self) -> String {
+        self.data.mermaid_node_label()
+    }
+
+    fn dot_node_label(&self) -> String {
+        self.data.dot_node_label()
+    }
+}
+
+impl<T> SnapshotData for Salted<T>
+where
+    T: SnapshotData,
+{
+    fn deserialize(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 32 {
+            return None;
+        }
+
+        let (salt, data) = bytes.split_at(32);
+        Some(Salted {
+            salt: salt.try_into().ok()?,
+            data: T::deserialize(data)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{generate_random_user_data, UserData};
+    use crate::MerkleTree;
+
+    const TAG_LEAF: &str = "ProofOfReserve_Leaf";
+    const TAG_BRANCH: &str = "ProofOfReserve_Branch";
+    const KEY: &[u8] = b"server secret";
+
+    fn salted_user_data(n: usize) -> Vec<Salted<UserData>> {
+        generate_random_user_data(n)
+            .into_iter()
+            .map(|data| Salted::with_key(KEY, data))
+            .collect()
+    }
+
+    #[test]
+    fn it_can_prove_salted_leaves() {
+        let user_data = salted_user_data(5);
+        let tree = MerkleTree::build(TAG_LEAF, TAG_BRANCH, &user_data);
+        let root = tree.root().unwrap();
+
+        let plain: Vec<UserData> = user_data.iter().map(|data| data.data().clone()).collect();
+        assert_ne!(
+            MerkleTree::build(TAG_LEAF, TAG_BRANCH, &plain).root(),
+            Some(root.clone())
+        );
+
+        let proof = tree.proof(2).unwrap();
+        assert!(proof.verify(TAG_LEAF, TAG_BRANCH, &user_data[2], &root));
+
+        // the user data alone, or with the salt of another leaf, does not verify
+        let wrong_salt = Salted::new(*user_data[3].salt(), user_data[2].data().clone());
+        assert!(!proof.verify(TAG_LEAF, TAG_BRANCH, &wrong_salt, &root));
+    }
+
+    #[test]
+    fn it_can_derive_salts_from_a_key() {
+        let user_data = generate_random_user_data(2);
+
+        let salted = Salted::with_key(KEY, user_data[0].clone());
+        assert_eq!(
+            salted.salt(),
+            Salted::with_key(KEY, user_data[0].clone()).salt()
+        );
+        assert_ne!(
+            salted.salt(),
+            Salted::with_key(KEY, user_data[1].clone()).salt()
+        );
+        assert_ne!(
+            salted.salt(),
+            Salted::with_key(b"other secret", user_data[0].clone()).salt()
+        );
+        assert_eq!(salted.into_data().id, user_data[0].id);
+    }
+
+    #[test]
+    fn it_can_restore_salted_leaves_from_a_snapshot() {
+        let tree = MerkleTree::build(TAG_LEAF, TAG_BRANCH, &salted_user_data(7));
+
+        let restored: MerkleTree<Salted<UserData>> =
+            MerkleTree::from_bytes(&tree.to_bytes()).unwrap();
+
+        assert_eq!(restored.root(), tree.root());
+        assert_eq!(
+            restored
+                .leaves()
+                .map(|(_, data, _)| data.serialize())
+                .collect::<Vec<_>>(),
+            tree.leaves()
+                .map(|(_, data, _)| data.serialize())
+                .collect::<Vec<_>>()
+        );
+        assert!(Salted::<UserData>::deserialize(&[0; 31]).is_none());
+    }
+}
@@ -0,0 +1,641 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::{
+    level_sizes, DomainSeparation, Hash, LeafEncode, MerkleNode, MerkleTree, MerkleTreeError,
+    OddLeafPolicy, TagHasher,
+};
+
+/// Identifies a binary snapshot produced by `MerkleTree::to_bytes`.
+const SNAPSHOT_MAGIC: &[u8; 4] = b"MKTS";
+const HASH_LEN: usize = 32;
+/// Snapshots of trees built with the default domain separation and odd leaf policy.
+const SNAPSHOT_VERSION: u8 = 1;
+/// Snapshots that store the domain separation and the odd leaf policy after the version.
+const SNAPSHOT_VERSION_LAYOUT: u8 = 2;
+/// The longest header: magic, version, domain separation, odd leaf policy and leaf count.
+const MAX_HEADER_LEN: usize = 15;
+
+/// The header of a snapshot: how its hashes were computed and how many leaves it has.
+struct SnapshotHeader {
+    domain_separation: DomainSeparation,
+    odd_leaf_policy: OddLeafPolicy,
+    leaf_count: u64,
+}
+
+impl SnapshotHeader {
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = SNAPSHOT_MAGIC.to_vec();
+        if self.domain_separation == DomainSeparation::Tags
+            && self.odd_leaf_policy == OddLeafPolicy::DuplicateLast
+        {
+            bytes.push(SNAPSHOT_VERSION);
+        } else {
+            bytes.push(SNAPSHOT_VERSION_LAYOUT);
+            bytes.push(match self.domain_separation {
+                DomainSeparation::Tags => 0,
+                DomainSeparation::Prefixed => 1,
+            });
+            bytes.push(match self.odd_leaf_policy {
+                OddLeafPolicy::DuplicateLast => 0,
+                OddLeafPolicy::PromoteOdd => 1,
+                OddLeafPolicy::PadWithEmptyHash => 2,
+            });
+        }
+        bytes.extend_from_slice(&self.leaf_count.to_le_bytes());
+
+        bytes
+    }
+
+    /// Decodes the header at the start of `bytes`.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the header and its length in bytes, `None` if `bytes` does not
+    /// start with a valid header.
+    fn decode(bytes: &[u8]) -> Option<(Self, usize)> {
+        let mut reader = SnapshotReader { bytes };
+        if reader.take(SNAPSHOT_MAGIC.len())? != SNAPSHOT_MAGIC {
+            return None;
+        }
+
+        let (domain_separation, odd_leaf_policy) = match reader.take(1)?[0] {
+            SNAPSHOT_VERSION => (DomainSeparation::Tags, OddLeafPolicy::DuplicateLast),
+            SNAPSHOT_VERSION_LAYOUT => {
+                let domain_separation = match reader.take(1)?[0] {
+                    0 => DomainSeparation::Tags,
+                    1 => DomainSeparation::Prefixed,
+                    _ => return None,
+                };
+                let odd_leaf_policy = match reader.take(1)?[0] {
+                    0 => OddLeafPolicy::DuplicateLast,
+                    1 => OddLeafPolicy::PromoteOdd,
+                    2 => OddLeafPolicy::PadWithEmptyHash,
+                    _ => return None,
+                };
+                (domain_separation, odd_leaf_policy)
+            }
+            _ => return None,
+        };
+
+        let header = SnapshotHeader {
+            domain_separation,
+            odd_leaf_policy,
+            leaf_count: reader.read_u64()?,
+        };
+        Some((header, bytes.len() - reader.bytes.len()))
+    }
+}
+
+/// User data that can be restored from the bytes produced by `LeafEncode::serialize`.
+///
+/// Snapshots store the serialized form of every leaf, which is also the input of the leaf
+/// hash, so restoring a tree only needs the inverse of `serialize`.
+pub trait SnapshotData: Sized {
+    fn deserialize(bytes: &[u8]) -> Option<Self>;
+}
+
+/// Reads consecutive fields out of a snapshot buffer.
+struct SnapshotReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> SnapshotReader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.bytes.len() < len {
+            return None;
+        }
+
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Some(head)
+    }
+
+    fn read_u32(&mut self) -> Option<u32> {
+        self.take(4)
+            .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn read_u64(&mut self) -> Option<u64> {
+        self.take(8)
+            .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+}
+
+impl<T> MerkleTree<T>
+where
+    T: Clone + LeafEncode,
+{
+    /// Encodes the tree into a compact binary snapshot.
+    ///
+    /// The layout is a header (`MKTS`, format version, leaf count as `u64`), the serialized
+    /// user data of every leaf prefixed with its length as `u32`, followed by the 32 byte
+    /// hashes of every level from the leaves up to the root. All integers are little endian.
+    /// Trees built with a non default `DomainSeparation` or `OddLeafPolicy` are written with
+    /// format version 2, which stores both as one byte each right after the version. A node
+    /// promoted by `OddLeafPolicy::PromoteOdd` is stored again on every level it moves up.
+    ///
+    /// # Returns
+    ///
+    /// The snapshot as a `Vec<u8>`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let levels = self.levels();
+
+        let mut bytes = SnapshotHeader {
+            domain_separation: self.domain_separation,
+            odd_leaf_policy: self.odd_leaf_policy,
+            leaf_count: self.leaf_count as u64,
+        }
+        .encode();
+
+        if let Some(leaves) = levels.first() {
+            for leaf in *leaves {
+                let payload = leaf
+                    .user_data
+                    .as_ref()
+                    .map(|data| data.serialize())
+                    .unwrap_or_default();
+                bytes.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+                bytes.extend_from_slice(&payload);
+            }
+        }
+
+        for node in levels.iter().copied().flatten() {
+            bytes.extend_from_slice(&node.hash);
+        }
+
+        bytes
+    }
+}
+
+impl<T> MerkleTree<T>
+where
+    T: Clone + LeafEncode + SnapshotData,
+{
+    /// Restores a tree from a snapshot produced by `to_bytes`.
+    ///
+    /// The stored hashes are taken as they are, nothing is re-hashed, which is what makes
+    /// loading a large tree cheaper than building it again.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes`: The snapshot.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the tree, `MerkleTreeError::MalformedSnapshot` if the snapshot is
+    /// truncated, has trailing bytes or an unknown header, `MerkleTreeError::InvalidLeafData` if
+    /// the user data of a leaf cannot be deserialized.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MerkleTreeError> {
+        let (header, header_len) =
+            SnapshotHeader::decode(bytes).ok_or(MerkleTreeError::MalformedSnapshot)?;
+        let SnapshotHeader {
+            domain_separation,
+            odd_leaf_policy,
+            ..
+        } = header;
+        let leaf_count =
+            usize::try_from(header.leaf_count).map_err(|_| MerkleTreeError::MalformedSnapshot)?;
+        let mut reader = SnapshotReader {
+            bytes: &bytes[header_len..],
+        };
+
+        if leaf_count == 0 {
+            if !reader.bytes.is_empty() {
+                return Err(MerkleTreeError::MalformedSnapshot);
+            }
+
+            return Ok(MerkleTree::from_leaf_nodes(
+                Vec::new(),
+                domain_separation,
+                odd_leaf_policy,
+                |_, _| Vec::new(),
+            ));
+        }
+
+        let mut user_data = Vec::with_capacity(leaf_count.min(reader.bytes.len()));
+        for index in 0..leaf_count {
+            let payload = reader
+                .read_u32()
+                .and_then(|len| reader.take(len as usize))
+                .ok_or(MerkleTreeError::MalformedSnapshot)?;
+            user_data
+                .push(T::deserialize(payload).ok_or(MerkleTreeError::InvalidLeafData { index })?);
+        }
+
+        let mut read_hash = || {
+            reader
+                .take(HASH_LEN)
+                .map(|hash| Hash::try_from(hash).expect("HASH_LEN bytes"))
+                .ok_or(MerkleTreeError::MalformedSnapshot)
+        };
+
+        let nodes: Vec<MerkleNode<T>> = user_data
+            .into_iter()
+            .map(|data| Ok(MerkleNode::new_leaf(read_hash()?, Some(data))))
+            .collect::<Result<_, MerkleTreeError>>()?;
+
+        let branch_count = level_sizes(leaf_count).iter().sum::<usize>() - leaf_count;
+        let branch_hashes = (0..branch_count)
+            .map(|_| read_hash())
+            .collect::<Result<Vec<Hash>, MerkleTreeError>>()?;
+
+        if !reader.bytes.is_empty() {
+            return Err(MerkleTreeError::MalformedSnapshot);
+        }
+
+        // the hash stored for a promoted node is its own, `new_parent` copies the node
+        let mut branch_hashes = branch_hashes.into_iter();
+        Ok(MerkleTree::from_leaf_nodes(
+            nodes,
+            domain_separation,
+            odd_leaf_policy,
+            |level, links| {
+                (0..level.len().div_ceil(2))
+                    .map(|position| {
+                        let hash = branch_hashes.next().unwrap_or_default();
+                        MerkleNode::new_parent(level, links, position, |_, _| hash)
+                    })
+                    .collect()
+            },
+        ))
+    }
+}
+
+/// Where and why `verify_snapshot_file` rejected a snapshot.
+#[derive(Debug)]
+pub enum SnapshotVerifyError {
+    Io(io::Error),
+    /// The file does not start with a snapshot header.
+    InvalidHeader,
+    /// The file is shorter or longer than the leaves and hashes the header announces.
+    InvalidLength,
+    /// The stored hash of a leaf does not match its user data.
+    LeafHashMismatch {
+        index: usize,
+    },
+    /// The stored hash of a branch does not match its children, `level` counts from the leaves at 0.
+    BranchHashMismatch {
+        level: u32,
+        position: usize,
+    },
+    /// The snapshot is consistent but its root is not the expected one.
+    RootMismatch,
+}
+
+impl fmt::Display for SnapshotVerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotVerifyError::Io(err) => write!(f, "failed to read snapshot: {err}"),
+            SnapshotVerifyError::InvalidHeader => write!(f, "snapshot header is invalid"),
+            SnapshotVerifyError::InvalidLength => {
+                write!(f, "snapshot length does not match its header")
+            }
+            SnapshotVerifyError::LeafHashMismatch { index } => {
+                write!(f, "hash of leaf {index} does not match its user data")
+            }
+            SnapshotVerifyError::BranchHashMismatch { level, position } => write!(
+                f,
+                "hash of node {position} on level {level} does not match its children"
+            ),
+            SnapshotVerifyError::RootMismatch => {
+                write!(f, "snapshot root is not the expected root")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SnapshotVerifyError {}
+
+impl From<io::Error> for SnapshotVerifyError {
+    fn from(err: io::Error) -> Self {
+        SnapshotVerifyError::Io(err)
+    }
+}
+
+fn open_at(path: &Path, offset: u64) -> io::Result<BufReader<File>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    reader.seek(SeekFrom::Start(offset))?;
+    Ok(reader)
+}
+
+fn read_hash(reader: &mut impl Read) -> io::Result<[u8; HASH_LEN]> {
+    let mut hash = [0; HASH_LEN];
+    reader.read_exact(&mut hash)?;
+    Ok(hash)
+}
+
+/// Verifies a snapshot file written from `MerkleTree::to_bytes` without loading it.
+///
+/// Every stored hash is recomputed from the user data or the stored hashes below it, level
+/// by level. The file is read sequentially through a couple of buffered readers, so memory
+/// stays bounded by the largest leaf whatever the size of the snapshot. The leaf lengths are
+/// checked against the file length before any hashing.
+///
+/// # Arguments
+///
+/// * `path`: The snapshot file.
+/// * `tag_leaf`: The tag used for hashing leaf nodes.
+/// * `tag_branch`: The tag used for hashing branch nodes.
+/// * `expected_root`: The hex encoded root hash the snapshot must have.
+///
+/// # Returns
+///
+/// `Ok(())` if the snapshot is intact and has the expected root, otherwise a
+/// `SnapshotVerifyError` with the first corrupted leaf or node.
+pub fn verify_snapshot_file<P>(
+    path: P,
+    tag_leaf: &str,
+    tag_branch: &str,
+    expected_root: &str,
+) -> Result<(), SnapshotVerifyError>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let file_len = std::fs::metadata(path)?.len();
+    let mut payloads = open_at(path, 0)?;
+
+    let mut header = Vec::with_capacity(MAX_HEADER_LEN);
+    (&mut payloads)
+        .take(MAX_HEADER_LEN as u64)
+        .read_to_end(&mut header)?;
+    let (
+        SnapshotHeader {
+            domain_separation,
+            odd_leaf_policy,
+            leaf_count,
+        },
+        header_len,
+    ) = SnapshotHeader::decode(&header).ok_or(SnapshotVerifyError::InvalidHeader)?;
+    let header_len = header_len as u64;
+    payloads.seek(SeekFrom::Start(header_len))?;
+
+    if leaf_count == 0 {
+        return Err(if file_len == header_len {
+            SnapshotVerifyError::RootMismatch
+        } else {
+            SnapshotVerifyError::InvalidLength
+        });
+    }
+
+    // every leaf takes at least its length and its hash, so a header announcing more leaves
+    // than fit into the file is rejected before any size is derived from it
+    leaf_count
+        .checked_mul(4 + HASH_LEN as u64)
+        .and_then(|leaves_len| leaves_len.checked_add(header_len))
+        .filter(|&min_len| min_len <= file_len)
+        .ok_or(SnapshotVerifyError::InvalidLength)?;
+
+    // all offsets are computed in `u64`, a `usize` is too small for them on 32 bit targets
+    let sizes =
+        level_sizes(usize::try_from(leaf_count).map_err(|_| SnapshotVerifyError::InvalidLength)?);
+    let mut level_starts = Vec::with_capacity(sizes.len());
+    let mut offset = sizes
+        .iter()
+        .try_fold(0u64, |sum, &size| sum.checked_add(size as u64))
+        .and_then(|hash_count| hash_count.checked_mul(HASH_LEN as u64))
+        .and_then(|hashes_len| file_len.checked_sub(hashes_len))
+        .filter(|&start| start >= header_len + 4 * leaf_count)
+        .ok_or(SnapshotVerifyError::InvalidLength)?;
+    for &size in &sizes {
+        level_starts.push(offset);
+        offset = (size as u64)
+            .checked_mul(HASH_LEN as u64)
+            .and_then(|level_len| offset.checked_add(level_len))
+            .ok_or(SnapshotVerifyError::InvalidLength)?;
+    }
+
+    // check the leaf lengths first, so a truncated download is reported as such rather
+    // than as a mismatching leaf
+    let mut payload_end = header_len;
+    for _ in 0..sizes[0] {
+        let mut len = [0; 4];
+        payloads.read_exact(&mut len)?;
+        payload_end += 4 + u32::from_le_bytes(len) as u64;
+        if payload_end > level_starts[0] {
+            return Err(SnapshotVerifyError::InvalidLength);
+        }
+        payloads.seek_relative(i64::from(u32::from_le_bytes(len)))?;
+    }
+
+    if payload_end != level_starts[0] {
+        return Err(SnapshotVerifyError::InvalidLength);
+    }
+
+    let (tag_leaf, tag_branch) = (TagHasher::new(tag_leaf), TagHasher::new(tag_branch));
+    let mut payloads = open_at(path, header_len)?;
+    let mut stored = open_at(path, level_starts[0])?;
+    for index in 0..sizes[0] {
+        let mut len = [0; 4];
+        payloads.read_exact(&mut len)?;
+
+        let mut payload = vec![0; u32::from_le_bytes(len) as usize];
+        payloads.read_exact(&mut payload)?;
+        if domain_separation.leaf_hash_with(&tag_leaf, &payload) != read_hash(&mut stored)? {
+            return Err(SnapshotVerifyError::LeafHashMismatch { index });
+        }
+    }
+
+    for level in 1..sizes.len() {
+        let mut children = open_at(path, level_starts[level - 1])?;
+        let mut parents = open_at(path, level_starts[level])?;
+
+        for position in 0..sizes[level] {
+            let left = read_hash(&mut children)?;
+            let parent = if 2 * position + 1 < sizes[level - 1] {
+                domain_separation.branch_hash_with(&tag_branch, &left, &read_hash(&mut children)?)
+            } else {
+                odd_leaf_policy.lone_parent_hash(domain_separation, &tag_branch, &left)
+            };
+
+            if parent != read_hash(&mut parents)? {
+                return Err(SnapshotVerifyError::BranchHashMismatch {
+                    level: level as u32,
+                    position,
+                });
+            }
+        }
+    }
+
+    let top = read_hash(&mut open_at(path, *level_starts.last().unwrap())?)?;
+    let root = domain_separation.root_hash(sizes[0], &top);
+    if hex::encode(root) != expected_root {
+        return Err(SnapshotVerifyError::RootMismatch);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::generate_random_user_data;
+    use rstest::rstest;
+
+    const TAG_LEAF: &str = "ProofOfReserve_Leaf";
+    const TAG_BRANCH: &str = "ProofOfReserve_Branch";
+
+    #[rstest]
+    #[case(0)]
+    #[case(1)]
+    #[case(2)]
+    #[case(5)]
+    #[case(8)]
+    #[case(100)]
+    fn it_can_round_trip_snapshot(#[case] n: usize) {
+        let user_data = generate_random_user_data(n);
+        let tree = MerkleTree::build(TAG_LEAF, TAG_BRANCH, &user_data);
+
+        let bytes = tree.to_bytes();
+        let restored = MerkleTree::<crate::util::UserData>::from_bytes(&bytes).unwrap();
+
+        assert_eq!(restored.root(), tree.root());
+        assert_eq!(restored.to_bytes(), bytes);
+
+        for (index, data) in user_data.iter().enumerate() {
+            let proof = restored.proof(index).unwrap();
+            assert!(proof.verify(TAG_LEAF, TAG_BRANCH, data, &tree.root().unwrap()));
+        }
+    }
+
+    #[test]
+    fn it_can_reject_malformed_snapshot() {
+        let user_data = generate_random_user_data(5);
+        let bytes = MerkleTree::build(TAG_LEAF, TAG_BRANCH, &user_data).to_bytes();
+
+        let from_bytes = MerkleTree::<crate::util::UserData>::from_bytes;
+
+        let malformed = Some(MerkleTreeError::MalformedSnapshot);
+        assert_eq!(from_bytes(&bytes[..bytes.len() - 1]).err(), malformed);
+        assert_eq!(
+            from_bytes(&[bytes.as_slice(), &[0]].concat()).err(),
+            malformed
+        );
+        assert_eq!(
+            from_bytes(&[b"XXXX", &bytes[4..]].concat()).err(),
+            malformed
+        );
+        assert_eq!(from_bytes(&[]).err(), malformed);
+
+        let mut invalid_leaf = bytes.clone();
+        invalid_leaf[17] = b'x';
+        assert_eq!(
+            from_bytes(&invalid_leaf).err(),
+            Some(MerkleTreeError::InvalidLeafData { index: 0 })
+        );
+    }
+
+    #[test]
+    fn it_can_verify_prefixed_snapshot() {
+        let user_data = generate_random_user_data(5);
+        let tree = MerkleTree::build_with_domain_separation(
+            TAG_LEAF,
+            TAG_BRANCH,
+            &user_data,
+            DomainSeparation::Prefixed,
+        );
+        let bytes = tree.to_bytes();
+
+        let restored = MerkleTree::<crate::util::UserData>::from_bytes(&bytes).unwrap();
+        assert_eq!(restored.domain_separation(), DomainSeparation::Prefixed);
+        assert_eq!(restored.root(), tree.root());
+
+        let path = std::env::temp_dir().join(format!("prefixed-{}.bin", std::process::id()));
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(verify_snapshot_file(&path, TAG_LEAF, TAG_BRANCH, &tree.root().unwrap()).is_ok());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[rstest]
+    #[case(OddLeafPolicy::PromoteOdd)]
+    #[case(OddLeafPolicy::PadWithEmptyHash)]
+    fn it_can_verify_snapshot_of_odd_leaf_policy(#[case] odd_leaf_policy: OddLeafPolicy) {
+        let user_data = generate_random_user_data(11);
+        let tree = MerkleTree::build_with_odd_leaf_policy(
+            TAG_LEAF,
+            TAG_BRANCH,
+            &user_data,
+            odd_leaf_policy,
+        );
+        let root = tree.root().unwrap();
+        let mut bytes = tree.to_bytes();
+
+        let path = std::env::temp_dir().join(format!(
+            "odd-leaf-{odd_leaf_policy:?}-{}.bin",
+            std::process::id()
+        ));
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(verify_snapshot_file(&path, TAG_LEAF, TAG_BRANCH, &root).is_ok());
+
+        // the same hashes read as a tree that duplicates the last node
+        bytes[6] = 0;
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(matches!(
+            verify_snapshot_file(&path, TAG_LEAF, TAG_BRANCH, &root),
+            Err(SnapshotVerifyError::BranchHashMismatch { .. })
+        ));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn it_can_verify_snapshot_file() {
+        let user_data = generate_random_user_data(13);
+        let tree = MerkleTree::build(TAG_LEAF, TAG_BRANCH, &user_data);
+        let root = tree.root().unwrap();
+        let bytes = tree.to_bytes();
+
+        let path = std::env::temp_dir().join(format!("snapshot-{}.bin", std::process::id()));
+        let verify = |bytes: &[u8], root: &str| {
+            std::fs::write(&path, bytes).unwrap();
+            verify_snapshot_file(&path, TAG_LEAF, TAG_BRANCH, root)
+        };
+
+        assert!(verify(&bytes, &root).is_ok());
+        assert!(matches!(
+            verify(&bytes, &"0".repeat(64)),
+            Err(SnapshotVerifyError::RootMismatch)
+        ));
+
+        // the payload of leaf 2 starts after the header and two "n,n000" payloads
+        let mut changed_leaf = bytes.clone();
+        changed_leaf[13 + 2 * 10 + 4] = b'9';
+        assert!(matches!(
+            verify(&changed_leaf, &root),
+            Err(SnapshotVerifyError::LeafHashMismatch { index: 2 })
+        ));
+
+        // the hashes of the 7 nodes on level 1 sit right after the 13 leaf hashes
+        let hashes_start = bytes.len() - (13 + 7 + 4 + 2 + 1) * 32;
+        let mut changed_branch = bytes.clone();
+        changed_branch[hashes_start + (13 + 5) * 32] ^= 1;
+        assert!(matches!(
+            verify(&changed_branch, &root),
+            Err(SnapshotVerifyError::BranchHashMismatch {
+                level: 1,
+                position: 5
+            })
+        ));
+
+        assert!(matches!(
+            verify(&bytes[..bytes.len() - 1], &root),
+            Err(SnapshotVerifyError::InvalidLength)
+        ));
+        assert!(matches!(
+            verify(&bytes[1..], &root),
+            Err(SnapshotVerifyError::InvalidHeader)
+        ));
+
+        // a header alone that claims more leaves than any file can hold
+        let mut huge = bytes[..13].to_vec();
+        huge[5..13].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(matches!(
+            verify(&huge, &root),
+            Err(SnapshotVerifyError::InvalidLength)
+        ));
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
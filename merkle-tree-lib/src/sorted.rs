@@ -0,0 +1,334 @@
+use crate::{
+    level_sizes, tagged_hash, LeafEncode, LeafRef, MerkleProof, MerkleTree, MerkleTreeError,
+    NodeDirection,
+};
+
+/// A Merkle Tree whose leaves are sorted by a key, so the absence of a key can be proven.
+///
+/// Built with `MerkleTree::build_sorted_by_key`, with the default domain separation and odd
+/// leaf policy. Leaves with equal keys keep the order they were passed in. Lookups by key
+/// are a binary search over the sorted keys.
+pub struct SortedMerkleTree<T, K> {
+    tree: MerkleTree<T>,
+    keys: Vec<K>,
+}
+
+/// A leaf next to a missing key, together with its inclusion proof.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BoundingLeaf<T> {
+    pub data: T,
+    pub proof: MerkleProof,
+}
+
+/// A proof that a key is not in a `SortedMerkleTree`.
+///
+/// It holds the two adjacent leaves whose keys bracket the missing key. A key before the
+/// first leaf only has a `right` leaf, a key after the last leaf only has a `left` one.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NonInclusionProof<T> {
+    pub leaf_count: usize,
+    pub left: Option<BoundingLeaf<T>>,
+    pub right: Option<BoundingLeaf<T>>,
+}
+
+impl<T, K> SortedMerkleTree<T, K>
+where
+    T: Clone + LeafEncode,
+    K: Ord,
+{
+    /// Returns the underlying Merkle Tree.
+    pub fn tree(&self) -> &MerkleTree<T> {
+        &self.tree
+    }
+
+    /// Returns the position of a leaf with the given key.
+    pub fn index_of(&self, key: &K) -> Option<usize> {
+        self.keys.binary_search(key).ok()
+    }
+
+    /// Searches for a leaf with the given key.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the `LeafRef` if a leaf has the key, `None` otherwise.
+    pub fn search_with_path(&self, key: &K) -> Option<LeafRef<'_, T>> {
+        self.index_of(key)
+            .and_then(|index| self.tree.search_index_with_path(index))
+    }
+
+    /// Generates an inclusion proof for a leaf with the given key.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `MerkleProof`, `MerkleTreeError::KeyNotFound` if no leaf has the key.
+    pub fn proof_for_key(&self, key: &K) -> Result<MerkleProof, MerkleTreeError> {
+        self.tree
+            .proof(self.index_of(key).ok_or(MerkleTreeError::KeyNotFound)?)
+    }
+
+    /// Generates a proof that no leaf has the given key.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `NonInclusionProof`, `MerkleTreeError::KeyIncluded` if a leaf
+    /// has the key. The proof of an empty tree has no leaves and never verifies.
+    pub fn non_inclusion_proof(&self, key: &K) -> Result<NonInclusionProof<T>, MerkleTreeError> {
+        let position = match self.keys.binary_search(key) {
+            Ok(_) => return Err(MerkleTreeError::KeyIncluded),
+            Err(position) => position,
+        };
+
+        let bounding_leaf = |index: usize| {
+            self.tree
+                .search_index_with_path(index)
+                .map(|leaf| BoundingLeaf {
+                    data: leaf.data().clone(),
+                    proof: leaf.proof(),
+                })
+        };
+
+        Ok(NonInclusionProof {
+            leaf_count: self.tree.len(),
+            left: position.checked_sub(1).and_then(bounding_leaf),
+            right: bounding_leaf(position),
+        })
+    }
+}
+
+impl<T> BoundingLeaf<T>
+where
+    T: LeafEncode,
+{
+    /// Verifies that the leaf is in the tree with the given root at the position its proof
+    /// claims.
+    ///
+    /// `MerkleProof::verify` does not look at `leaf_index`, here the directions have to match
+    /// the position and the last node of an odd level has to be its own sibling, otherwise a
+    /// leaf could pretend to be the first or the last one.
+    fn verify(&self, leaf_count: usize, tag_leaf: &str, tag_branch: &str, root: &str) -> bool {
+        let sizes = level_sizes(leaf_count);
+        if self.proof.leaf_index >= leaf_count || self.proof.hashes.len() != sizes.len() - 1 {
+            return false;
+        }
+
+        let mut current = tagged_hash(tag_leaf, &self.data.serialize());
+        let mut position = self.proof.leaf_index;
+        for ((hash, direction), size) in self
+            .proof
+            .hashes
+            .iter()
+            .zip(&self.proof.directions)
+            .zip(sizes)
+        {
+            let Ok(sibling) = hex::decode(hash) else {
+                return false;
+            };
+
+            current = match (position & 1, direction) {
+                (0, NodeDirection::Right) if position + 1 < size || sibling == current => {
+                    tagged_hash(tag_branch, &[current, sibling].concat())
+                }
+                (1, NodeDirection::Left) => tagged_hash(tag_branch, &[sibling, current].concat()),
+                _ => return false,
+            };
+            position /= 2;
+        }
+
+        hex::encode(current) == root
+    }
+}
+
+impl<T> NonInclusionProof<T>
+where
+    T: LeafEncode,
+{
+    /// Verifies that no leaf of the tree with the given root has the given key.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_leaf`: The tag used for hashing leaf nodes.
+    /// * `tag_branch`: The tag used for hashing branch nodes.
+    /// * `key`: The key that is proven to be missing.
+    /// * `key_fn`: The function the tree was sorted with.
+    /// * `root`: The hex encoded root hash of the tree.
+    ///
+    /// # Returns
+    ///
+    /// `true` if both leaves are in the tree, next to each other, and their keys bracket
+    /// `key`, `false` otherwise.
+    pub fn verify<K, F>(
+        &self,
+        tag_leaf: &str,
+        tag_branch: &str,
+        key: &K,
+        key_fn: F,
+        root: &str,
+    ) -> bool
+    where
+        K: Ord,
+        F: Fn(&T) -> K,
+    {
+        let bracketed = match (&self.left, &self.right) {
+            (None, None) => false,
+            (Some(left), None) => {
+                left.proof.leaf_index.checked_add(1) == Some(self.leaf_count)
+                    && key_fn(&left.data) < *key
+            }
+            (None, Some(right)) => right.proof.leaf_index == 0 && *key < key_fn(&right.data),
+            (Some(left), Some(right)) => {
+                left.proof.leaf_index.checked_add(1) == Some(right.proof.leaf_index)
+                    && key_fn(&left.data) < *key
+                    && *key < key_fn(&right.data)
+            }
+        };
+
+        bracketed
+            && [&self.left, &self.right]
+                .into_iter()
+                .flatten()
+                .all(|leaf| leaf.verify(self.leaf_count, tag_leaf, tag_branch, root))
+    }
+}
+
+impl<T> MerkleTree<T>
+where
+    T: Clone + LeafEncode,
+{
+    /// Builds a Merkle Tree with the leaves sorted by a key.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_leaf`: The tag used for hashing leaf nodes.
+    /// * `tag_branch`: The tag used for hashing branch nodes.
+    /// * `input`: The user data stored in the leaves, in any order.
+    /// * `key_fn`: A function that extracts the sort key from the user data.
+    pub fn build_sorted_by_key<K, F>(
+        tag_leaf: &str,
+        tag_branch: &str,
+        input: &[T],
+        key_fn: F,
+    ) -> SortedMerkleTree<T, K>
+    where
+        K: Ord,
+        F: Fn(&T) -> K,
+    {
+        let mut sorted: Vec<(K, T)> = input
+            .iter()
+            .map(|data| (key_fn(data), data.clone()))
+            .collect();
+        sorted.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let (keys, input): (Vec<K>, Vec<T>) = sorted.into_iter().unzip();
+        SortedMerkleTree {
+            tree: MerkleTree::build(tag_leaf, tag_branch, &input),
+            keys,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{generate_random_user_data, UserData};
+    use rstest::rstest;
+
+    const TAG_LEAF: &str = "ProofOfReserve_Leaf";
+    const TAG_BRANCH: &str = "ProofOfReserve_Branch";
+
+    /// Users with the even ids from 2 to `2 * n`, in reverse order.
+    fn even_users(n: usize) -> Vec<UserData> {
+        let mut user_data: Vec<UserData> = generate_random_user_data(n)
+            .into_iter()
+            .map(|data| UserData {
+                id: data.id * 2,
+                ..data
+            })
+            .collect();
+        user_data.reverse();
+
+        user_data
+    }
+
+    #[rstest]
+    #[case(1)]
+    #[case(2)]
+    #[case(5)]
+    #[case(8)]
+    #[case(13)]
+    fn it_can_prove_every_missing_key(#[case] n: usize) {
+        let sorted =
+            MerkleTree::build_sorted_by_key(TAG_LEAF, TAG_BRANCH, &even_users(n), |data| data.id);
+        let root = sorted.tree().root().unwrap();
+
+        for id in (1..=2 * n as u32 + 1).step_by(2) {
+            let proof = sorted.non_inclusion_proof(&id).unwrap();
+            assert!(proof.verify(TAG_LEAF, TAG_BRANCH, &id, |data| data.id, &root));
+        }
+
+        for id in (2..=2 * n as u32).step_by(2) {
+            assert!(matches!(
+                sorted.non_inclusion_proof(&id),
+                Err(MerkleTreeError::KeyIncluded)
+            ));
+            let proof = sorted.proof_for_key(&id).unwrap();
+            let leaf = sorted.search_with_path(&id).unwrap();
+            assert_eq!(leaf.data().id, id);
+            assert!(proof.verify(TAG_LEAF, TAG_BRANCH, leaf.data(), &root));
+        }
+    }
+
+    #[test]
+    fn it_can_reject_a_forged_non_inclusion_proof() {
+        let sorted =
+            MerkleTree::build_sorted_by_key(TAG_LEAF, TAG_BRANCH, &even_users(5), |data| data.id);
+        let root = sorted.tree().root().unwrap();
+        let verify = |proof: &NonInclusionProof<UserData>, id: u32| {
+            proof.verify(TAG_LEAF, TAG_BRANCH, &id, |data| data.id, &root)
+        };
+
+        // the bracket of 5 does not prove 7 missing, whose bracket is 6 and 8
+        let proof = sorted.non_inclusion_proof(&5).unwrap();
+        assert!(verify(&proof, 5));
+        assert!(!verify(&proof, 7));
+
+        // leaves that are not next to each other leave a gap for the key
+        let mut gap = proof.clone();
+        gap.right = sorted.non_inclusion_proof(&7).unwrap().right;
+        assert!(!verify(&gap, 7));
+
+        // an included leaf pretending to be the last one
+        let mut last = sorted.non_inclusion_proof(&7).unwrap();
+        last.right = None;
+        last.left.as_mut().unwrap().proof.leaf_index = 4;
+        assert!(!verify(&last, 11));
+
+        // a proof claiming fewer leaves than the tree has
+        let mut shorter = sorted.non_inclusion_proof(&11).unwrap();
+        shorter.leaf_count = 6;
+        assert!(!verify(&shorter, 11));
+
+        let empty =
+            MerkleTree::<UserData>::build_sorted_by_key(TAG_LEAF, TAG_BRANCH, &[], |data| data.id);
+        assert!(!verify(&empty.non_inclusion_proof(&1).unwrap(), 1));
+    }
+
+    #[test]
+    fn it_can_reject_a_non_inclusion_proof_with_an_overflowing_index() {
+        let sorted =
+            MerkleTree::build_sorted_by_key(TAG_LEAF, TAG_BRANCH, &even_users(5), |data| data.id);
+        let root = sorted.tree().root().unwrap();
+        let verify = |proof: &NonInclusionProof<UserData>, id: u32| {
+            proof.verify(TAG_LEAF, TAG_BRANCH, &id, |data| data.id, &root)
+        };
+
+        let mut between = sorted.non_inclusion_proof(&5).unwrap();
+        between.left.as_mut().unwrap().proof.leaf_index = usize::MAX;
+        assert!(!verify(&between, 5));
+
+        let mut last = sorted.non_inclusion_proof(&11).unwrap();
+        last.left.as_mut().unwrap().proof.leaf_index = usize::MAX;
+        assert!(!verify(&last, 11));
+    }
+}
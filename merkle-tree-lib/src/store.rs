@@ -0,0 +1,522 @@
+use std::io;
+
+#[cfg(feature = "mmap")]
+use std::fs::{File, OpenOptions};
+#[cfg(feature = "mmap")]
+use std::io::{BufWriter, Write};
+#[cfg(feature = "mmap")]
+use std::path::Path;
+
+use crate::{
+    level_sizes, odd_leaf, to_hash, DomainSeparation, Hash, LeafEncode, MerkleProof,
+    MerkleTreeError, NodeDirection, OddLeafPolicy, TagHasher,
+};
+
+/// Where a `StoredMerkleTree` keeps the hashes of its nodes.
+///
+/// Nodes are pushed level by level from the leaves up, the same order `MerkleTree` stores
+/// them in, and read back by their position in that order.
+pub trait NodeStore {
+    /// Appends the hash of the next node.
+    fn push(&mut self, hash: &Hash) -> io::Result<()>;
+
+    /// Makes every node pushed so far readable with `get`.
+    fn flush(&mut self) -> io::Result<()>;
+
+    /// Returns the hash of the node at the given position.
+    ///
+    /// # Panics
+    ///
+    /// If no node was pushed and flushed at that position.
+    fn get(&self, id: usize) -> Hash;
+
+    /// Returns the number of nodes readable with `get`.
+    fn len(&self) -> usize;
+
+    /// Returns `true` if no node is readable.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A `NodeStore` that keeps the hashes in memory.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryNodeStore {
+    hashes: Vec<Hash>,
+}
+
+impl MemoryNodeStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl NodeStore for MemoryNodeStore {
+    fn push(&mut self, hash: &Hash) -> io::Result<()> {
+        self.hashes.push(*hash);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn get(&self, id: usize) -> Hash {
+        self.hashes[id]
+    }
+
+    fn len(&self) -> usize {
+        self.hashes.len()
+    }
+}
+
+/// A `NodeStore` that writes the hashes to a file, 32 bytes per node, and reads them back
+/// through a memory map.
+///
+/// Only the pages holding the nodes that are read get loaded, a proof touches one or two
+/// per level, so trees far larger than RAM can be queried. The file must not be changed by
+/// anything else while the store has it open.
+#[cfg(feature = "mmap")]
+pub struct FileNodeStore {
+    writer: BufWriter<File>,
+    map: Option<memmap2::Mmap>,
+}
+
+#[cfg(feature = "mmap")]
+impl FileNodeStore {
+    /// Creates an empty store, truncating the file at `path`.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+
+        Ok(FileNodeStore {
+            writer: BufWriter::new(file),
+            map: None,
+        })
+    }
+
+    /// Opens the nodes written to `path` by an earlier store, to query them without
+    /// building the tree again.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the store, an `io::Error` of kind `InvalidData` if the file does
+    /// not hold whole 32 byte hashes.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).append(true).open(path)?;
+        if file.metadata()?.len() % 32 != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} does not hold whole node hashes", path.display()),
+            ));
+        }
+
+        let mut store = FileNodeStore {
+            writer: BufWriter::new(file),
+            map: None,
+        };
+        store.flush()?;
+        Ok(store)
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl NodeStore for FileNodeStore {
+    fn push(&mut self, hash: &Hash) -> io::Result<()> {
+        self.writer.write_all(hash)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()?;
+        let file = self.writer.get_ref();
+        if file.metadata()?.len() > 0 {
+            // SAFETY: the file is only written through `writer`, which is flushed, and the
+            // type documents that nothing else may change it while the store is open.
+            self.map = Some(unsafe { memmap2::Mmap::map(file)? });
+        }
+
+        Ok(())
+    }
+
+    fn get(&self, id: usize) -> Hash {
+        let bytes = self.map.as_deref().unwrap_or_default();
+        bytes[id * 32..(id + 1) * 32].try_into().unwrap()
+    }
+
+    fn len(&self) -> usize {
+        self.map.as_ref().map_or(0, |map| map.len() / 32)
+    }
+}
+
+/// A Merkle Tree that only keeps the hashes of its nodes, in a `NodeStore`.
+///
+/// Building it holds a couple of hashes in memory at a time whatever the number of leaves,
+/// and it has the same roots and proofs as a `MerkleTree` built with the same options. The
+/// user data is not stored, it stays wherever the caller keeps it, e.g. the dataset file,
+/// and goes to the verifier together with the proof.
+#[derive(Debug)]
+pub struct StoredMerkleTree<S> {
+    store: S,
+    leaf_count: usize,
+    domain_separation: DomainSeparation,
+    odd_leaf_policy: OddLeafPolicy,
+}
+
+impl<S> StoredMerkleTree<S>
+where
+    S: NodeStore,
+{
+    /// Hashes the leaves into the empty store and builds the levels above them.
+    ///
+    /// Every level is read back from the store to build the next one.
+    pub(crate) fn build<T, I>(
+        mut store: S,
+        tag_leaf: &TagHasher,
+        tag_branch: &TagHasher,
+        leaves: I,
+        domain_separation: DomainSeparation,
+        odd_leaf_policy: OddLeafPolicy,
+    ) -> io::Result<Self>
+    where
+        T: LeafEncode,
+        I: IntoIterator<Item = T>,
+    {
+        if !store.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "trees are built into an empty store",
+            ));
+        }
+
+        let mut leaf_count = 0;
+        for data in leaves {
+            store.push(&to_hash(
+                domain_separation.leaf_hash_with(tag_leaf, &data.serialize()),
+            ))?;
+            leaf_count += 1;
+        }
+        store.flush()?;
+
+        let sizes = level_sizes(leaf_count);
+        let mut start = 0;
+        for &size in &sizes[..sizes.len() - 1] {
+            for position in (0..size).step_by(2) {
+                let left = store.get(start + position);
+                let parent = if position + 1 < size {
+                    let right = store.get(start + position + 1);
+                    domain_separation.branch_hash_with(tag_branch, &left, &right)
+                } else {
+                    odd_leaf_policy.lone_parent_hash(domain_separation, tag_branch, &left)
+                };
+                store.push(&to_hash(parent))?;
+            }
+            store.flush()?;
+            start += size;
+        }
+
+        Ok(StoredMerkleTree {
+            store,
+            leaf_count,
+            domain_separation,
+            odd_leaf_policy,
+        })
+    }
+
+    /// Opens a tree whose nodes an earlier build left in the store.
+    ///
+    /// # Arguments
+    ///
+    /// * `store`: The store holding the nodes.
+    /// * `leaf_count`: The number of leaves the tree was built from.
+    /// * `domain_separation`: How the tree was built to separate its hashes.
+    /// * `odd_leaf_policy`: How the tree was built to pair the last node of odd levels.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the tree, `MerkleTreeError::NodeCountMismatch` if the store does
+    /// not hold the number of nodes a tree of `leaf_count` leaves has.
+    pub fn open(
+        store: S,
+        leaf_count: usize,
+        domain_separation: DomainSeparation,
+        odd_leaf_policy: OddLeafPolicy,
+    ) -> Result<Self, MerkleTreeError> {
+        // a node count beyond `usize::MAX` saturates, no store holds that many nodes
+        let expected = level_sizes(leaf_count)
+            .iter()
+            .fold(0usize, |sum, &size| sum.saturating_add(size));
+        if store.len() != expected {
+            return Err(MerkleTreeError::NodeCountMismatch {
+                expected,
+                stored: store.len(),
+            });
+        }
+
+        Ok(StoredMerkleTree {
+            store,
+            leaf_count,
+            domain_separation,
+            odd_leaf_policy,
+        })
+    }
+
+    /// Returns the number of leaves.
+    pub fn len(&self) -> usize {
+        self.leaf_count
+    }
+
+    /// Returns `true` if the tree has no leaves.
+    pub fn is_empty(&self) -> bool {
+        self.leaf_count == 0
+    }
+
+    /// Returns how the hashes of the tree are separated from each other.
+    pub fn domain_separation(&self) -> DomainSeparation {
+        self.domain_separation
+    }
+
+    /// Returns how the tree pairs the last node of odd levels.
+    pub fn odd_leaf_policy(&self) -> OddLeafPolicy {
+        self.odd_leaf_policy
+    }
+
+    /// Returns the hex encoded root hash, `None` if the tree has no leaves.
+    pub fn root(&self) -> Option<String> {
+        self.root_bytes().map(hex::encode)
+    }
+
+    /// Returns the root hash like `root`, as bytes instead of hex.
+    pub fn root_bytes(&self) -> Option<Hash> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let top = self.store.get(self.store.len() - 1);
+        Some(to_hash(
+            self.domain_separation.root_hash(self.leaf_count, &top),
+        ))
+    }
+
+    /// Generates the inclusion proof of a leaf, reading one node per level from the store.
+    ///
+    /// # Arguments
+    ///
+    /// * `index`: The position of the leaf, counted from the left starting at 0.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `MerkleProof`, the same `MerkleTree::proof` returns for the
+    /// leaf, `MerkleTreeError::LeafIndexOutOfRange` if there is no leaf at `index`.
+    pub fn proof(&self, index: usize) -> Result<MerkleProof, MerkleTreeError> {
+        if index >= self.leaf_count {
+            return Err(MerkleTreeError::LeafIndexOutOfRange {
+                index,
+                leaf_count: self.leaf_count,
+            });
+        }
+
+        let sizes = level_sizes(self.leaf_count);
+        let mut proof = MerkleProof::new(index);
+        let mut start = 0;
+        for (level, &size) in sizes[..sizes.len() - 1].iter().enumerate() {
+            let (level_start, position) = (start, index >> level);
+            start += size;
+
+            let (sibling, direction) = if position & 1 == 1 {
+                (
+                    self.store.get(level_start + position - 1),
+                    NodeDirection::Left,
+                )
+            } else if position + 1 < size {
+                (
+                    self.store.get(level_start + position + 1),
+                    NodeDirection::Right,
+                )
+            } else {
+                match self.odd_leaf_policy {
+                    OddLeafPolicy::DuplicateLast => {
+                        (self.store.get(level_start + position), NodeDirection::Right)
+                    }
+                    OddLeafPolicy::PromoteOdd => continue,
+                    OddLeafPolicy::PadWithEmptyHash => (odd_leaf::EMPTY_HASH, NodeDirection::Right),
+                }
+            };
+
+            proof.add_sibling(hex::encode(sibling), direction);
+        }
+
+        Ok(proof)
+    }
+
+    /// Returns the store, e.g. to reopen it later with `open`.
+    pub fn into_store(self) -> S {
+        self.store
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{generate_random_user_data, UserData};
+    use crate::MerkleTree;
+    use rstest::rstest;
+    use std::cell::Cell;
+
+    const TAG_LEAF: &str = "ProofOfReserve_Leaf";
+    const TAG_BRANCH: &str = "ProofOfReserve_Branch";
+
+    /// Counts the reads of the wrapped store.
+    #[derive(Default)]
+    struct CountingStore {
+        inner: MemoryNodeStore,
+        reads: Cell<usize>,
+    }
+
+    impl NodeStore for CountingStore {
+        fn push(&mut self, hash: &Hash) -> io::Result<()> {
+            self.inner.push(hash)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.inner.flush()
+        }
+
+        fn get(&self, id: usize) -> Hash {
+            self.reads.set(self.reads.get() + 1);
+            self.inner.get(id)
+        }
+
+        fn len(&self) -> usize {
+            self.inner.len()
+        }
+    }
+
+    fn assert_same_tree<S: NodeStore>(stored: &StoredMerkleTree<S>, tree: &MerkleTree<UserData>) {
+        assert_eq!(stored.len(), tree.len());
+        assert_eq!(stored.root(), tree.root());
+        assert_eq!(stored.root_bytes(), tree.root_bytes());
+        for index in 0..tree.len() {
+            assert_eq!(stored.proof(index), tree.proof(index));
+        }
+        assert_eq!(stored.proof(tree.len()), tree.proof(tree.len()));
+    }
+
+    #[rstest]
+    #[case(DomainSeparation::Tags, OddLeafPolicy::DuplicateLast)]
+    #[case(DomainSeparation::Tags, OddLeafPolicy::PromoteOdd)]
+    #[case(DomainSeparation::Prefixed, OddLeafPolicy::PadWithEmptyHash)]
+    fn it_can_build_the_same_tree_into_a_store(
+        #[case] domain_separation: DomainSeparation,
+        #[case] odd_leaf_policy: OddLeafPolicy,
+    ) {
+        for n in [0, 1, 2, 5, 11, 16] {
+            let user_data = generate_random_user_data(n);
+            let builder = MerkleTree::builder(TAG_LEAF, TAG_BRANCH)
+                .domain_separation(domain_separation)
+                .odd_leaf_policy(odd_leaf_policy);
+
+            let stored = builder
+                .build_into_store(user_data.iter().cloned(), MemoryNodeStore::new())
+                .unwrap();
+
+            assert_same_tree(&stored, &builder.build(&user_data));
+            assert_eq!(stored.domain_separation(), domain_separation);
+            assert_eq!(stored.odd_leaf_policy(), odd_leaf_policy);
+        }
+    }
+
+    #[test]
+    fn it_can_prove_with_a_read_per_level() {
+        let stored = MerkleTree::builder(TAG_LEAF, TAG_BRANCH)
+            .build_into_store(generate_random_user_data(4099), CountingStore::default())
+            .unwrap();
+
+        stored.store.reads.set(0);
+        let proof = stored.proof(4098).unwrap();
+
+        assert_eq!(proof.hashes.len(), 13);
+        assert_eq!(stored.store.reads.get(), 13);
+    }
+
+    #[test]
+    fn it_can_reopen_a_store() {
+        let user_data = generate_random_user_data(7);
+        let builder = MerkleTree::builder(TAG_LEAF, TAG_BRANCH);
+        let store = builder
+            .build_into_store(user_data.iter().cloned(), MemoryNodeStore::new())
+            .unwrap()
+            .into_store();
+
+        assert_eq!(
+            StoredMerkleTree::open(
+                store.clone(),
+                8,
+                DomainSeparation::Tags,
+                OddLeafPolicy::DuplicateLast
+            )
+            .err(),
+            Some(MerkleTreeError::NodeCountMismatch {
+                expected: 15,
+                stored: 15 - 1
+            })
+        );
+        assert_eq!(
+            StoredMerkleTree::open(
+                store.clone(),
+                usize::MAX,
+                DomainSeparation::Tags,
+                OddLeafPolicy::DuplicateLast
+            )
+            .err(),
+            Some(MerkleTreeError::NodeCountMismatch {
+                expected: usize::MAX,
+                stored: 15 - 1
+            })
+        );
+        let reopened = StoredMerkleTree::open(
+            store.clone(),
+            7,
+            DomainSeparation::Tags,
+            OddLeafPolicy::DuplicateLast,
+        )
+        .unwrap();
+        assert_same_tree(&reopened, &builder.build(&user_data));
+
+        let err = builder.build_into_store(user_data, store).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn it_can_build_into_a_file() {
+        let path = std::env::temp_dir().join(format!("nodes-{}.bin", std::process::id()));
+        let user_data = generate_random_user_data(1000);
+        let builder = MerkleTree::builder(TAG_LEAF, TAG_BRANCH);
+        let tree = builder.build(&user_data);
+
+        let stored = builder
+            .build_into_store(user_data, FileNodeStore::create(&path).unwrap())
+            .unwrap();
+        assert_same_tree(&stored, &tree);
+        drop(stored);
+
+        let reopened = StoredMerkleTree::open(
+            FileNodeStore::open(&path).unwrap(),
+            1000,
+            DomainSeparation::Tags,
+            OddLeafPolicy::DuplicateLast,
+        )
+        .unwrap();
+        assert_same_tree(&reopened, &tree);
+
+        std::fs::write(&path, [0; 33]).unwrap();
+        let err = FileNodeStore::open(&path).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,361 @@
+use crate::{tagged_hash, LeafEncode, MerkleTreeError, NodeDirection};
+
+/// User data that contributes a value to the sums committed by a `MerkleSumTree`.
+pub trait SumTreeData {
+    fn sum_value(&self) -> u64;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct SumNode {
+    hash: Vec<u8>,
+    sum: u64,
+}
+
+impl SumNode {
+    /// The node used to pair up the last node of a level with an odd number of nodes.
+    ///
+    /// Duplicating the last node, like `MerkleTree` does, would count its balance twice.
+    fn empty() -> Self {
+        SumNode {
+            hash: vec![0; 32],
+            sum: 0,
+        }
+    }
+
+    /// Creates a branch committing to the hashes and the sums of both children.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the branch node, `None` if the sum overflows.
+    fn new_branch(left: &SumNode, right: &SumNode, tag: &str) -> Option<Self> {
+        let sum = left.sum.checked_add(right.sum)?;
+
+        Some(SumNode {
+            hash: branch_hash(tag, &left.hash, left.sum, &right.hash, right.sum),
+            sum,
+        })
+    }
+}
+
+fn leaf_hash<T>(tag: &str, data: &T) -> Vec<u8>
+where
+    T: LeafEncode + SumTreeData,
+{
+    let combined = [data.serialize(), data.sum_value().to_le_bytes().to_vec()].concat();
+    tagged_hash(tag, &combined)
+}
+
+fn branch_hash(tag: &str, left: &[u8], left_sum: u64, right: &[u8], right_sum: u64) -> Vec<u8> {
+    let combined = [
+        left,
+        &left_sum.to_le_bytes(),
+        right,
+        &right_sum.to_le_bytes(),
+    ]
+    .concat();
+    tagged_hash(tag, &combined)
+}
+
+/// A Merkle Sum Tree, where every node commits to the sum of the values below it as well
+/// as to the hashes of its children.
+///
+/// For proof of liabilities this means a proof does not only show that a balance is
+/// included, but also that it is counted in the total committed by the root.
+pub struct MerkleSumTree<T> {
+    levels: Vec<Vec<SumNode>>,
+    user_data: Vec<T>,
+}
+
+impl<T> MerkleSumTree<T>
+where
+    T: Clone + LeafEncode + SumTreeData,
+{
+    /// Builds a Merkle Sum Tree from the given user data.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_leaf`: The tag used for hashing leaf nodes.
+    /// * `tag_branch`: The tag used for hashing branch nodes.
+    /// * `input`: The user data stored in the leaves.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the tree, `MerkleTreeError::SumOverflow` if the total of all values
+    /// overflows a `u64`.
+    pub fn build(tag_leaf: &str, tag_branch: &str, input: &[T]) -> Result<Self, MerkleTreeError> {
+        let mut levels = vec![input
+            .iter()
+            .map(|data| SumNode {
+                hash: leaf_hash(tag_leaf, data),
+                sum: data.sum_value(),
+            })
+            .collect::<Vec<_>>()];
+
+        while levels.last().unwrap().len() > 1 {
+            let next = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => SumNode::new_branch(left, right, tag_branch),
+                    [left] => SumNode::new_branch(left, &SumNode::empty(), tag_branch),
+                    _ => unreachable!(),
+                })
+                .collect::<Option<Vec<_>>>()
+                .ok_or(MerkleTreeError::SumOverflow)?;
+            levels.push(next);
+        }
+
+        Ok(MerkleSumTree {
+            levels,
+            user_data: input.to_vec(),
+        })
+    }
+
+    fn root_node(&self) -> Option<&SumNode> {
+        self.levels.last().and_then(|level| level.first())
+    }
+
+    /// Returns the hash of the root node of the Merkle Sum Tree.
+    pub fn root(&self) -> Option<String> {
+        self.root_node().map(|node| hex::encode(&node.hash))
+    }
+
+    /// Returns the sum of all values committed by the root node.
+    pub fn root_sum(&self) -> Option<u64> {
+        self.root_node().map(|node| node.sum)
+    }
+
+    /// Returns the user data of the leaf at the given position.
+    pub fn user_data(&self, index: usize) -> Option<&T> {
+        self.user_data.get(index)
+    }
+
+    /// Generates an inclusion proof for the leaf at the given position.
+    ///
+    /// # Arguments
+    ///
+    /// * `index`: The position of the leaf, counted from the left starting at 0.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `MerkleSumProof`, `MerkleTreeError::LeafIndexOutOfRange` if
+    /// there is no leaf at `index`.
+    pub fn proof(&self, index: usize) -> Result<MerkleSumProof, MerkleTreeError> {
+        if index >= self.user_data.len() {
+            return Err(MerkleTreeError::LeafIndexOutOfRange {
+                index,
+                leaf_count: self.user_data.len(),
+            });
+        }
+
+        let mut proof = MerkleSumProof {
+            leaf_index: index,
+            hashes: Vec::new(),
+            sums: Vec::new(),
+            directions: Vec::new(),
+        };
+
+        let mut position = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            let (sibling, direction) = if position & 1 == 0 {
+                (level.get(position + 1), NodeDirection::Right)
+            } else {
+                (level.get(position - 1), NodeDirection::Left)
+            };
+            let sibling = sibling.cloned().unwrap_or_else(SumNode::empty);
+
+            proof.hashes.push(hex::encode(&sibling.hash));
+            proof.sums.push(sibling.sum);
+            proof.directions.push(direction);
+            position /= 2;
+        }
+
+        Ok(proof)
+    }
+}
+
+/// An inclusion proof for a leaf of a `MerkleSumTree`.
+///
+/// Besides the sibling hashes it carries the sibling sums, ordered from the leaf upwards,
+/// so the verifier can recompute both the root hash and the root total.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MerkleSumProof {
+    pub leaf_index: usize,
+    pub hashes: Vec<String>,
+    pub sums: Vec<u64>,
+    pub directions: Vec<NodeDirection>,
+}
+
+impl MerkleSumProof {
+    /// Recomputes the root hash and the root sum from the user data and the proof.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_leaf`: The tag used for hashing leaf nodes.
+    /// * `tag_branch`: The tag used for hashing branch nodes.
+    /// * `data`: The user data the proof was generated for.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the hex encoded root hash and the root sum, `None` if the proof
+    /// is malformed or the sums overflow.
+    pub fn compute_root<T>(
+        &self,
+        tag_leaf: &str,
+        tag_branch: &str,
+        data: &T,
+    ) -> Option<(String, u64)>
+    where
+        T: LeafEncode + SumTreeData,
+    {
+        if self.hashes.len() != self.sums.len() || self.hashes.len() != self.directions.len() {
+            return None;
+        }
+
+        let (hash, sum) = self
+            .hashes
+            .iter()
+            .zip(self.sums.iter())
+            .zip(self.directions.iter())
+            .try_fold(
+                (leaf_hash(tag_leaf, data), data.sum_value()),
+                |(hash, sum), ((sibling_hash, &sibling_sum), direction)| {
+                    let sibling_hash = hex::decode(sibling_hash).ok()?;
+                    let total = sum.checked_add(sibling_sum)?;
+                    let parent = match direction {
+                        NodeDirection::Left => {
+                            branch_hash(tag_branch, &sibling_hash, sibling_sum, &hash, sum)
+                        }
+                        _ => branch_hash(tag_branch, &hash, sum, &sibling_hash, sibling_sum),
+                    };
+
+                    Some((parent, total))
+                },
+            )?;
+
+        Some((hex::encode(hash), sum))
+    }
+
+    /// Verifies that the user data is included in the tree and counted in its total.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_leaf`: The tag used for hashing leaf nodes.
+    /// * `tag_branch`: The tag used for hashing branch nodes.
+    /// * `data`: The user data the proof was generated for.
+    /// * `root`: The hex encoded root hash of the tree.
+    /// * `root_sum`: The total committed by the root of the tree.
+    ///
+    /// # Returns
+    ///
+    /// `true` if both the root hash and the root sum are reproduced, `false` otherwise.
+    pub fn verify<T>(
+        &self,
+        tag_leaf: &str,
+        tag_branch: &str,
+        data: &T,
+        root: &str,
+        root_sum: u64,
+    ) -> bool
+    where
+        T: LeafEncode + SumTreeData,
+    {
+        self.compute_root(tag_leaf, tag_branch, data)
+            .is_some_and(|(hash, sum)| hash == root && sum == root_sum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{generate_random_user_data, UserData};
+    use rstest::rstest;
+
+    const TAG_LEAF: &str = "ProofOfLiabilities_Leaf";
+    const TAG_BRANCH: &str = "ProofOfLiabilities_Branch";
+
+    #[rstest]
+    #[case(1)]
+    #[case(2)]
+    #[case(5)]
+    #[case(8)]
+    #[case(13)]
+    fn it_can_verify_sum_proof_for_every_leaf(#[case] n: usize) {
+        let user_data = generate_random_user_data(n);
+        let tree = MerkleSumTree::build(TAG_LEAF, TAG_BRANCH, &user_data).unwrap();
+        let root = tree.root().unwrap();
+        let root_sum = tree.root_sum().unwrap();
+
+        assert_eq!(
+            root_sum,
+            user_data
+                .iter()
+                .map(|data| data.balance as u64)
+                .sum::<u64>()
+        );
+
+        for (index, data) in user_data.iter().enumerate() {
+            let proof = tree.proof(index).unwrap();
+            assert!(proof.verify(TAG_LEAF, TAG_BRANCH, data, &root, root_sum));
+        }
+
+        assert!(matches!(
+            tree.proof(n),
+            Err(MerkleTreeError::LeafIndexOutOfRange { .. })
+        ));
+    }
+
+    #[test]
+    fn it_can_reject_sum_proof_with_tampered_sums() {
+        let user_data = generate_random_user_data(5);
+        let tree = MerkleSumTree::build(TAG_LEAF, TAG_BRANCH, &user_data).unwrap();
+        let root = tree.root().unwrap();
+        let root_sum = tree.root_sum().unwrap();
+
+        let mut proof = tree.proof(1).unwrap();
+        assert!(!proof.verify(TAG_LEAF, TAG_BRANCH, &user_data[1], &root, root_sum - 1));
+
+        proof.sums[0] -= 1;
+        assert!(!proof.verify(TAG_LEAF, TAG_BRANCH, &user_data[1], &root, root_sum - 1));
+
+        let understated = UserData { id: 2, balance: 1 };
+        let proof = tree.proof(1).unwrap();
+        assert!(!proof.verify(TAG_LEAF, TAG_BRANCH, &understated, &root, root_sum));
+    }
+
+    #[test]
+    fn it_can_build_a_sum_tree_with_empty_input() {
+        let input: Vec<UserData> = vec![];
+        let tree = MerkleSumTree::build(TAG_LEAF, TAG_BRANCH, &input).unwrap();
+
+        assert!(tree.root().is_none());
+        assert!(tree.root_sum().is_none());
+    }
+
+    #[derive(Clone)]
+    struct Liability(u64);
+
+    impl LeafEncode for Liability {
+        fn serialize(&self) -> Vec<u8> {
+            self.0.to_le_bytes().to_vec()
+        }
+    }
+
+    impl SumTreeData for Liability {
+        fn sum_value(&self) -> u64 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn it_can_reject_overflowing_sums() {
+        let input = vec![Liability(u64::MAX), Liability(1)];
+
+        assert!(matches!(
+            MerkleSumTree::build(TAG_LEAF, TAG_BRANCH, &input),
+            Err(MerkleTreeError::SumOverflow)
+        ));
+    }
+}
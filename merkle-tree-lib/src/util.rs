@@ -1,21 +1,45 @@
-use crate::MerkleTreeData;
+use std::io::{self, BufRead};
+use std::ops::RangeInclusive;
+
+use crate::{LeafEncode, NodeLabel, SnapshotData, SumTreeData};
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct UserData {
     pub id: u32,
     pub balance: u32,
 }
 
-impl MerkleTreeData for UserData {
+impl LeafEncode for UserData {
     fn serialize(&self) -> Vec<u8> {
         format!("{},{}", self.id, self.balance).as_bytes().to_vec()
     }
+}
 
+impl NodeLabel for UserData {
     fn mermaid_node_label(&self) -> String {
         format!("<br>User ID: {}<br>Balance: {}", self.id, self.balance)
     }
 }
 
+impl SumTreeData for UserData {
+    fn sum_value(&self) -> u64 {
+        self.balance as u64
+    }
+}
+
+impl SnapshotData for UserData {
+    fn deserialize(bytes: &[u8]) -> Option<Self> {
+        let (id, balance) = std::str::from_utf8(bytes).ok()?.split_once(',')?;
+
+        Some(UserData {
+            id: id.parse().ok()?,
+            balance: balance.parse().ok()?,
+        })
+    }
+}
+
 pub fn generate_random_user_data(n: usize) -> Vec<UserData> {
     vec![0; n]
         .iter()
@@ -29,3 +53,272 @@ pub fn generate_random_user_data(n: usize) -> Vec<UserData> {
         })
         .collect()
 }
+
+/// How `generate_random_user_data_with` draws a dataset.
+#[derive(Debug, Clone)]
+pub struct DatasetOptions {
+    /// The seed of the generator, the same seed always gives the same dataset.
+    pub seed: u64,
+    /// The range the balances are drawn from uniformly.
+    pub balances: RangeInclusive<u32>,
+    /// The probability that a user reuses the id of an earlier one, between 0 and 1.
+    pub duplicate_ids: f64,
+    /// Shuffles the users instead of keeping them sorted by id.
+    pub shuffle_ids: bool,
+}
+
+impl Default for DatasetOptions {
+    fn default() -> Self {
+        DatasetOptions {
+            seed: 0,
+            balances: 0..=1_000_000,
+            duplicate_ids: 0.0,
+            shuffle_ids: false,
+        }
+    }
+}
+
+/// The SplitMix64 generator, small and good enough to draw reproducible datasets.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Draws a number below `bound`, which must not be 0.
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+
+    /// Returns `true` with the given probability.
+    fn chance(&mut self, probability: f64) -> bool {
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < probability
+    }
+}
+
+/// Generates `n` users with ids from 1 to `n` and random balances.
+///
+/// # Arguments
+///
+/// * `n`: The number of users.
+/// * `options`: The seed, the balance range and how the ids are duplicated and ordered. The
+///   balance range must not be empty, a reversed range like `200..=100` panics.
+///
+/// # Returns
+///
+/// The users, the same ones for the same `n` and `options`.
+pub fn generate_random_user_data_with(n: usize, options: &DatasetOptions) -> Vec<UserData> {
+    let mut rng = SplitMix64(options.seed);
+    let (low, high) = (*options.balances.start(), *options.balances.end());
+    assert!(
+        low <= high,
+        "the balance range {low}..={high} of the dataset options is empty"
+    );
+    let balance_span = u64::from(high - low) + 1;
+
+    let mut user_data: Vec<UserData> = (0..n)
+        .map(|i| {
+            let id = if i > 0 && rng.chance(options.duplicate_ids) {
+                rng.below(i as u64) as u32 + 1
+            } else {
+                (i + 1) as u32
+            };
+
+            UserData {
+                id,
+                balance: low + rng.below(balance_span) as u32,
+            }
+        })
+        .collect();
+
+    if options.shuffle_ids {
+        for i in (1..user_data.len()).rev() {
+            user_data.swap(i, rng.below(i as u64 + 1) as usize);
+        }
+    }
+
+    user_data
+}
+
+/// Reads users from a CSV with one `id,balance` record per line.
+///
+/// The rules are the ones of the proof of reserve app, so a dataset written for tests loads
+/// there too: the first non blank line must be the `id,balance` header, blank lines are
+/// skipped and every record has exactly two fields.
+///
+/// # Arguments
+///
+/// * `reader`: The CSV, e.g. a `BufReader` over a file.
+///
+/// # Returns
+///
+/// A `Result` containing the users in file order, an `io::Error` if reading fails or of kind
+/// `InvalidData` naming the line of a missing header or a malformed record.
+pub fn generate_from_csv<R>(reader: R) -> io::Result<Vec<UserData>>
+where
+    R: BufRead,
+{
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+    let mut user_data = Vec::new();
+    let mut header = false;
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let record = line.trim();
+        let line_number = index + 1;
+        if record.is_empty() {
+            continue;
+        }
+
+        if !header {
+            if record != "id,balance" {
+                return Err(invalid(format!(
+                    "line {line_number}: expected the header id,balance"
+                )));
+            }
+            header = true;
+            continue;
+        }
+
+        let mut fields = record.split(',');
+        let mut parse = |field: &str| {
+            fields
+                .next()
+                .and_then(|value| value.trim().parse::<u32>().ok())
+                .ok_or_else(|| invalid(format!("line {line_number}: invalid {field}")))
+        };
+        let data = UserData {
+            id: parse("id")?,
+            balance: parse("balance")?,
+        };
+
+        if fields.next().is_some() {
+            return Err(invalid(format!("line {line_number}: expected 2 fields")));
+        }
+        user_data.push(data);
+    }
+
+    if !header {
+        return Err(invalid(
+            "line 1: expected the header id,balance".to_string(),
+        ));
+    }
+
+    Ok(user_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use std::collections::HashSet;
+
+    #[test]
+    fn it_can_generate_the_same_dataset_from_a_seed() {
+        let options = DatasetOptions {
+            seed: 7,
+            balances: 100..=200,
+            ..Default::default()
+        };
+
+        let user_data = generate_random_user_data_with(1000, &options);
+        let again = generate_random_user_data_with(1000, &options);
+        let other = generate_random_user_data_with(
+            1000,
+            &DatasetOptions {
+                seed: 8,
+                ..options.clone()
+            },
+        );
+
+        let serialize =
+            |data: &[UserData]| data.iter().map(UserData::serialize).collect::<Vec<_>>();
+        assert_eq!(serialize(&user_data), serialize(&again));
+        assert_ne!(serialize(&user_data), serialize(&other));
+        assert!(user_data
+            .iter()
+            .enumerate()
+            .all(|(i, data)| data.id == i as u32 + 1 && options.balances.contains(&data.balance)));
+    }
+
+    #[test]
+    fn it_can_duplicate_and_shuffle_ids() {
+        let duplicated = generate_random_user_data_with(
+            1000,
+            &DatasetOptions {
+                duplicate_ids: 0.2,
+                ..Default::default()
+            },
+        );
+        let ids: HashSet<u32> = duplicated.iter().map(|data| data.id).collect();
+        assert!(ids.len() < 900 && ids.len() > 700);
+        assert!(ids.iter().all(|&id| (1..=1000).contains(&id)));
+
+        let shuffled = generate_random_user_data_with(
+            1000,
+            &DatasetOptions {
+                shuffle_ids: true,
+                ..Default::default()
+            },
+        );
+        let mut ids: Vec<u32> = shuffled.iter().map(|data| data.id).collect();
+        assert!(ids.windows(2).any(|pair| pair[0] > pair[1]));
+        ids.sort_unstable();
+        assert_eq!(ids, (1..=1000).collect::<Vec<_>>());
+
+        let fixed = generate_random_user_data_with(
+            3,
+            &DatasetOptions {
+                balances: u32::MAX..=u32::MAX,
+                ..Default::default()
+            },
+        );
+        assert!(fixed.iter().all(|data| data.balance == u32::MAX));
+    }
+
+    #[test]
+    #[should_panic(expected = "the balance range 200..=100 of the dataset options is empty")]
+    fn it_can_reject_an_empty_balance_range() {
+        generate_random_user_data_with(
+            1,
+            &DatasetOptions {
+                #[allow(clippy::reversed_empty_ranges)]
+                balances: 200..=100,
+                ..Default::default()
+            },
+        );
+    }
+
+    #[test]
+    fn it_can_read_users_from_csv() {
+        let user_data = generate_from_csv("id,balance\n1,1111\n\n2, 2222\n".as_bytes()).unwrap();
+        assert_eq!(
+            user_data
+                .iter()
+                .map(UserData::serialize)
+                .collect::<Vec<_>>(),
+            vec![b"1,1111".to_vec(), b"2,2222".to_vec()]
+        );
+
+        let err = generate_from_csv("id,balance\n1,1111\n2;2222\n".as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().starts_with("line 3:"));
+    }
+
+    #[rstest]
+    #[case("", "line 1: expected the header id,balance")]
+    #[case("1,1111\n", "line 1: expected the header id,balance")]
+    #[case("id,balance\n1,1111\n2,-5\n", "line 3: invalid balance")]
+    #[case("id,balance\n1,1111,7\n", "line 2: expected 2 fields")]
+    fn it_can_reject_csv_like_the_app(#[case] csv: &str, #[case] expected: &str) {
+        assert_eq!(
+            generate_from_csv(csv.as_bytes()).unwrap_err().to_string(),
+            expected
+        );
+    }
+}
@@ -0,0 +1,260 @@
+use std::collections::BTreeMap;
+
+use crate::{LeafEncode, MerkleProof, MerkleTree, MerkleTreeError};
+
+/// What is kept of a version once a newer one replaces it.
+enum Retained<T> {
+    Root(Option<String>),
+    Tree(MerkleTree<T>),
+}
+
+impl<T> Retained<T>
+where
+    T: Clone + LeafEncode,
+{
+    fn root(&self) -> Option<String> {
+        match self {
+            Retained::Root(root) => root.clone(),
+            Retained::Tree(tree) => tree.root(),
+        }
+    }
+}
+
+/// A Merkle Tree that remembers the roots of its earlier versions.
+///
+/// Every call to `update` applies a batch of changes, rebuilds the tree and starts a new
+/// version, numbered from 0 for the initial leaves. The root of every earlier version stays
+/// available through `root_at`, so an attestation can name the root that was current when
+/// it was issued. With `retain_trees` the whole tree of earlier versions is kept as well and
+/// `proof_at` still proves their leaves. Keeping a tree costs no copy, it shares its nodes
+/// with the clones handed out before, but it keeps them alive until the version is pruned.
+///
+/// Updates rebuild the tree with the domain separation and odd leaf policy of the first one.
+pub struct VersionedMerkleTree<T> {
+    tag_leaf: String,
+    tag_branch: String,
+    current: MerkleTree<T>,
+    version: u64,
+    history: BTreeMap<u64, Retained<T>>,
+    retain_trees: bool,
+}
+
+impl<T> VersionedMerkleTree<T>
+where
+    T: Clone + LeafEncode,
+{
+    /// Builds version 0 from the given user data, see `MerkleTree::build`.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_leaf`: The tag used for hashing leaf nodes.
+    /// * `tag_branch`: The tag used for hashing branch nodes.
+    /// * `input`: The user data stored in the leaves.
+    pub fn new(tag_leaf: &str, tag_branch: &str, input: &[T]) -> Self {
+        Self::from_tree(
+            tag_leaf,
+            tag_branch,
+            MerkleTree::build(tag_leaf, tag_branch, input),
+        )
+    }
+
+    /// Starts the history with an existing tree as version 0, e.g. one loaded from a snapshot.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_leaf`: The tag the tree was built with, used to rebuild it on updates.
+    /// * `tag_branch`: The tag the tree was built with, used to rebuild it on updates.
+    /// * `tree`: The tree of version 0.
+    pub fn from_tree(tag_leaf: &str, tag_branch: &str, tree: MerkleTree<T>) -> Self {
+        VersionedMerkleTree {
+            tag_leaf: tag_leaf.to_string(),
+            tag_branch: tag_branch.to_string(),
+            current: tree,
+            version: 0,
+            history: BTreeMap::new(),
+            retain_trees: false,
+        }
+    }
+
+    /// Keeps the whole tree of every version replaced from now on, not only its root, so
+    /// `proof_at` works for them.
+    pub fn retain_trees(mut self, retain_trees: bool) -> Self {
+        self.retain_trees = retain_trees;
+        self
+    }
+
+    /// Returns the current version.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Returns the tree of the current version.
+    pub fn tree(&self) -> &MerkleTree<T> {
+        &self.current
+    }
+
+    /// Returns the versions whose root is still known, oldest first, ending with the current one.
+    pub fn versions(&self) -> impl Iterator<Item = u64> + '_ {
+        self.history
+            .keys()
+            .copied()
+            .chain(std::iter::once(self.version))
+    }
+
+    /// Applies a batch of changes to the user data and rebuilds the tree as a new version.
+    ///
+    /// # Arguments
+    ///
+    /// * `change`: A function that adds, removes or updates the user data of the leaves.
+    ///
+    /// # Returns
+    ///
+    /// The new version.
+    pub fn update<F>(&mut self, change: F) -> u64
+    where
+        F: FnOnce(&mut Vec<T>),
+    {
+        let mut user_data: Vec<T> = self
+            .current
+            .leaves()
+            .map(|(_index, data, _hash)| data.clone())
+            .collect();
+        change(&mut user_data);
+
+        let rebuilt = MerkleTree::build_iter_with(
+            &self.tag_leaf,
+            &self.tag_branch,
+            user_data,
+            self.current.domain_separation(),
+            self.current.odd_leaf_policy(),
+        );
+        let replaced = std::mem::replace(&mut self.current, rebuilt);
+        let retained = if self.retain_trees {
+            Retained::Tree(replaced)
+        } else {
+            Retained::Root(replaced.root())
+        };
+
+        self.history.insert(self.version, retained);
+        self.version += 1;
+        self.version
+    }
+
+    /// Returns the root of the given version.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the root, `None` if the tree of that version was empty.
+    /// `MerkleTreeError::VersionNotFound` if the version was pruned or does not exist yet.
+    pub fn root_at(&self, version: u64) -> Result<Option<String>, MerkleTreeError> {
+        if version == self.version {
+            return Ok(self.current.root());
+        }
+
+        self.history
+            .get(&version)
+            .map(Retained::root)
+            .ok_or(MerkleTreeError::VersionNotFound { version })
+    }
+
+    /// Generates an inclusion proof for a leaf of the given version, which verifies against
+    /// the root `root_at` returns for it.
+    ///
+    /// # Arguments
+    ///
+    /// * `version`: The version whose tree the leaf is in.
+    /// * `index`: The position of the leaf, counted from the left starting at 0.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `MerkleProof`. `MerkleTreeError::VersionNotFound` if the
+    /// tree of that version is not kept, `MerkleTreeError::LeafIndexOutOfRange` if it has no
+    /// leaf at `index`.
+    pub fn proof_at(&self, version: u64, index: usize) -> Result<MerkleProof, MerkleTreeError> {
+        let tree = if version == self.version {
+            &self.current
+        } else {
+            match self.history.get(&version) {
+                Some(Retained::Tree(tree)) => tree,
+                _ => return Err(MerkleTreeError::VersionNotFound { version }),
+            }
+        };
+
+        tree.proof(index)
+    }
+
+    /// Forgets every version older than the given one. The current version is never pruned.
+    ///
+    /// # Arguments
+    ///
+    /// * `version`: The oldest version to keep.
+    pub fn prune_before(&mut self, version: u64) {
+        self.history = self.history.split_off(&version);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{generate_random_user_data, UserData};
+    use rstest::rstest;
+
+    const TAG_LEAF: &str = "ProofOfReserve_Leaf";
+    const TAG_BRANCH: &str = "ProofOfReserve_Branch";
+
+    #[rstest]
+    #[case(false)]
+    #[case(true)]
+    fn it_can_keep_the_roots_of_earlier_versions(#[case] retain_trees: bool) {
+        let user_data = generate_random_user_data(5);
+        let mut tree =
+            VersionedMerkleTree::new(TAG_LEAF, TAG_BRANCH, &user_data).retain_trees(retain_trees);
+        let first_root = tree.tree().root();
+        let first_proof = tree.proof_at(0, 3).unwrap();
+
+        assert_eq!(tree.update(|data| data[0].balance += 1), 1);
+        assert_eq!(tree.update(|data| data.push(UserData::default())), 2);
+        assert_eq!(tree.version(), 2);
+        assert_eq!(tree.tree().len(), 6);
+        assert_eq!(tree.versions().collect::<Vec<_>>(), vec![0, 1, 2]);
+
+        assert_eq!(tree.root_at(0).unwrap(), first_root);
+        assert_ne!(tree.root_at(1).unwrap(), first_root);
+        assert_eq!(tree.root_at(2).unwrap(), tree.tree().root());
+        assert_eq!(
+            tree.root_at(3),
+            Err(MerkleTreeError::VersionNotFound { version: 3 })
+        );
+
+        if retain_trees {
+            let proof = tree.proof_at(0, 3).unwrap();
+            assert_eq!(proof, first_proof);
+            assert!(proof.verify(TAG_LEAF, TAG_BRANCH, &user_data[3], &first_root.unwrap()));
+        } else {
+            assert_eq!(
+                tree.proof_at(0, 3),
+                Err(MerkleTreeError::VersionNotFound { version: 0 })
+            );
+        }
+        assert_eq!(tree.proof_at(2, 5), tree.tree().proof(5));
+    }
+
+    #[test]
+    fn it_can_prune_old_versions() {
+        let mut tree =
+            VersionedMerkleTree::new(TAG_LEAF, TAG_BRANCH, &generate_random_user_data(4))
+                .retain_trees(true);
+        for _ in 0..3 {
+            tree.update(|data| data[0].balance += 1);
+        }
+
+        tree.prune_before(2);
+        assert_eq!(tree.versions().collect::<Vec<_>>(), vec![2, 3]);
+        assert!(tree.root_at(1).is_err());
+        assert!(tree.proof_at(2, 0).is_ok());
+
+        tree.prune_before(10);
+        assert_eq!(tree.versions().collect::<Vec<_>>(), vec![3]);
+        assert_eq!(tree.root_at(3).unwrap(), tree.tree().root());
+    }
+}
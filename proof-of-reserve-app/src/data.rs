@@ -0,0 +1,144 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use rocket::serde::json;
+
+use crate::UserData;
+
+/// Loads the user balances from a dataset file.
+///
+/// The format follows the file extension:
+///
+/// * `.csv`: an `id,balance` header followed by one user per line.
+/// * `.json`: an array of `{ "id": .., "balance": .. }` objects.
+/// * `.ndjson` or `.jsonl`: one `{ "id": .., "balance": .. }` object per line.
+///
+/// # Returns
+///
+/// A `Result` containing the users in file order, or a message naming the file and the
+/// offending line if the file cannot be read, a record is invalid or an id appears twice.
+pub fn load_user_data(path: &Path) -> Result<Vec<UserData>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|err| format!("failed to read {}: {err}", path.display()))?;
+
+    let user_data = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("csv") => parse_csv(&content),
+        Some("json") => json::from_str(&content).map_err(|err| err.to_string()),
+        Some("ndjson" | "jsonl") => parse_ndjson(&content),
+        _ => Err("unknown format, expected .csv, .json, .ndjson or .jsonl".to_string()),
+    }
+    .map_err(|err| format!("failed to load {}: {err}", path.display()))?;
+
+    check_unique_ids(&user_data)
+        .map_err(|err| format!("failed to load {}: {err}", path.display()))?;
+
+    Ok(user_data)
+}
+
+/// Records that are empty after trimming, like a trailing newline, are skipped.
+fn non_empty_lines(content: &str) -> impl Iterator<Item = (usize, &str)> {
+    content
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_line_number, line)| !line.is_empty())
+}
+
+fn parse_csv(content: &str) -> Result<Vec<UserData>, String> {
+    let mut lines = non_empty_lines(content);
+
+    match lines.next() {
+        Some((_line_number, "id,balance")) => {}
+        _ => return Err("line 1: expected the header id,balance".to_string()),
+    }
+
+    lines
+        .map(|(line_number, line)| {
+            let parse = |value: Option<&str>, field: &str| {
+                value
+                    .and_then(|value| value.trim().parse::<u32>().ok())
+                    .ok_or_else(|| format!("line {line_number}: invalid {field}"))
+            };
+
+            let mut fields = line.split(',');
+            let user_data = UserData {
+                id: parse(fields.next(), "id")?,
+                balance: parse(fields.next(), "balance")?,
+            };
+
+            match fields.next() {
+                Some(_) => Err(format!("line {line_number}: expected 2 fields")),
+                None => Ok(user_data),
+            }
+        })
+        .collect()
+}
+
+fn parse_ndjson(content: &str) -> Result<Vec<UserData>, String> {
+    non_empty_lines(content)
+        .map(|(line_number, line)| {
+            json::from_str(line).map_err(|err| format!("line {line_number}: {err}"))
+        })
+        .collect()
+}
+
+fn check_unique_ids(user_data: &[UserData]) -> Result<(), String> {
+    let mut ids = HashSet::with_capacity(user_data.len());
+
+    match user_data.iter().find(|data| !ids.insert(data.id)) {
+        Some(duplicate) => Err(format!("user id {} appears more than once", duplicate.id)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(user_data: &[UserData]) -> Vec<(u32, u32)> {
+        user_data
+            .iter()
+            .map(|data| (data.id, data.balance))
+            .collect()
+    }
+
+    #[test]
+    fn it_can_parse_every_format() {
+        let expected = vec![(1, 1111), (2, 2222)];
+
+        let csv = parse_csv("id,balance\n1,1111\n2, 2222\n").unwrap();
+        let json: Vec<UserData> =
+            json::from_str(r#"[{"id":1,"balance":1111},{"id":2,"balance":2222}]"#).unwrap();
+        let ndjson =
+            parse_ndjson("{\"id\":1,\"balance\":1111}\n\n{\"id\":2,\"balance\":2222}\n").unwrap();
+
+        assert_eq!(ids(&csv), expected);
+        assert_eq!(ids(&json), expected);
+        assert_eq!(ids(&ndjson), expected);
+    }
+
+    #[test]
+    fn it_can_reject_invalid_records() {
+        assert!(parse_csv("1,1111\n").is_err());
+        assert_eq!(
+            parse_csv("id,balance\n1,1111\n2,-5\n").unwrap_err(),
+            "line 3: invalid balance"
+        );
+        assert_eq!(
+            parse_csv("id,balance\n1,1111,7\n").unwrap_err(),
+            "line 2: expected 2 fields"
+        );
+        assert!(parse_ndjson("{\"id\":1}\n").is_err());
+    }
+
+    #[test]
+    fn it_can_detect_duplicate_ids() {
+        let user_data = parse_csv("id,balance\n1,1111\n2,2222\n1,3333\n").unwrap();
+
+        assert_eq!(
+            check_unique_ids(&user_data).unwrap_err(),
+            "user id 1 appears more than once"
+        );
+        assert!(check_unique_ids(&user_data[..2]).is_ok());
+    }
+}
@@ -1,63 +1,469 @@
-use merkle_tree_lib::{self, MerkleTreeData};
-use rocket::serde::{json::Json, Serialize};
-use rocket::State;
+use merkle_tree_lib::{
+    self, BuildProgress, LeafEncode, MerkleProof, MerkleTree, NodeLabel, Salted, SnapshotData,
+};
+use rocket::http::Status;
+use rocket::response::stream::{Event, EventStream};
+use rocket::response::{self, Responder};
+use rocket::serde::{json::Json, Deserialize, Serialize};
+use rocket::tokio::select;
+use rocket::tokio::sync::watch;
+use rocket::{Request, Shutdown, State};
+use std::ops::ControlFlow;
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+mod data;
 
 #[macro_use]
 extern crate rocket;
 
+/// The errors returned by the routes, sent as an `ErrorBody` with the status code of the
+/// variant.
+#[derive(Debug)]
+enum ApiError {
+    BadRequest(String),
+    NotFound(String),
+    Conflict(String),
+    Internal(String),
+}
+
+/// The JSON body of every error response.
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct ErrorBody {
+    error: String,
+}
+
+impl<'r> Responder<'r, 'static> for ApiError {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let (status, error) = match self {
+            ApiError::BadRequest(error) => (Status::BadRequest, error),
+            ApiError::NotFound(error) => (Status::NotFound, error),
+            ApiError::Conflict(error) => (Status::Conflict, error),
+            ApiError::Internal(error) => (Status::InternalServerError, error),
+        };
+
+        (status, Json(ErrorBody { error })).respond_to(request)
+    }
+}
+
+/// Answers the requests no route handles, e.g. unknown paths or bodies that are not valid
+/// JSON, with the same error body as the routes.
+#[catch(default)]
+fn default_catcher(status: Status, _request: &Request) -> (Status, Json<ErrorBody>) {
+    let error = status.reason().unwrap_or("unknown error").to_lowercase();
+
+    (status, Json(ErrorBody { error }))
+}
+
+fn parse_user_id(user_id: &str) -> Result<u32, ApiError> {
+    user_id
+        .parse::<u32>()
+        .map_err(|_| ApiError::BadRequest(format!("user id {user_id} is not a number")))
+}
+
 #[get("/proof")]
-fn proof_all_users(state: &State<AppState>) -> String {
-    state.tree.root().unwrap()
+fn proof_all_users(state: &State<AppState>) -> Result<Json<TreeRoot>, ApiError> {
+    let current = state.root_updates.borrow().clone();
+    if current.root.is_none() {
+        return Err(ApiError::NotFound("tree is empty".to_string()));
+    }
+
+    Ok(Json(current))
 }
 
 #[get("/proof/mermaid")]
 fn proof_all_users_display_mermaid_diagram(state: &State<AppState>) -> String {
-    state.tree.display_mermaid_diagram()
+    state.tree().tree().display_mermaid_diagram()
+}
+
+/// The merkle root the server serves, returned by `/proof` and the user updates and pushed
+/// to the subscribers of `/proof/stream`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct TreeRoot {
+    root: Option<String>,
+    /// The number of users in the tree.
+    tree_size: usize,
+    /// The number of updates applied since the server started.
+    version: u64,
+    /// When the tree was built, in seconds since the Unix epoch.
+    timestamp: u64,
+}
+
+impl TreeRoot {
+    fn new(tree: &MerkleTree<UserLeaf>, version: u64) -> Self {
+        TreeRoot {
+            root: tree.root(),
+            tree_size: tree.len(),
+            version,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Streams the merkle root as server-sent events, so dashboards do not have to poll `/proof`.
+///
+/// The current root is sent as soon as a client subscribes, then again after every update.
+/// A client that falls behind only gets the latest root.
+#[get("/proof/stream")]
+fn proof_stream(state: &State<AppState>, mut shutdown: Shutdown) -> EventStream![] {
+    let mut updates = state.root_updates.subscribe();
+
+    EventStream! {
+        loop {
+            let update = updates.borrow_and_update().clone();
+            yield Event::json(&update).event("root");
+
+            select! {
+                changed = updates.changed() => if changed.is_err() { break },
+                _ = &mut shutdown => break,
+            }
+        }
+    }
 }
 
 #[derive(Serialize)]
 #[serde(crate = "rocket::serde")]
-struct MerkleProof {
+struct UserProof {
     user_balance: u32,
-    proof: Vec<(String, u8)>,
+    /// The hex encoded salt of the leaf, needed to verify the proof.
+    salt: String,
+    /// The sibling hashes from the leaf up to the root, accepted as is by `/verify`.
+    proof: MerkleProof,
 }
 
 #[get("/proof/<user_id>")]
-fn proof_by_user_id(state: &State<AppState>, user_id: &str) -> Json<MerkleProof> {
-    let (node, path) = state
-        .tree
-        .search_with_path(|user_data| user_data.id == user_id.parse::<u32>().unwrap())
-        .unwrap();
-
-    Json(MerkleProof {
-        user_balance: node.user_data.as_ref().unwrap().balance,
-        proof: path.to_vec(),
+fn proof_by_user_id(state: &State<AppState>, user_id: &str) -> Result<Json<UserProof>, ApiError> {
+    let user_id = parse_user_id(user_id)?;
+
+    let tree = state.tree();
+    let leaf = tree
+        .search_with_path(&user_id)
+        .ok_or_else(|| ApiError::NotFound(format!("user {user_id} not found")))?;
+    let proof = tree
+        .proof_for_key(&user_id)
+        .map_err(|err| ApiError::Internal(err.to_string()))?;
+
+    Ok(Json(UserProof {
+        user_balance: leaf.data().data().balance,
+        salt: hex::encode(leaf.data().salt()),
+        proof,
+    }))
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct VerifyRequest {
+    leaf_data: UserData,
+    /// The hex encoded salt returned together with the proof.
+    salt: String,
+    proof: MerkleProof,
+    root: String,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct VerifyResponse {
+    /// The proof links the leaf data to the submitted root.
+    valid: bool,
+    /// The submitted root is the root the server currently serves.
+    current_root: bool,
+}
+
+/// Verifies a proof submitted by a client or an auditor.
+///
+/// A proof is only reported valid if it checks out against the server's current root.
+#[post("/verify", data = "<request>")]
+fn verify_proof(
+    state: &State<AppState>,
+    request: Json<VerifyRequest>,
+) -> Result<Json<VerifyResponse>, ApiError> {
+    let salt = hex::decode(&request.salt)
+        .ok()
+        .and_then(|salt| salt.try_into().ok())
+        .ok_or_else(|| ApiError::BadRequest("salt is not 32 hex encoded bytes".to_string()))?;
+    let leaf = Salted::new(salt, request.leaf_data.clone());
+    let current_root = state.tree().tree().root().as_deref() == Some(request.root.as_str());
+
+    Ok(Json(VerifyResponse {
+        valid: current_root
+            && request
+                .proof
+                .verify(TAG_LEAF, TAG_BRANCH, &leaf, &request.root),
+        current_root,
+    }))
+}
+
+/// Adds a user and returns the new merkle root.
+#[post("/users", data = "<user>")]
+fn add_user(state: &State<AppState>, user: Json<UserData>) -> Result<Json<TreeRoot>, ApiError> {
+    state.update(|user_data, salt_key| {
+        if user_data.iter().any(|data| data.data().id == user.id) {
+            return Err(ApiError::Conflict(format!(
+                "user {} already exists",
+                user.id
+            )));
+        }
+
+        user_data.push(Salted::with_key(salt_key, user.into_inner()));
+        Ok(())
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct BalanceUpdate {
+    balance: u32,
+}
+
+/// Updates the balance of a user and returns the new merkle root.
+#[put("/users/<user_id>", data = "<update>")]
+fn update_user(
+    state: &State<AppState>,
+    user_id: &str,
+    update: Json<BalanceUpdate>,
+) -> Result<Json<TreeRoot>, ApiError> {
+    let user_id = parse_user_id(user_id)?;
+
+    state.update(|user_data, salt_key| {
+        let data = user_data
+            .iter_mut()
+            .find(|data| data.data().id == user_id)
+            .ok_or_else(|| ApiError::NotFound(format!("user {user_id} not found")))?;
+
+        let user = UserData {
+            id: user_id,
+            balance: update.balance,
+        };
+        *data = Salted::with_key(salt_key, user);
+        Ok(())
     })
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(crate = "rocket::serde")]
 struct UserData {
     id: u32,
     balance: u32,
 }
 
-impl MerkleTreeData for UserData {
+impl LeafEncode for UserData {
     fn serialize(&self) -> Vec<u8> {
         format!("{},{}", self.id, self.balance).as_bytes().to_vec()
     }
+}
 
+impl NodeLabel for UserData {
     fn mermaid_node_label(&self) -> String {
         format!("<br>User ID: {}<br>Balance: {}", self.id, self.balance)
     }
 }
 
+impl SnapshotData for UserData {
+    fn deserialize(bytes: &[u8]) -> Option<Self> {
+        let (id, balance) = std::str::from_utf8(bytes).ok()?.split_once(',')?;
+
+        Some(UserData {
+            id: id.parse().ok()?,
+            balance: balance.parse().ok()?,
+        })
+    }
+}
+
+const TAG_LEAF: &str = "ProofOfReserve_Leaf";
+const TAG_BRANCH: &str = "ProofOfReserve_Branch";
+
+/// Environment variable pointing to a binary snapshot of the tree.
+///
+/// When the file exists the tree is loaded from it instead of being built, otherwise the
+/// tree is built and the snapshot is written there for the next start.
+const SNAPSHOT_PATH_ENV: &str = "PROOF_OF_RESERVE_SNAPSHOT";
+
+/// Environment variable holding the secret key the salts of the leaves are derived from.
+///
+/// Without the salts the `id,balance` behind a published leaf hash could be brute forced.
+/// When it is not set a random key is used, so the root changes with every restart unless
+/// the tree is loaded from a snapshot, which stores the salts.
+const SALT_KEY_ENV: &str = "PROOF_OF_RESERVE_SALT_KEY";
+
+fn salt_key() -> Vec<u8> {
+    match std::env::var(SALT_KEY_ENV) {
+        Ok(key) => key.into_bytes(),
+        Err(_) => {
+            eprintln!("{SALT_KEY_ENV} is not set, salting the leaves with a random key");
+            rand::random::<[u8; 32]>().to_vec()
+        }
+    }
+}
+
+type UserLeaf = Salted<UserData>;
+
+fn save_snapshot(path: &str, tree: &MerkleTree<UserLeaf>) -> Result<(), String> {
+    std::fs::write(path, tree.to_bytes())
+        .map_err(|err| format!("failed to write snapshot {path}: {err}"))
+}
+
+fn write_snapshot(tree: &MerkleTree<UserLeaf>) -> Result<(), String> {
+    match std::env::var(SNAPSHOT_PATH_ENV) {
+        Ok(path) => save_snapshot(&path, tree),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Builds the tree, logging every tenth of the leaves hashed so that starting on a large
+/// dataset does not look stuck.
+fn build_tree(
+    tag_leaf: &str,
+    tag_branch: &str,
+    user_data: &[UserLeaf],
+) -> Result<MerkleTree<UserLeaf>, String> {
+    let tenths = |hashed: usize| hashed * 10 / user_data.len().max(1);
+    let mut logged = 0;
+
+    MerkleTree::builder(tag_leaf, tag_branch)
+        .build_with_progress(user_data.iter().cloned(), |progress| {
+            if let BuildProgress::LeavesHashed { hashed } = progress {
+                if tenths(hashed) > logged {
+                    logged = tenths(hashed);
+                    eprintln!("hashed {hashed} of {} leaves", user_data.len());
+                }
+            }
+            ControlFlow::Continue(())
+        })
+        .map_err(|err| format!("failed to build the tree: {err}"))
+}
+
+/// Loads the tree from the snapshot at `snapshot`, or builds it from `user_data` and writes
+/// the snapshot there when the file does not exist yet.
+///
+/// `from_bytes` takes the stored hashes as they are, so a loaded snapshot is audited before
+/// it is served. When the user data comes from a data file as well, the snapshot must have
+/// the root that data builds, a stale snapshot never silently wins over the file.
+fn load_or_build_tree(
+    tag_leaf: &str,
+    tag_branch: &str,
+    snapshot: Option<&str>,
+    user_data: &[UserLeaf],
+    from_data_file: bool,
+) -> Result<MerkleTree<UserLeaf>, String> {
+    let Some(path) = snapshot else {
+        return build_tree(tag_leaf, tag_branch, user_data);
+    };
+
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            let tree = build_tree(tag_leaf, tag_branch, user_data)?;
+            save_snapshot(path, &tree)?;
+            return Ok(tree);
+        }
+        Err(err) => return Err(format!("failed to read snapshot {path}: {err}")),
+    };
+
+    let tree: MerkleTree<UserLeaf> = MerkleTree::from_bytes(&bytes)
+        .map_err(|err| format!("failed to load snapshot {path}: {err}"))?;
+    tree.audit(tag_leaf, tag_branch)
+        .map_err(|err| format!("snapshot {path} is corrupt: {err}"))?;
+
+    if from_data_file {
+        let built = build_tree(tag_leaf, tag_branch, user_data)?;
+        if built.root() != tree.root() {
+            return Err(format!(
+                "snapshot {path} has root {}, the data of {DATA_PATH_ENV} builds root {}. \
+                 Remove the snapshot to rebuild it, or set {SALT_KEY_ENV} to the key it was \
+                 salted with",
+                tree.root().unwrap_or_default(),
+                built.root().unwrap_or_default(),
+            ));
+        }
+    }
+
+    Ok(tree)
+}
+
+type UserTree = merkle_tree_lib::KeyedMerkleTree<UserLeaf, u32>;
+
 struct AppState {
-    tree: merkle_tree_lib::MerkleTree<UserData>,
+    tree: RwLock<Arc<UserTree>>,
+    /// Held by `update` for the whole rebuild, so concurrent updates never overwrite each other.
+    update_lock: Mutex<()>,
+    salt_key: Vec<u8>,
+    /// The root after the latest update, watched by the clients of `/proof/stream`.
+    root_updates: watch::Sender<TreeRoot>,
 }
 
-#[launch]
-fn rocket() -> _ {
-    let user_data = vec![
+impl AppState {
+    fn new(tree: MerkleTree<UserLeaf>, salt_key: Vec<u8>) -> Self {
+        let (root_updates, _) = watch::channel(TreeRoot::new(&tree, 0));
+
+        AppState {
+            tree: RwLock::new(Arc::new(UserTree::from_tree(tree, |data| data.data().id))),
+            update_lock: Mutex::new(()),
+            salt_key,
+            root_updates,
+        }
+    }
+
+    /// Returns the current tree. It stays valid for as long as the caller needs it, even
+    /// if `update` swaps in a new tree meanwhile.
+    fn tree(&self) -> Arc<UserTree> {
+        Arc::clone(&self.tree.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Applies a change to the user data and swaps in a tree rebuilt from the result.
+    ///
+    /// The change gets the salt key to salt the users it adds or updates.
+    ///
+    /// The tree is rebuilt while requests keep reading the old one, the lock on the current
+    /// tree is only taken to swap the new one in. When a snapshot is configured it is
+    /// rewritten, otherwise a restart would bring back the old balances. The new root is
+    /// then pushed to the clients of `/proof/stream`.
+    ///
+    /// # Returns
+    ///
+    /// The new merkle root, or the error returned by `change`.
+    fn update<F>(&self, change: F) -> Result<Json<TreeRoot>, ApiError>
+    where
+        F: FnOnce(&mut Vec<UserLeaf>, &[u8]) -> Result<(), ApiError>,
+    {
+        let _update = self
+            .update_lock
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        let mut user_data: Vec<UserLeaf> = self
+            .tree()
+            .tree()
+            .leaves()
+            .map(|(_index, data, _hash)| data.clone())
+            .collect();
+        change(&mut user_data, &self.salt_key)?;
+
+        let rebuilt = MerkleTree::build_from_iter(TAG_LEAF, TAG_BRANCH, user_data);
+        write_snapshot(&rebuilt).map_err(ApiError::Internal)?;
+        let version = self.root_updates.borrow().version + 1;
+        let root = TreeRoot::new(&rebuilt, version);
+
+        let rebuilt = Arc::new(UserTree::from_tree(rebuilt, |data| data.data().id));
+        *self.tree.write().unwrap_or_else(PoisonError::into_inner) = rebuilt;
+        self.root_updates.send_replace(root.clone());
+
+        Ok(Json(root))
+    }
+}
+
+/// Environment variable pointing to a CSV, JSON or NDJSON file with the user balances.
+///
+/// Without it the server starts with a small built in sample dataset.
+const DATA_PATH_ENV: &str = "PROOF_OF_RESERVE_DATA";
+
+fn sample_user_data() -> Vec<UserData> {
+    vec![
         (1, 1111),
         (2, 2222),
         (3, 3333),
@@ -69,19 +475,279 @@ fn rocket() -> _ {
     ]
     .into_iter()
     .map(|(id, balance)| UserData { id, balance })
-    .collect();
+    .collect()
+}
+
+fn exit_with_error<T>(err: String) -> T {
+    eprintln!("{err}");
+    std::process::exit(1)
+}
 
-    let tag_leaf = "ProofOfReserve_Leaf";
-    let tag_branch = "ProofOfReserve_Branch";
+#[launch]
+fn rocket() -> _ {
+    let data_path = std::env::var(DATA_PATH_ENV).ok();
+    let user_data = match &data_path {
+        Some(path) => data::load_user_data(Path::new(path)).unwrap_or_else(exit_with_error),
+        None => sample_user_data(),
+    };
 
-    let tree = merkle_tree_lib::MerkleTree::build(tag_leaf, tag_branch, &user_data);
+    let salt_key = salt_key();
+    let user_data: Vec<UserLeaf> = user_data
+        .into_iter()
+        .map(|data| Salted::with_key(&salt_key, data))
+        .collect();
 
-    rocket::build().manage(AppState { tree }).mount(
-        "/",
-        routes![
-            proof_all_users,
-            proof_all_users_display_mermaid_diagram,
-            proof_by_user_id
-        ],
+    let snapshot = std::env::var(SNAPSHOT_PATH_ENV).ok();
+    let tree = load_or_build_tree(
+        TAG_LEAF,
+        TAG_BRANCH,
+        snapshot.as_deref(),
+        &user_data,
+        data_path.is_some(),
     )
+    .unwrap_or_else(exit_with_error);
+
+    rocket::build()
+        .manage(AppState::new(tree, salt_key))
+        .mount(
+            "/",
+            routes![
+                proof_all_users,
+                proof_all_users_display_mermaid_diagram,
+                proof_stream,
+                proof_by_user_id,
+                verify_proof,
+                add_user,
+                update_user
+            ],
+        )
+        .register("/", catchers![default_catcher])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::http::ContentType;
+    use rocket::local::asynchronous::Client;
+    use rocket::serde::json::{json, Value};
+
+    fn salted(user_data: Vec<UserData>) -> Vec<UserLeaf> {
+        user_data
+            .into_iter()
+            .map(|data| Salted::with_key(b"test key", data))
+            .collect()
+    }
+
+    #[test]
+    fn it_can_check_a_loaded_snapshot() {
+        let path = std::env::temp_dir().join(format!("app-snapshot-{}.bin", std::process::id()));
+        let snapshot = path.to_str();
+        let user_data = salted(sample_user_data());
+        let load = |user_data: &[UserLeaf], from_data_file| {
+            load_or_build_tree(TAG_LEAF, TAG_BRANCH, snapshot, user_data, from_data_file)
+        };
+
+        let built = load(&user_data, true).unwrap();
+        assert!(path.exists());
+        assert_eq!(load(&user_data, true).unwrap().root(), built.root());
+
+        let mut changed = sample_user_data();
+        changed[0].balance += 1;
+        let changed = salted(changed);
+        let err = load(&changed, true).err().unwrap();
+        assert!(err.contains(&format!("has root {}", built.root().unwrap())));
+        // without a data file the snapshot is the source of the user data
+        assert_eq!(load(&changed, false).unwrap().root(), built.root());
+
+        // flips a bit of the root hash, the last 32 bytes of the snapshot
+        let mut bytes = std::fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        std::fs::write(&path, bytes).unwrap();
+        let err = load(&user_data, false).err().unwrap();
+        assert!(err.contains("is corrupt"));
+        std::fs::remove_file(&path).unwrap();
+
+        // only a missing snapshot is built, a directory is reported and left alone
+        let dir = std::env::temp_dir();
+        let err = load_or_build_tree(TAG_LEAF, TAG_BRANCH, dir.to_str(), &user_data, true)
+            .err()
+            .unwrap();
+        assert!(err.starts_with("failed to read snapshot"));
+        assert!(dir.is_dir());
+    }
+
+    #[rocket::async_test]
+    async fn it_can_stream_the_root_after_an_update() {
+        let client = Client::tracked(rocket()).await.unwrap();
+        let stream = client.get("/proof/stream").dispatch().await;
+
+        let added: Value = client
+            .post("/users")
+            .json(&json!({ "id": 9, "balance": 9999 }))
+            .dispatch()
+            .await
+            .into_json()
+            .await
+            .unwrap();
+        client.rocket().shutdown().notify();
+
+        let events = stream.into_string().await.unwrap();
+        assert!(events.starts_with("event:root\n"));
+        assert!(events.contains(&format!(
+            "\"root\":\"{}\",\"tree_size\":9,\"version\":1,",
+            added["root"].as_str().unwrap()
+        )));
+    }
+
+    #[rocket::async_test]
+    async fn it_can_return_the_root_as_json() {
+        let client = Client::tracked(rocket()).await.unwrap();
+
+        let response = client.get("/proof").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        let root: Value = response.into_json().await.unwrap();
+
+        assert_eq!(root["root"].as_str().map(str::len), Some(64));
+        assert_eq!(root["tree_size"], 8);
+        assert_eq!(root["version"], 0);
+        assert!(root["timestamp"]
+            .as_u64()
+            .is_some_and(|timestamp| timestamp > 0));
+    }
+
+    #[rocket::async_test]
+    async fn it_can_return_json_errors() {
+        let client = Client::tracked(rocket()).await.unwrap();
+
+        for (uri, status, error) in [
+            ("/proof/42", Status::NotFound, "user 42 not found"),
+            (
+                "/proof/abc",
+                Status::BadRequest,
+                "user id abc is not a number",
+            ),
+            ("/unknown", Status::NotFound, "not found"),
+        ] {
+            let response = client.get(uri).dispatch().await;
+            assert_eq!(response.status(), status);
+            let body: Value = response.into_json().await.unwrap();
+            assert_eq!(body, json!({ "error": error }));
+        }
+
+        let response = client
+            .post("/users")
+            .header(ContentType::JSON)
+            .body("{ \"id\": ")
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::BadRequest);
+        assert!(response.into_json::<Value>().await.unwrap()["error"].is_string());
+    }
+
+    /// Fetches the proof of user 3 and posts it to `/verify` with the given leaf data and salt,
+    /// `None` keeping the salt of the proof.
+    async fn verify_user_3(client: &Client, balance: u32, salt: Option<String>) -> Value {
+        let proof: Value = client
+            .get("/proof/3")
+            .dispatch()
+            .await
+            .into_json()
+            .await
+            .unwrap();
+        let root: Value = client
+            .get("/proof")
+            .dispatch()
+            .await
+            .into_json()
+            .await
+            .unwrap();
+
+        let response = client
+            .post("/verify")
+            .json(&json!({
+                "leaf_data": { "id": 3, "balance": balance },
+                "salt": salt.unwrap_or_else(|| proof["salt"].as_str().unwrap().to_string()),
+                "proof": proof["proof"],
+                "root": root["root"],
+            }))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        response.into_json().await.unwrap()
+    }
+
+    #[rocket::async_test]
+    async fn it_can_verify_a_served_proof() {
+        let client = Client::tracked(rocket()).await.unwrap();
+
+        assert_eq!(
+            verify_user_3(&client, 3333, None).await,
+            json!({ "valid": true, "current_root": true })
+        );
+        assert_eq!(
+            verify_user_3(&client, 3334, None).await,
+            json!({ "valid": false, "current_root": true })
+        );
+        assert_eq!(
+            verify_user_3(&client, 3333, Some("00".repeat(32))).await,
+            json!({ "valid": false, "current_root": true })
+        );
+    }
+
+    #[rocket::async_test]
+    async fn it_can_add_and_update_users() {
+        let client = Client::tracked(rocket()).await.unwrap();
+
+        let response = client
+            .post("/users")
+            .json(&json!({ "id": 9, "balance": 9999 }))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let added: Value = response.into_json().await.unwrap();
+        assert_eq!(added["tree_size"], 9);
+        assert_eq!(added["version"], 1);
+
+        let response = client
+            .post("/users")
+            .json(&json!({ "id": 9, "balance": 1 }))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Conflict);
+
+        let response = client
+            .put("/users/9")
+            .json(&json!({ "balance": 1 }))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let updated: Value = response.into_json().await.unwrap();
+        assert_eq!(updated["version"], 2);
+        assert_ne!(updated["root"], added["root"]);
+
+        let response = client
+            .put("/users/99")
+            .json(&json!({ "balance": 1 }))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+
+        let proof: Value = client
+            .get("/proof/9")
+            .dispatch()
+            .await
+            .into_json()
+            .await
+            .unwrap();
+        assert_eq!(proof["user_balance"], 1);
+        let current: Value = client
+            .get("/proof")
+            .dispatch()
+            .await
+            .into_json()
+            .await
+            .unwrap();
+        assert_eq!(current, updated);
+    }
 }
@@ -0,0 +1,278 @@
+#!/usr/bin/env python3
+"""Prints the public API of a crate from its rustdoc JSON, one item per line, sorted.
+
+Generate the JSON with a nightly toolchain, then compare the output with the checked in
+snapshot so that any added, removed or changed public item shows up in review:
+
+    cargo +nightly rustdoc -p merkle-tree-lib --all-features -- \
+        -Z unstable-options --output-format json
+    python3 scripts/public_api.py target/doc/merkle_tree_lib.json > merkle-tree-lib/public-api.txt
+
+Items hidden with `#[doc(hidden)]` are left out by rustdoc and therefore not part of the API.
+"""
+
+import json
+import sys
+
+
+def render_generic_args(args):
+    if not args:
+        return ""
+    if "angle_bracketed" in args:
+        parts = []
+        for arg in args["angle_bracketed"]["args"]:
+            if "type" in arg:
+                parts.append(render_type(arg["type"]))
+            elif "lifetime" in arg:
+                parts.append(arg["lifetime"])
+            elif "const" in arg:
+                parts.append(arg["const"].get("expr", "_"))
+        for constraint in args["angle_bracketed"].get("constraints", []):
+            binding = constraint["binding"]
+            if "equality" in binding:
+                parts.append(f"{constraint['name']} = {render_term(binding['equality'])}")
+            else:
+                parts.append(f"{constraint['name']}: {render_bounds(binding['constraint'])}")
+        return f"<{', '.join(parts)}>" if parts else ""
+    if "parenthesized" in args:
+        inputs = ", ".join(render_type(t) for t in args["parenthesized"]["inputs"])
+        output = args["parenthesized"].get("output")
+        return f"({inputs})" + (f" -> {render_type(output)}" if output else "")
+    return ""
+
+
+def render_term(term):
+    return render_type(term["type"]) if "type" in term else term.get("constant", {}).get("expr", "_")
+
+
+def render_path(path):
+    return path["path"] + render_generic_args(path.get("args"))
+
+
+def render_bounds(bounds):
+    rendered = []
+    for bound in bounds:
+        if "trait_bound" in bound:
+            modifier = "?" if bound["trait_bound"].get("modifier") == "maybe" else ""
+            rendered.append(modifier + render_path(bound["trait_bound"]["trait"]))
+        elif "outlives" in bound:
+            rendered.append(bound["outlives"])
+        elif "use" in bound:
+            rendered.append("use<..>")
+    return " + ".join(rendered)
+
+
+def render_type(ty):
+    if ty is None:
+        return "()"
+    kind, value = next(iter(ty.items()))
+    if kind == "resolved_path":
+        return render_path(value)
+    if kind in ("generic", "primitive"):
+        return value
+    if kind == "borrowed_ref":
+        lifetime = f"{value['lifetime']} " if value.get("lifetime") else ""
+        mutable = "mut " if value["is_mutable"] else ""
+        return f"&{lifetime}{mutable}{render_type(value['type'])}"
+    if kind == "raw_pointer":
+        mutable = "mut" if value["is_mutable"] else "const"
+        return f"*{mutable} {render_type(value['type'])}"
+    if kind == "slice":
+        return f"[{render_type(value)}]"
+    if kind == "array":
+        return f"[{render_type(value['type'])}; {value['len']}]"
+    if kind == "tuple":
+        return f"({', '.join(render_type(t) for t in value)})"
+    if kind == "impl_trait":
+        return f"impl {render_bounds(value)}"
+    if kind == "dyn_trait":
+        traits = " + ".join(render_path(t["trait"]) for t in value["traits"])
+        return f"dyn {traits}"
+    if kind == "qualified_path":
+        trait = f" as {render_path(value['trait'])}" if value.get("trait") else ""
+        return f"<{render_type(value['self_type'])}{trait}>::{value['name']}"
+    if kind == "function_pointer":
+        inputs = ", ".join(render_type(t) for _, t in value["sig"]["inputs"])
+        output = value["sig"].get("output")
+        return f"fn({inputs})" + (f" -> {render_type(output)}" if output else "")
+    return kind
+
+
+def render_generics(generics):
+    params = []
+    for param in generics["params"]:
+        kind = param["kind"]
+        if "lifetime" in kind:
+            params.append(param["name"])
+        elif "type" in kind:
+            if kind["type"].get("is_synthetic"):
+                continue
+            bounds = render_bounds(kind["type"]["bounds"])
+            params.append(param["name"] + (f": {bounds}" if bounds else ""))
+        elif "const" in kind:
+            params.append(f"const {param['name']}: {render_type(kind['const']['type'])}")
+    where = []
+    for predicate in generics["where_predicates"]:
+        if "bound_predicate" in predicate:
+            bound = predicate["bound_predicate"]
+            where.append(f"{render_type(bound['type'])}: {render_bounds(bound['bounds'])}")
+    rendered = f"<{', '.join(params)}>" if params else ""
+    return rendered, (f" where {', '.join(where)}" if where else "")
+
+
+def render_function(name, function):
+    params, where = render_generics(function["generics"])
+    inputs = []
+    for arg, ty in function["sig"]["inputs"]:
+        if arg == "self":
+            inputs.append(render_type(ty).replace("Self", "self") if ty != {"generic": "Self"} else "self")
+        else:
+            inputs.append(f"{arg}: {render_type(ty)}")
+    output = function["sig"].get("output")
+    returns = f" -> {render_type(output)}" if output else ""
+    header = function["header"]
+    prefix = ("const " if header["is_const"] else "") + ("unsafe " if header["is_unsafe"] else "")
+    abi = header["abi"]
+    if isinstance(abi, dict):
+        prefix += f'extern "{next(iter(abi))}" '
+    return f"{prefix}fn {name}{params}({', '.join(inputs)}){returns}{where}"
+
+
+class Api:
+    def __init__(self, doc):
+        self.index = doc["index"]
+        self.lines = set()
+        self.visited = set()
+
+    def item(self, item_id):
+        return self.index.get(str(item_id))
+
+    def add(self, line):
+        self.lines.add(line)
+
+    def walk_module(self, path, module):
+        for item_id in module["items"]:
+            item = self.item(item_id)
+            if item is None or item["visibility"] != "public":
+                continue
+            kind, inner = next(iter(item["inner"].items()))
+            if kind == "use":
+                target = self.item(inner["id"]) if inner.get("id") is not None else None
+                if inner["is_glob"] or target is None:
+                    self.add(f"pub use {path}::{inner['name']} = {inner['source']}")
+                    continue
+                self.walk_item(f"{path}::{inner['name']}", target)
+            else:
+                self.walk_item(f"{path}::{item['name']}", item)
+
+    def walk_item(self, path, item):
+        kind, inner = next(iter(item["inner"].items()))
+        if kind == "module":
+            self.add(f"pub mod {path}")
+            if (path, item["id"]) not in self.visited:
+                self.visited.add((path, item["id"]))
+                self.walk_module(path, inner)
+        elif kind == "function":
+            self.add(f"pub {render_function(path, inner)}")
+        elif kind == "constant":
+            self.add(f"pub const {path}: {render_type(inner['type'])}")
+        elif kind == "static":
+            self.add(f"pub static {path}: {render_type(inner['type'])}")
+        elif kind == "type_alias":
+            params, _ = render_generics(inner["generics"])
+            self.add(f"pub type {path}{params} = {render_type(inner['type'])}")
+        elif kind == "struct":
+            params, where = render_generics(inner["generics"])
+            self.add(f"pub struct {path}{params}{where}")
+            struct_kind = inner["kind"]
+            fields = []
+            if isinstance(struct_kind, dict):
+                fields = struct_kind.get("plain", {}).get("fields", []) or [
+                    field for field in struct_kind.get("tuple", []) if field is not None
+                ]
+            for field_id in fields:
+                field = self.item(field_id)
+                if field and field["visibility"] == "public":
+                    ty = render_type(field["inner"]["struct_field"])
+                    self.add(f"pub {path}::{field['name']}: {ty}")
+            self.walk_impls(path, inner["impls"])
+        elif kind == "enum":
+            params, where = render_generics(inner["generics"])
+            self.add(f"pub enum {path}{params}{where}")
+            for variant_id in inner["variants"]:
+                variant = self.item(variant_id)
+                variant_kind = variant["inner"]["variant"]["kind"]
+                fields = []
+                if isinstance(variant_kind, dict) and "struct" in variant_kind:
+                    fields = [
+                        f"{self.item(f)['name']}: {render_type(self.item(f)['inner']['struct_field'])}"
+                        for f in variant_kind["struct"]["fields"]
+                    ]
+                    self.add(f"pub {path}::{variant['name']} {{ {', '.join(fields)} }}")
+                elif isinstance(variant_kind, dict) and "tuple" in variant_kind:
+                    fields = [
+                        render_type(self.item(f)["inner"]["struct_field"])
+                        for f in variant_kind["tuple"]
+                        if f is not None
+                    ]
+                    self.add(f"pub {path}::{variant['name']}({', '.join(fields)})")
+                else:
+                    self.add(f"pub {path}::{variant['name']}")
+            self.walk_impls(path, inner["impls"])
+        elif kind == "trait":
+            params, where = render_generics(inner["generics"])
+            bounds = render_bounds(inner["bounds"])
+            self.add(f"pub trait {path}{params}" + (f": {bounds}" if bounds else "") + where)
+            for member_id in inner["items"]:
+                member = self.item(member_id)
+                member_kind, member_inner = next(iter(member["inner"].items()))
+                member_path = f"{path}::{member['name']}"
+                if member_kind == "function":
+                    self.add(f"pub {render_function(member_path, member_inner)}")
+                elif member_kind == "assoc_type":
+                    self.add(f"pub type {member_path}")
+                elif member_kind == "assoc_const":
+                    self.add(f"pub const {member_path}")
+        else:
+            self.add(f"pub {kind} {path}")
+
+    def walk_impls(self, path, impl_ids):
+        for impl_id in impl_ids:
+            impl_item = self.item(impl_id)
+            if impl_item is None:
+                continue
+            impl = impl_item["inner"]["impl"]
+            if impl["is_synthetic"] or impl["blanket_impl"] is not None:
+                continue
+            params, where = render_generics(impl["generics"])
+            target = render_type(impl["for"])
+            if impl["trait"] is not None:
+                negative = "!" if impl["is_negative"] else ""
+                trait = render_path(impl["trait"])
+                self.add(f"impl{params} {negative}{trait} for {target}{where}")
+                continue
+            for member_id in impl["items"]:
+                member = self.item(member_id)
+                if member is None or member["visibility"] != "public":
+                    continue
+                member_kind, member_inner = next(iter(member["inner"].items()))
+                if member_kind == "function":
+                    function = render_function(f"{path}::{member['name']}", member_inner)
+                    self.add(f"pub {function}" + (f" (impl{params}{where})" if params else ""))
+                elif member_kind == "assoc_const":
+                    self.add(f"pub const {path}::{member['name']}")
+
+
+def main():
+    with open(sys.argv[1]) as file:
+        doc = json.load(file)
+
+    api = Api(doc)
+    root = api.item(doc["root"])
+    api.walk_module(root["name"], root["inner"]["module"])
+    for line in sorted(api.lines):
+        print(line)
+
+
+if __name__ == "__main__":
+    main()